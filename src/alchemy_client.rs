@@ -1,10 +1,114 @@
-use bitcoin::{Amount, Transaction, Txid};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bitcoin::{
+    Address, Amount, FeeRate, Network, OutPoint, ScriptBuf, Sequence, Transaction, Txid, Wtxid,
+};
 use serde_json::{Value, json};
+use tokio_util::sync::CancellationToken;
+
+use crate::amount::btc_string_to_sat;
+use crate::fees::actual_fee_rate;
+use crate::rune_decode::RuneId;
+use crate::transactions::parse_address;
+
+/// sendrawtransaction 默认的最大费率：0.1 BTC/kvB
+pub const DEFAULT_MAX_FEE_RATE: FeeRate = FeeRate::from_sat_per_kwu(2_500_000);
 
 /// Alchemy Client - 与 Bitcoin RPC 通信
 pub struct AlchemyClient {
     endpoint: String,
     client: reqwest::Client,
+    /// 解析 RPC 返回的地址时使用的网络
+    network: Network,
+    /// with_stats 开启后记录每个 RPC method 的调用次数和耗时
+    stats: Option<RpcStats>,
+}
+
+/// =====================================================
+/// RPC 调用统计
+/// =====================================================
+///
+/// 用来看一个操作到底发了多少次 RPC、花了多久（batch / 并发很容易不小心发出几百次调用）：
+///   - requests：HTTP 请求次数（一次 batch 只算一次）
+///   - 每个 method 的调用次数：batch 里的每个调用都单独计数
+///   - 每个 method 的累计耗时：包含该 method 的 HTTP 请求的耗时之和
+#[derive(Debug, Default)]
+pub struct RpcStats {
+    requests: AtomicU64,
+    methods: Mutex<HashMap<String, Arc<MethodCounter>>>,
+}
+
+#[derive(Debug, Default)]
+struct MethodCounter {
+    count: AtomicU64,
+    nanos: AtomicU64,
+}
+
+/// 某个 method 的统计快照
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MethodStats {
+    pub count: u64,
+    pub total_duration: Duration,
+}
+
+impl RpcStats {
+    fn counter(&self, method: &str) -> Arc<MethodCounter> {
+        let mut methods = self.methods.lock().unwrap_or_else(|e| e.into_inner());
+        methods.entry(method.to_string()).or_default().clone()
+    }
+
+    fn record(&self, methods: &[&str], elapsed: Duration) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+
+        let mut seen = HashSet::new();
+        for method in methods {
+            let counter = self.counter(method);
+            counter.count.fetch_add(1, Ordering::Relaxed);
+            if seen.insert(*method) {
+                counter.nanos.fetch_add(nanos, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// HTTP 请求次数
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    /// 某个 method 的调用次数和累计耗时
+    pub fn method(&self, method: &str) -> MethodStats {
+        let methods = self.methods.lock().unwrap_or_else(|e| e.into_inner());
+        methods
+            .get(method)
+            .map(|counter| MethodStats {
+                count: counter.count.load(Ordering::Relaxed),
+                total_duration: Duration::from_nanos(counter.nanos.load(Ordering::Relaxed)),
+            })
+            .unwrap_or_default()
+    }
+
+    /// 所有 method 的统计快照，按 method 名排序
+    pub fn snapshot(&self) -> BTreeMap<String, MethodStats> {
+        let names: Vec<String> = self
+            .methods
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .keys()
+            .cloned()
+            .collect();
+        names
+            .into_iter()
+            .map(|name| {
+                let stats = self.method(&name);
+                (name, stats)
+            })
+            .collect()
+    }
 }
 
 /// UTXO 信息结构
@@ -20,8 +124,9 @@ pub struct UtxoInfo {
 #[derive(Clone, Debug)]
 pub struct ScriptPubKey {
     pub asm: String,
-    pub hex: String,
-    pub address: Option<String>,
+    /// 由 hex 字段解析而来，在 get_tx_out 里只解析一次
+    pub script: ScriptBuf,
+    pub address: Option<Address>,
 }
 
 /// gettxout 返回的完整结果
@@ -29,20 +134,355 @@ pub struct ScriptPubKey {
 pub struct TxOut {
     pub bestblock: String,
     pub confirmations: i64,
+    /// confirmations == 0：UTXO 还在 mempool 中（include_mempool = true 时）。
+    /// Core 总会返回 bestblock（当前链顶），不能用它判断
+    pub in_mempool: bool,
     pub value: u64,
     pub script_pubkey: ScriptPubKey,
     pub coinbase: Option<bool>,
-    pub txid: String,
+    pub txid: Txid,
     pub vout: u32,
 }
 
+/// 测试用的固定 txid
+#[cfg(test)]
+pub const TEST_TXID: &str = "43e447c5cb23868653680858a51dce44f1e08a84dbf79a29194f618c70eb3826";
+
+#[cfg(test)]
+impl TxOut {
+    /// 测试用的 UTXO：txid 为 TEST_TXID，1 个确认，非 coinbase
+    pub fn fixture(script: ScriptBuf, value: u64, vout: u32) -> Self {
+        TxOut {
+            bestblock: String::new(),
+            confirmations: 1,
+            in_mempool: false,
+            value,
+            script_pubkey: ScriptPubKey {
+                asm: String::new(),
+                script,
+                address: None,
+            },
+            coinbase: Some(false),
+            txid: TEST_TXID.parse().unwrap(),
+            vout,
+        }
+    }
+}
+
+/// submitpackage 中单笔交易的结果
+#[derive(Clone, Debug)]
+pub struct PackageTxResult {
+    pub wtxid: Wtxid,
+    pub txid: Txid,
+    pub vsize: Option<u64>,
+    /// 本交易自身的手续费
+    pub base_fee: Option<Amount>,
+    /// 打包计算后的有效费率（每 kvB 的金额，与 RPC 的 BTC/kvB 一致）
+    pub effective_feerate_per_kvb: Option<Amount>,
+    /// 单笔交易被拒绝时的原因
+    pub error: Option<String>,
+}
+
+/// testmempoolaccept 中单笔交易的结果
+#[derive(Clone, Debug)]
+pub struct MempoolAcceptResult {
+    pub txid: String,
+    pub allowed: bool,
+    /// 被拒绝时的原因（包内其他交易失败时为 package-error）
+    pub reject_reason: Option<String>,
+}
+
+/// submitpackage 的整体结果
+#[derive(Clone, Debug)]
+pub struct PackageResult {
+    /// "success" 表示整个包都已进入 mempool
+    pub package_msg: String,
+    /// 与提交的 txs 一一对应（同样的顺序）
+    pub tx_results: Vec<PackageTxResult>,
+}
+
+impl PackageResult {
+    pub fn is_success(&self) -> bool {
+        self.package_msg == "success"
+    }
+
+    /// 解析 submitpackage 的 result 字段
+    ///
+    /// tx-results 是以 wtxid 为 key 的对象，key 的顺序和提交顺序无关，
+    /// 所以按 txs 逐笔用 wtxid 查找；包提前失败时可能缺少部分交易的结果
+    pub fn from_rpc_result(
+        res: &Value,
+        txs: &[Transaction],
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut tx_results = Vec::new();
+        for tx in txs {
+            let wtxid = tx.compute_wtxid();
+            let tx_res = &res["tx-results"][wtxid.to_string()];
+            if tx_res.is_null() {
+                tx_results.push(PackageTxResult {
+                    wtxid,
+                    txid: tx.compute_txid(),
+                    vsize: None,
+                    base_fee: None,
+                    effective_feerate_per_kvb: None,
+                    error: Some("missing from submitpackage tx-results".to_string()),
+                });
+                continue;
+            }
+
+            let fees = &tx_res["fees"];
+            tx_results.push(PackageTxResult {
+                wtxid,
+                txid: tx.compute_txid(),
+                vsize: tx_res["vsize"].as_u64(),
+                base_fee: match &fees["base"] {
+                    Value::Null => None,
+                    v => Some(parse_btc_amount(v)?),
+                },
+                effective_feerate_per_kvb: match &fees["effective-feerate"] {
+                    Value::Null => None,
+                    v => Some(parse_btc_amount(v)?),
+                },
+                error: tx_res["error"].as_str().map(|e| e.to_string()),
+            });
+        }
+
+        Ok(Self {
+            package_msg: res["package_msg"].as_str().unwrap_or("").to_string(),
+            tx_results,
+        })
+    }
+
+    /// 包结构不符合「一个子交易 + 其未确认父交易」时的拒绝结果
+    fn rejected(package_msg: &str) -> Self {
+        Self {
+            package_msg: package_msg.to_string(),
+            tx_results: Vec::new(),
+        }
+    }
+}
+
+/// getmempoolinfo 中与费率下限相关的字段
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MempoolInfo {
+    /// mempool 当前接受的最低费率（mempool 满时会高于 minrelaytxfee）
+    pub mempool_min_fee: FeeRate,
+    /// 节点转发交易的最低费率
+    pub min_relay_tx_fee: FeeRate,
+}
+
+impl MempoolInfo {
+    /// 交易能被节点接受的最低费率
+    pub fn relay_floor(&self) -> FeeRate {
+        self.mempool_min_fee.max(self.min_relay_tx_fee)
+    }
+
+    /// 低于 relay_floor 的费率提高到 relay_floor
+    pub fn floor_fee_rate(&self, fee_rate: FeeRate) -> FeeRate {
+        fee_rate.max(self.relay_floor())
+    }
+}
+
+/// fast / medium / slow 三档推荐费率，保证 fast >= medium >= slow
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeRecommendations {
+    /// 约 1 个区块内确认
+    pub fast: FeeRate,
+    /// 约 1 小时（6 个区块）
+    pub medium: FeeRate,
+    /// 约 1 天（144 个区块）
+    pub slow: FeeRate,
+}
+
+impl FeeRecommendations {
+    /// 把按确认目标从短到长得到的费率整理为单调不增：目标更长的费率不应该更高
+    /// （estimatesmartfee 在不同目标上的估算相互独立，偶尔会出现倒挂）
+    fn monotonic(fast: FeeRate, medium: FeeRate, slow: FeeRate) -> Self {
+        let medium = medium.min(fast);
+        let slow = slow.min(medium);
+        FeeRecommendations { fast, medium, slow }
+    }
+}
+
+/// fee_recommendations 使用的确认目标
+const FEE_TARGETS: [u16; 3] = [1, 6, 144];
+
+/// JSON-RPC 调用错误
+#[derive(Debug)]
+pub enum AlchemyError {
+    /// HTTP 请求失败或响应不是 JSON
+    Http(reqwest::Error),
+    /// 节点返回的 error 对象
+    Rpc { code: i64, message: String },
+    /// txid 不是 64 位 hex（32 字节），请求不会发出
+    InvalidTxid(String),
+    /// 节点返回的 scriptPubKey.hex 不是合法的 hex
+    InvalidScriptPubKey(String),
+}
+
+impl fmt::Display for AlchemyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlchemyError::Http(e) => write!(f, "HTTP Error: {}", e),
+            AlchemyError::Rpc { code, message } => write!(f, "RPC Error ({}): {}", code, message),
+            AlchemyError::InvalidTxid(txid) => write!(f, "Invalid txid: {:?}", txid),
+            AlchemyError::InvalidScriptPubKey(hex) => {
+                write!(f, "Invalid scriptPubKey hex: {:?}", hex)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AlchemyError {}
+
+impl From<reqwest::Error> for AlchemyError {
+    fn from(e: reqwest::Error) -> Self {
+        AlchemyError::Http(e)
+    }
+}
+
 impl AlchemyClient {
-    /// 创建新的 AlchemyClient 实例
-    pub fn new(endpoint: &str) -> Self {
+    /// 创建新的 AlchemyClient 实例，network 为节点所在的网络，RPC 返回的地址按该网络校验
+    pub fn new(endpoint: &str, network: Network) -> Self {
+        Self::with_client(endpoint, network, reqwest::Client::new())
+    }
+
+    /// 使用自定义的 reqwest::Client 创建实例（可配置超时、代理等；测试里指向本地 mock server）
+    pub fn with_client(endpoint: &str, network: Network, client: reqwest::Client) -> Self {
         Self {
             endpoint: endpoint.to_string(),
-            client: reqwest::Client::new(),
+            client,
+            network,
+            stats: None,
+        }
+    }
+
+    /// 开启 RPC 调用统计，之后通过 stats() 读取
+    pub fn with_stats(mut self) -> Self {
+        self.stats = Some(RpcStats::default());
+        self
+    }
+
+    /// RPC 调用统计；未调用 with_stats 时为 None
+    pub fn stats(&self) -> Option<&RpcStats> {
+        self.stats.as_ref()
+    }
+
+    /// =====================================================
+    /// 通用 JSON-RPC 调用
+    /// =====================================================
+    ///
+    /// 统一处理请求信封（jsonrpc / id）和 error 字段，返回 result。
+    /// 也可以直接调用没有封装的 RPC，例如 call("getmempoolinfo", json!([]))
+    pub async fn call(&self, method: &str, params: Value) -> Result<Value, AlchemyError> {
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params
+        });
+
+        let mut result: Value = self.post_json(&[method], &payload).await?;
+
+        // 检查错误
+        if let Some(error) = result.get("error").filter(|e| !e.is_null()) {
+            return Err(AlchemyError::Rpc {
+                code: error.get("code").and_then(|c| c.as_i64()).unwrap_or(0),
+                message: error
+                    .get("message")
+                    .and_then(|m| m.as_str())
+                    .unwrap_or("Unknown error")
+                    .to_string(),
+            });
+        }
+
+        Ok(result["result"].take())
+    }
+
+    /// 发送一次 HTTP 请求并解析 JSON 响应；开启统计时记录 methods 中每个调用
+    async fn post_json<T: serde::de::DeserializeOwned>(
+        &self,
+        methods: &[&str],
+        payload: &impl serde::Serialize,
+    ) -> Result<T, AlchemyError> {
+        let started = Instant::now();
+        let result = async {
+            let response = self
+                .client
+                .post(&self.endpoint)
+                .json(payload)
+                .send()
+                .await?;
+            Ok(response.json().await?)
+        }
+        .await;
+
+        if let Some(stats) = &self.stats {
+            stats.record(methods, started.elapsed());
+        }
+        result
+    }
+
+    /// =====================================================
+    /// JSON-RPC batch 调用
+    /// =====================================================
+    ///
+    /// 一次 HTTP 请求发送多个调用（请求体为数组），按 calls 的顺序返回每个调用的结果；
+    /// 节点返回的数组不保证顺序，这里按 id（即下标）对应回去
+    pub async fn call_batch(
+        &self,
+        calls: &[(&str, Value)],
+    ) -> Result<Vec<Result<Value, AlchemyError>>, AlchemyError> {
+        if calls.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let payload: Vec<Value> = calls
+            .iter()
+            .enumerate()
+            .map(|(id, (method, params))| {
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "method": method,
+                    "params": params
+                })
+            })
+            .collect();
+
+        let methods: Vec<&str> = calls.iter().map(|(method, _)| *method).collect();
+        let responses: Vec<Value> = self.post_json(&methods, &payload).await?;
+
+        let mut results: Vec<Result<Value, AlchemyError>> = (0..calls.len())
+            .map(|_| {
+                Err(AlchemyError::Rpc {
+                    code: 0,
+                    message: "Missing response in batch".to_string(),
+                })
+            })
+            .collect();
+        for mut res in responses {
+            let Some(slot) = res["id"]
+                .as_u64()
+                .and_then(|id| results.get_mut(id as usize))
+            else {
+                continue;
+            };
+            let error = &res["error"];
+            *slot = if error.is_null() {
+                Ok(res["result"].take())
+            } else {
+                Err(AlchemyError::Rpc {
+                    code: error["code"].as_i64().unwrap_or(0),
+                    message: error["message"]
+                        .as_str()
+                        .unwrap_or("Unknown error")
+                        .to_string(),
+                })
+            };
         }
+
+        Ok(results)
     }
 
     /// =====================================================
@@ -60,79 +500,102 @@ impl AlchemyClient {
         vout: u32,
         include_mempool: bool,
     ) -> Result<Option<TxOut>, Box<dyn std::error::Error>> {
+        let parsed_txid = txid
+            .parse::<Txid>()
+            .map_err(|_| AlchemyError::InvalidTxid(txid.to_string()))?;
+
         println!(
             "  [RPC] 调用 gettxout (txid: {}..., vout: {})",
-            &txid[..16],
+            txid.get(..16).unwrap_or(txid),
             vout
         );
 
-        let payload = json!({
-            "jsonrpc": "2.0",
-            "id": 1,
-            "method": "gettxout",
-            "params": [txid, vout, include_mempool]
-        });
-
-        let response = self
-            .client
-            .post(&self.endpoint)
-            .json(&payload)
-            .send()
-            .await?;
-
-        let result: Value = response.json().await?;
+        let res = match self
+            .call("gettxout", json!([txid, vout, include_mempool]))
+            .await
+        {
+            Ok(res) => res,
+            // gettxout 在 UTXO 已被花费时返回 null，这不是错误
+            Err(AlchemyError::Rpc { message, .. })
+                if message.contains("spent") || message.contains("not found") =>
+            {
+                return Ok(None);
+            }
+            Err(e) => return Err(e.into()),
+        };
 
-        println!("  [RPC] 响应: {:?}", result);
+        println!("  [RPC] 响应: {:?}", res);
 
-        // 检查错误
-        if let Some(error) = result.get("error") {
-            if !error.is_null() {
-                let error_msg = error
-                    .get("message")
-                    .and_then(|m| m.as_str())
-                    .unwrap_or("Unknown error");
-                // gettxout 在 UTXO 已被花费时返回 null，这不是错误
-                if error_msg.contains("spent") || error_msg.contains("not found") {
-                    return Ok(None);
-                }
-                return Err(format!("RPC Error: {}", error_msg).into());
-            }
-        }
+        self.parse_tx_out(&res, parsed_txid, vout)
+    }
 
-        // 如果结果是 null，表示 UTXO 已被花费或不存在
-        if result["result"].is_null() {
+    /// 解析 gettxout 的 result；null 表示 UTXO 已被花费或不存在
+    fn parse_tx_out(
+        &self,
+        res: &Value,
+        txid: Txid,
+        vout: u32,
+    ) -> Result<Option<TxOut>, Box<dyn std::error::Error>> {
+        if res.is_null() {
             println!("  [RPC] 结果为 null，UTXO 已被花费或不存在");
             return Ok(None);
         }
 
-        let res = &result["result"];
-
         // 解析返回结果
+        let script_hex = res["scriptPubKey"]["hex"]
+            .as_str()
+            .filter(|hex| !hex.is_empty())
+            .ok_or("Missing scriptPubKey.hex in gettxout")?;
+        let script = ScriptBuf::from_hex(script_hex)
+            .map_err(|_| AlchemyError::InvalidScriptPubKey(script_hex.to_string()))?;
+
         let tx_out = TxOut {
             bestblock: res["bestblock"].as_str().unwrap_or("").to_string(),
             confirmations: res["confirmations"].as_i64().unwrap_or(0),
-            value: Amount::from_btc(res["value"].as_f64().unwrap_or(0.0))?.to_sat(), // satoshis
+            in_mempool: res["confirmations"].as_i64().unwrap_or(0) == 0,
+            value: parse_btc_amount(&res["value"])?.to_sat(), // satoshis
             script_pubkey: ScriptPubKey {
                 asm: res["scriptPubKey"]["asm"]
                     .as_str()
                     .unwrap_or("")
                     .to_string(),
-                hex: res["scriptPubKey"]["hex"]
-                    .as_str()
-                    .unwrap_or("")
-                    .to_string(),
+                script,
                 address: res["scriptPubKey"]["address"]
                     .as_str()
-                    .map(|s| s.to_string()),
+                    .map(|s| parse_address(s, self.network))
+                    .transpose()?,
             },
             coinbase: res["coinbase"].as_bool(),
-            txid: txid.to_string(),
-            vout: vout,
+            txid,
+            vout,
         };
 
         Ok(Some(tx_out))
     }
 
+    /// =====================================================
+    /// 用一次 batch 请求获取多个 UTXO（gettxout）
+    /// =====================================================
+    ///
+    /// 按 outpoints 的顺序返回，已花费或不存在的为 None
+    pub async fn get_tx_outs_batch(
+        &self,
+        outpoints: &[OutPoint],
+    ) -> Result<Vec<Option<TxOut>>, Box<dyn std::error::Error>> {
+        println!("  [RPC] 批量调用 gettxout ({} outpoints)", outpoints.len());
+
+        let calls: Vec<(&str, Value)> = outpoints
+            .iter()
+            .map(|op| ("gettxout", json!([op.txid.to_string(), op.vout, true])))
+            .collect();
+
+        let mut tx_outs = Vec::with_capacity(outpoints.len());
+        for (outpoint, res) in outpoints.iter().zip(self.call_batch(&calls).await?) {
+            tx_outs.push(self.parse_tx_out(&res?, outpoint.txid, outpoint.vout)?);
+        }
+        Ok(tx_outs)
+    }
+
     /// =====================================================
     /// 获取多个 UTXO 的详情
     /// =====================================================
@@ -159,13 +622,74 @@ impl AlchemyClient {
     /// 使用 sendrawtransaction 将签名的交易广播到网络
     /// 参数：
     ///   - tx: 序列化的交易对象
-    ///   - max_fee_rate: 最大费率（BTC/kB），0 表示不限制
+    ///   - max_fee_rate: 节点端的最大费率，超过时节点拒绝广播
     pub async fn broadcast_tx(
         &self,
         tx: &Transaction,
+        max_fee_rate: FeeRate,
     ) -> Result<String, Box<dyn std::error::Error>> {
-        self.broadcast_tx_hex(&bitcoin::consensus::encode::serialize_hex(tx), 0.1)
+        // sendrawtransaction 的 maxfeerate 单位是 BTC/kvB
+        let max_fee_rate_btc_kvb = (max_fee_rate.to_sat_per_kwu() * 4) as f64 / 100_000_000.0;
+        // txid 在本地计算，"已广播过" 时节点不会再返回它
+        let txid = tx.compute_txid().to_string();
+
+        match self
+            .send_raw_transaction(
+                &bitcoin::consensus::encode::serialize_hex(tx),
+                max_fee_rate_btc_kvb,
+            )
             .await
+        {
+            Ok(res) => res
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| "Unknown broadcast error".into()),
+            // 超时后重试时，上一次其实已经成功：交易已在 mempool 或已上链，都算成功
+            Err(AlchemyError::Rpc { code, message }) if is_already_broadcast(code, &message) => {
+                println!("  ℹ️ 交易已在 mempool 或链上: {} ({})", txid, message);
+                Ok(txid)
+            }
+            Err(AlchemyError::Rpc { message, .. }) => {
+                Err(format!("Broadcast failed: {}", message).into())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 使用默认上限广播交易（0.1 BTC/kvB，即 10000 sat/vB）
+    pub async fn broadcast_tx_default(
+        &self,
+        tx: &Transaction,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        self.broadcast_tx(tx, DEFAULT_MAX_FEE_RATE).await
+    }
+
+    /// 广播前先用 prevouts 在本地计算实际费率，超过 max_fee_rate 时直接报错，不发起网络请求
+    ///
+    /// 用于防止 vsize 算错或手误导致的超额手续费
+    pub async fn broadcast_tx_with_prevouts(
+        &self,
+        tx: &Transaction,
+        prevouts: &[bitcoin::TxOut],
+        max_fee_rate: FeeRate,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let (fee, fee_rate) = actual_fee_rate(tx, prevouts)?;
+        println!(
+            "  💰 Fee: {} sat ({} sat/vB)",
+            fee.to_sat(),
+            fee_rate.to_sat_per_vb_ceil()
+        );
+
+        if fee_rate > max_fee_rate {
+            return Err(format!(
+                "Fee rate {} sat/vB exceeds cap {} sat/vB",
+                fee_rate.to_sat_per_vb_ceil(),
+                max_fee_rate.to_sat_per_vb_floor()
+            )
+            .into());
+        }
+
+        self.broadcast_tx(tx, max_fee_rate).await
     }
 
     /// 使用 16 进制字符串广播交易
@@ -174,56 +698,1702 @@ impl AlchemyClient {
         tx_hex: &str,
         max_fee_rate: f64,
     ) -> Result<String, Box<dyn std::error::Error>> {
-        println!("  [RPC] 调用 sendrawtransaction");
+        let res = match self.send_raw_transaction(tx_hex, max_fee_rate).await {
+            Ok(res) => res,
+            Err(AlchemyError::Rpc { message, .. }) => {
+                return Err(format!("Broadcast failed: {}", message).into());
+            }
+            Err(e) => return Err(e.into()),
+        };
 
-        let payload = json!({
-            "jsonrpc": "2.0",
-            "id": 1,
-            "method": "sendrawtransaction",
-            "params": [tx_hex, max_fee_rate]
-        });
+        // 返回 TXID
+        if let Some(txid) = res.as_str() {
+            Ok(txid.to_string())
+        } else {
+            Err("Unknown broadcast error".into())
+        }
+    }
 
-        let response = self
-            .client
-            .post(&self.endpoint)
-            .json(&payload)
-            .send()
-            .await?;
+    async fn send_raw_transaction(
+        &self,
+        tx_hex: &str,
+        max_fee_rate: f64,
+    ) -> Result<Value, AlchemyError> {
+        println!("  [RPC] 调用 sendrawtransaction");
+        self.call("sendrawtransaction", json!([tx_hex, max_fee_rate]))
+            .await
+    }
+
+    /// =====================================================
+    /// 使用 submitpackage 原子地广播父子交易（CPFP）
+    /// =====================================================
+    ///
+    /// 父交易费率低于 mempool 最低费率时单独广播会失败，
+    /// 打包提交时按整个包的费率评估。txs 需按拓扑排序，子交易在最后。
+    /// 包结构不合法（package-not-child-with-unconfirmed-parents）时返回
+    /// is_success() 为 false 的结果而不是 Err
+    pub async fn submit_package(
+        &self,
+        txs: &[Transaction],
+    ) -> Result<PackageResult, Box<dyn std::error::Error>> {
+        println!("  [RPC] 调用 submitpackage ({} 笔交易)", txs.len());
 
-        let result: Value = response.json().await?;
+        let raw_txs: Vec<String> = txs
+            .iter()
+            .map(bitcoin::consensus::encode::serialize_hex)
+            .collect();
 
-        // 检查错误
-        if let Some(error) = result.get("error") {
-            if !error.is_null() {
-                let error_msg = error
-                    .get("message")
-                    .and_then(|m| m.as_str())
-                    .unwrap_or("Unknown error");
-                return Err(format!("Broadcast failed: {}", error_msg).into());
+        match self.call("submitpackage", json!([raw_txs])).await {
+            Ok(res) => PackageResult::from_rpc_result(&res, txs),
+            Err(AlchemyError::Rpc { message, .. })
+                if message.contains("package-not-child-with-unconfirmed-parents")
+                    || message.contains("not child-with-parents") =>
+            {
+                Ok(PackageResult::rejected(&message))
+            }
+            Err(AlchemyError::Rpc { message, .. }) => {
+                Err(format!("submitpackage failed: {}", message).into())
             }
+            Err(e) => Err(e.into()),
         }
+    }
 
-        // 返回 TXID
-        if let Some(txid) = result["result"].as_str() {
-            Ok(txid.to_string())
-        } else {
-            Err("Unknown broadcast error".into())
+    /// =====================================================
+    /// 使用 scantxoutset 扫描某个地址的所有 UTXO
+    /// =====================================================
+    ///
+    /// 扫描整个 UTXO 集合，主网上可能需要几分钟
+    pub async fn scan_utxos_for_address(
+        &self,
+        address: &str,
+    ) -> Result<Vec<UtxoInfo>, Box<dyn std::error::Error>> {
+        println!("  [RPC] 调用 scantxoutset (address: {})", address);
+
+        self.scan_utxos(vec![json!(format!("addr({})", address))])
+            .await
+    }
+
+    /// 用 scantxoutset 一次扫描多个对象：字符串描述符（如 addr(...)），
+    /// 或带 range 的 {"desc": ..., "range": n}（扫描序号 0..=n）
+    pub async fn scan_utxos(
+        &self,
+        scan_objects: Vec<Value>,
+    ) -> Result<Vec<UtxoInfo>, Box<dyn std::error::Error>> {
+        let res = self
+            .call("scantxoutset", json!(["start", scan_objects]))
+            .await?;
+        parse_scan_result(&res)
+    }
+
+    /// =====================================================
+    /// 带进度和取消的 scantxoutset 扫描
+    /// =====================================================
+    ///
+    /// scantxoutset start 会一直阻塞到扫描结束。扫描期间每隔 poll_interval 调用一次
+    /// scantxoutset status，把 progress（0 ~ 100）交给 on_progress，便于 CLI 显示进度条。
+    ///
+    /// cancel 被触发时发送 scantxoutset abort 并返回错误。节点不支持从中断处继续扫描，
+    /// 取消后只能重新开始；可以把 descriptors 分成几批分别扫描
+    pub async fn scan_utxos_with_progress(
+        &self,
+        descriptors: &[String],
+        poll_interval: Duration,
+        mut on_progress: impl FnMut(f64),
+        cancel: &CancellationToken,
+    ) -> Result<Vec<UtxoInfo>, Box<dyn std::error::Error>> {
+        println!(
+            "  [RPC] 调用 scantxoutset ({} descriptors)",
+            descriptors.len()
+        );
+
+        let start = self.call("scantxoutset", json!(["start", descriptors]));
+        tokio::pin!(start);
+
+        loop {
+            tokio::select! {
+                res = &mut start => return parse_scan_result(&res?),
+                _ = cancel.cancelled() => {
+                    self.call("scantxoutset", json!(["abort"])).await?;
+                    return Err("scantxoutset cancelled".into());
+                }
+                _ = tokio::time::sleep(poll_interval) => {
+                    // 扫描刚开始或刚结束时 status 返回 null
+                    let status = self.call("scantxoutset", json!(["status"])).await?;
+                    if let Some(progress) = status["progress"].as_f64() {
+                        on_progress(progress);
+                    }
+                }
+            }
         }
     }
 
     /// =====================================================
-    /// 辅助方法：验证 UTXO
+    /// 使用 getrawtransaction（verbose）获取交易详情
     /// =====================================================
     ///
-    /// 检查 UTXO 是否仍然可用
-    pub async fn verify_utxo(
+    /// 已确认的交易会带有 `blockhash` 字段，未确认时没有
+    pub async fn get_raw_transaction_verbose(
         &self,
         txid: &str,
-        vout: u32,
-    ) -> Result<bool, Box<dyn std::error::Error>> {
-        match self.get_tx_out(txid, vout, true).await? {
-            Some(_) => Ok(true),
-            None => Ok(false),
+    ) -> Result<Value, Box<dyn std::error::Error>> {
+        println!("  [RPC] 调用 getrawtransaction (txid: {})", txid);
+
+        Ok(self.call("getrawtransaction", json!([txid, true])).await?)
+    }
+
+    /// =====================================================
+    /// 交易是否显式声明可被 RBF 替换（BIP125 opt-in）
+    /// =====================================================
+    ///
+    /// 任意 input 的 sequence < 0xfffffffe 即视为声明。本 crate 构造的交易都用
+    /// ENABLE_RBF_NO_LOCKTIME，这里用来检查别人构造的交易；
+    /// 不检查从未确认祖先继承的可替换性
+    pub async fn is_rbf_signaled(&self, txid: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        txid.parse::<Txid>()
+            .map_err(|_| AlchemyError::InvalidTxid(txid.to_string()))?;
+
+        let tx = self.get_raw_transaction_verbose(txid).await?;
+        let inputs = tx["vin"]
+            .as_array()
+            .ok_or(format!("getrawtransaction {} returned no vin", txid))?;
+
+        let mut signaled = false;
+        for input in inputs {
+            let sequence = input["sequence"]
+                .as_u64()
+                .ok_or(format!("getrawtransaction {} input without sequence", txid))?;
+            signaled |= sequence < Sequence::ENABLE_LOCKTIME_NO_RBF.0 as u64;
         }
+        Ok(signaled)
+    }
+
+    /// =====================================================
+    /// 用一次 batch 请求获取多笔完整交易（getrawtransaction，非 verbose）
+    /// =====================================================
+    ///
+    /// 按 txids 的顺序返回；节点返回的交易 txid 对不上时报错
+    pub async fn get_raw_transactions_batch(
+        &self,
+        txids: &[Txid],
+    ) -> Result<Vec<Transaction>, Box<dyn std::error::Error>> {
+        println!("  [RPC] 批量调用 getrawtransaction ({} txids)", txids.len());
+
+        let calls: Vec<(&str, Value)> = txids
+            .iter()
+            .map(|txid| ("getrawtransaction", json!([txid.to_string(), false])))
+            .collect();
+
+        let mut txs = Vec::with_capacity(txids.len());
+        for (txid, res) in txids.iter().zip(self.call_batch(&calls).await?) {
+            let res = res?;
+            let hex = res
+                .as_str()
+                .ok_or(format!("getrawtransaction {} returned no hex", txid))?;
+            let tx: Transaction = bitcoin::consensus::encode::deserialize_hex(hex)?;
+            if tx.compute_txid() != *txid {
+                return Err(format!(
+                    "getrawtransaction {} returned transaction {}",
+                    txid,
+                    tx.compute_txid()
+                )
+                .into());
+            }
+            txs.push(tx);
+        }
+        Ok(txs)
+    }
+
+    /// =====================================================
+    /// 从 OutPoint 还原 prevout（getrawtransaction 取 tx.output[vout]）
+    /// =====================================================
+    ///
+    /// 与 gettxout 不同，已花费的输出也能取到（reorg 后恢复交易时需要）。
+    /// 同一笔交易只获取一次，所有交易用一次 batch 请求；按 outpoints 的顺序返回，
+    /// vout 超出交易输出数时报错
+    pub async fn fetch_prevouts(
+        &self,
+        outpoints: &[OutPoint],
+    ) -> Result<Vec<bitcoin::TxOut>, Box<dyn std::error::Error>> {
+        let mut txids: Vec<Txid> = Vec::new();
+        for outpoint in outpoints {
+            if !txids.contains(&outpoint.txid) {
+                txids.push(outpoint.txid);
+            }
+        }
+        let txs: HashMap<Txid, Transaction> = txids
+            .iter()
+            .copied()
+            .zip(self.get_raw_transactions_batch(&txids).await?)
+            .collect();
+
+        outpoints
+            .iter()
+            .map(|outpoint| {
+                txs[&outpoint.txid]
+                    .output
+                    .get(outpoint.vout as usize)
+                    .cloned()
+                    .ok_or_else(|| {
+                        format!(
+                            "{} has only {} outputs, vout {} out of range",
+                            outpoint.txid,
+                            txs[&outpoint.txid].output.len(),
+                            outpoint.vout
+                        )
+                        .into()
+                    })
+            })
+            .collect()
+    }
+
+    /// =====================================================
+    /// 使用 getblock 获取区块（verbosity = 1，tx 为 txid 列表）
+    /// =====================================================
+    pub async fn get_block(&self, blockhash: &str) -> Result<Value, Box<dyn std::error::Error>> {
+        println!("  [RPC] 调用 getblock (blockhash: {})", blockhash);
+
+        Ok(self.call("getblock", json!([blockhash, 1])).await?)
+    }
+
+    /// =====================================================
+    /// 计算 etching 交易对应的 rune id
+    /// =====================================================
+    ///
+    /// rune id = etching 交易所在区块高度 : 交易在区块中的序号；
+    /// 交易未确认时没有 rune id，返回错误
+    pub async fn rune_id_for_txid(&self, txid: &str) -> Result<RuneId, Box<dyn std::error::Error>> {
+        let tx = self.get_raw_transaction_verbose(txid).await?;
+        let blockhash = tx["blockhash"]
+            .as_str()
+            .ok_or(format!("transaction {} is not confirmed yet", txid))?;
+
+        let block = self.get_block(blockhash).await?;
+        let height = block["height"].as_u64().ok_or("missing block height")?;
+        let index = block["tx"]
+            .as_array()
+            .and_then(|txs| txs.iter().position(|t| t.as_str() == Some(txid)))
+            .ok_or("transaction not found in block")?;
+
+        Ok(RuneId {
+            block: height,
+            tx: u32::try_from(index)?,
+        })
+    }
+
+    /// =====================================================
+    /// 使用 testmempoolaccept 检查交易能否进入 mempool（不广播）
+    /// =====================================================
+    ///
+    /// 多笔交易按拓扑顺序作为一个包检查，子交易可以花费包内父交易的输出
+    pub async fn test_mempool_accept(
+        &self,
+        txs: &[&Transaction],
+    ) -> Result<Vec<MempoolAcceptResult>, Box<dyn std::error::Error>> {
+        println!("  [RPC] 调用 testmempoolaccept ({} txs)", txs.len());
+
+        let hexes: Vec<String> = txs
+            .iter()
+            .map(|tx| bitcoin::consensus::encode::serialize_hex(*tx))
+            .collect();
+        let res = self.call("testmempoolaccept", json!([hexes])).await?;
+
+        Ok(res
+            .as_array()
+            .ok_or("Invalid testmempoolaccept result")?
+            .iter()
+            .map(|r| MempoolAcceptResult {
+                txid: r["txid"].as_str().unwrap_or("").to_string(),
+                allowed: r["allowed"].as_bool().unwrap_or(false),
+                reject_reason: r["reject-reason"]
+                    .as_str()
+                    .or(r["package-error"].as_str())
+                    .map(|e| e.to_string()),
+            })
+            .collect())
+    }
+
+    /// =====================================================
+    /// 使用 getmempoolinfo 获取 mempool 的费率下限
+    /// =====================================================
+    pub async fn get_mempool_info(&self) -> Result<MempoolInfo, Box<dyn std::error::Error>> {
+        println!("  [RPC] 调用 getmempoolinfo");
+
+        let info = self.call("getmempoolinfo", json!([])).await?;
+        Ok(MempoolInfo {
+            mempool_min_fee: parse_btc_per_kvb(&info["mempoolminfee"])?,
+            min_relay_tx_fee: parse_btc_per_kvb(&info["minrelaytxfee"])?,
+        })
+    }
+
+    /// =====================================================
+    /// 使用 getblockchaininfo 获取链信息
+    /// =====================================================
+    pub async fn get_blockchain_info(&self) -> Result<Value, Box<dyn std::error::Error>> {
+        println!("  [RPC] 调用 getblockchaininfo");
+
+        Ok(self.call("getblockchaininfo", json!([])).await?)
+    }
+
+    /// =====================================================
+    /// 辅助方法：检查节点所在的链与钱包网络一致
+    /// =====================================================
+    ///
+    /// getblockchaininfo 的 chain 字段为 main / test / testnet4 / signet / regtest
+    pub async fn check_network(&self, expected: Network) -> Result<(), Box<dyn std::error::Error>> {
+        let info = self.get_blockchain_info().await?;
+        let chain = info["chain"]
+            .as_str()
+            .ok_or("Missing chain in getblockchaininfo")?;
+        let network = Network::from_core_arg(chain)?;
+
+        if network != expected {
+            return Err(format!(
+                "Network mismatch: node is on {}, wallet expects {}",
+                chain,
+                expected.to_core_arg()
+            )
+            .into());
+        }
+
+        println!("  ✓ 节点网络: {}", chain);
+        Ok(())
+    }
+
+    /// =====================================================
+    /// 辅助方法：验证 UTXO
+    /// =====================================================
+    ///
+    /// 检查 UTXO 是否仍然可用
+    pub async fn verify_utxo(
+        &self,
+        txid: &str,
+        vout: u32,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        match self.get_tx_out(txid, vout, true).await? {
+            Some(_) => Ok(true),
+            None => Ok(false),
+        }
+    }
+
+    /// =====================================================
+    /// 使用 estimatesmartfee 估算费率
+    /// =====================================================
+    ///
+    /// 节点数据不足时（返回 errors 而没有 feerate）返回 None
+    pub async fn estimate_smart_fee(
+        &self,
+        conf_target: u16,
+    ) -> Result<Option<FeeRate>, Box<dyn std::error::Error>> {
+        println!(
+            "  [RPC] 调用 estimatesmartfee (conf_target={})",
+            conf_target
+        );
+
+        let res = self.call("estimatesmartfee", json!([conf_target])).await?;
+        if res["feerate"].is_null() {
+            return Ok(None);
+        }
+        Ok(Some(parse_btc_per_kvb(&res["feerate"])?))
+    }
+
+    /// =====================================================
+    /// 推荐费率：fast / medium / slow 三档
+    /// =====================================================
+    ///
+    /// 分别用确认目标 1、6、144 调用 estimatesmartfee；节点没有估算数据的档位
+    /// 使用 mempool 的 relay 下限，最后整理为 fast >= medium >= slow
+    pub async fn fee_recommendations(
+        &self,
+    ) -> Result<FeeRecommendations, Box<dyn std::error::Error>> {
+        let mut estimates = Vec::with_capacity(FEE_TARGETS.len());
+        for target in FEE_TARGETS {
+            estimates.push(self.estimate_smart_fee(target).await?);
+        }
+
+        let floor = if estimates.iter().any(Option::is_none) {
+            Some(self.get_mempool_info().await?.relay_floor())
+        } else {
+            None
+        };
+        let rates = estimates
+            .into_iter()
+            .map(|rate| rate.or(floor).ok_or("no fee estimate available"))
+            .collect::<Result<Vec<FeeRate>, _>>()?;
+
+        Ok(FeeRecommendations::monotonic(rates[0], rates[1], rates[2]))
+    }
+
+    /// =====================================================
+    /// 使用 getblockcount 获取当前区块高度
+    /// =====================================================
+    pub async fn get_block_count(&self) -> Result<u64, Box<dyn std::error::Error>> {
+        println!("  [RPC] 调用 getblockcount");
+
+        let res = self.call("getblockcount", json!([])).await?;
+        res.as_u64()
+            .ok_or_else(|| "Invalid getblockcount result".into())
+    }
+}
+
+/// =====================================================
+/// Bitcoin RPC 抽象
+/// =====================================================
+///
+/// tx_first_commit 等单 UTXO 流程只用到查询 UTXO 和广播，接受 `&impl BitcoinRpc`；
+/// 测试时可以传入手写的 fake，不需要真实节点或 mock HTTP server。
+/// 只放这些流程实际调用的方法，其它流程仍直接使用 AlchemyClient
+pub trait BitcoinRpc {
+    async fn get_tx_out(
+        &self,
+        txid: &str,
+        vout: u32,
+        include_mempool: bool,
+    ) -> Result<Option<TxOut>, Box<dyn std::error::Error>>;
+
+    async fn broadcast_tx(
+        &self,
+        tx: &Transaction,
+        max_fee_rate: FeeRate,
+    ) -> Result<String, Box<dyn std::error::Error>>;
+
+    /// 使用默认上限广播交易（0.1 BTC/kvB）
+    async fn broadcast_tx_default(
+        &self,
+        tx: &Transaction,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        self.broadcast_tx(tx, DEFAULT_MAX_FEE_RATE).await
+    }
+}
+
+impl BitcoinRpc for AlchemyClient {
+    async fn get_tx_out(
+        &self,
+        txid: &str,
+        vout: u32,
+        include_mempool: bool,
+    ) -> Result<Option<TxOut>, Box<dyn std::error::Error>> {
+        AlchemyClient::get_tx_out(self, txid, vout, include_mempool).await
+    }
+
+    async fn broadcast_tx(
+        &self,
+        tx: &Transaction,
+        max_fee_rate: FeeRate,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        AlchemyClient::broadcast_tx(self, tx, max_fee_rate).await
+    }
+}
+
+/// =====================================================
+/// 多 input 签名用的 prevout 缓存
+/// =====================================================
+///
+/// Prevouts::All 需要所有 input 的 prevout。fetch 用一次 batch gettxout 取回
+/// 所有尚未缓存的 prevout，之后签名、验证都直接查缓存，不再逐个请求节点
+#[derive(Debug, Default)]
+pub struct PrevoutCache {
+    prevouts: HashMap<OutPoint, bitcoin::TxOut>,
+}
+
+impl PrevoutCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 批量获取缺失的 prevout；任一 outpoint 已花费或不存在时报错
+    pub async fn fetch(
+        &mut self,
+        alchemy: &AlchemyClient,
+        outpoints: &[OutPoint],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut missing: Vec<OutPoint> = outpoints
+            .iter()
+            .filter(|op| !self.prevouts.contains_key(op))
+            .copied()
+            .collect();
+        missing.sort();
+        missing.dedup();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        for (outpoint, tx_out) in missing
+            .iter()
+            .zip(alchemy.get_tx_outs_batch(&missing).await?)
+        {
+            let tx_out =
+                tx_out.ok_or(format!("prevout {} is spent or does not exist", outpoint))?;
+            self.prevouts.insert(
+                *outpoint,
+                bitcoin::TxOut {
+                    value: Amount::from_sat(tx_out.value),
+                    script_pubkey: tx_out.script_pubkey.script,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, outpoint: &OutPoint) -> Option<&bitcoin::TxOut> {
+        self.prevouts.get(outpoint)
+    }
+
+    /// 按 input 顺序取出交易的全部 prevout（即 Prevouts::All 的参数）
+    pub fn prevouts_for(
+        &self,
+        tx: &Transaction,
+    ) -> Result<Vec<bitcoin::TxOut>, Box<dyn std::error::Error>> {
+        tx.input
+            .iter()
+            .map(|input| {
+                self.get(&input.previous_output)
+                    .cloned()
+                    .ok_or_else(|| format!("prevout {} not in cache", input.previous_output).into())
+            })
+            .collect()
+    }
+}
+
+/// RPC_VERIFY_ALREADY_IN_CHAIN：交易已在链上
+const RPC_VERIFY_ALREADY_IN_CHAIN: i64 = -27;
+
+/// sendrawtransaction 的错误是否表示这笔交易之前已经广播成功
+fn is_already_broadcast(code: i64, message: &str) -> bool {
+    code == RPC_VERIFY_ALREADY_IN_CHAIN
+        || message.contains("txn-already-in-mempool")
+        || message.contains("txn-already-known")
+}
+
+/// 把 RPC 返回的 BTC 金额解析为 Amount，不经过 f64
+///
+/// serde_json 开启了 arbitrary_precision，Number 保留 JSON 里的原始十进制文本，
+/// 交给 amount::btc_string_to_sat 按十进制解析；缺失（null）或不是数字的值返回错误
+fn parse_btc_amount(value: &Value) -> Result<Amount, Box<dyn std::error::Error>> {
+    let amount_str = match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => expand_exponent(&n.to_string())?,
+        other => return Err(format!("Invalid BTC amount: {}", other).into()),
+    };
+    Ok(Amount::from_sat(btc_string_to_sat(&amount_str)?))
+}
+
+/// 把 `1e-8` 一类的科学计数法改写为普通十进制（移动小数点，纯字符串运算）；
+/// Bitcoin Core 不输出科学计数法，但其它 JSON 生成方可能会
+fn expand_exponent(s: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let Some((mantissa, exp)) = s.split_once(['e', 'E']) else {
+        return Ok(s.to_string());
+    };
+    let exp: i64 = exp.parse()?;
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(m) => ("-", m),
+        None => ("", mantissa),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", int, frac);
+    let point = int.len() as i64 + exp;
+
+    let expanded = if point <= 0 {
+        format!("0.{}{}", "0".repeat(point.unsigned_abs() as usize), digits)
+    } else if point as usize >= digits.len() {
+        format!("{}{}", digits, "0".repeat(point as usize - digits.len()))
+    } else {
+        let (int, frac) = digits.split_at(point as usize);
+        format!("{}.{}", int, frac)
+    };
+    let expanded = match expanded.split_once('.') {
+        Some((int, frac)) if frac.trim_end_matches('0').is_empty() => int.to_string(),
+        Some((int, frac)) => format!("{}.{}", int, frac.trim_end_matches('0')),
+        None => expanded,
+    };
+    Ok(format!("{}{}", sign, expanded))
+}
+
+/// 解析 scantxoutset start 的结果
+fn parse_scan_result(res: &Value) -> Result<Vec<UtxoInfo>, Box<dyn std::error::Error>> {
+    let tip_height = res["height"].as_i64().unwrap_or(0);
+
+    let mut utxos = Vec::new();
+    for unspent in res["unspents"].as_array().into_iter().flatten() {
+        // height 是 UTXO 所在区块高度，据此换算确认数
+        let confirmations = unspent["height"]
+            .as_i64()
+            .filter(|h| *h > 0)
+            .map(|h| tip_height - h + 1);
+
+        utxos.push(UtxoInfo {
+            txid: unspent["txid"].as_str().unwrap_or("").to_string(),
+            vout: unspent["vout"].as_u64().unwrap_or(0) as usize,
+            value: parse_btc_amount(&unspent["amount"])?.to_sat(),
+            confirmations,
+        });
+    }
+
+    Ok(utxos)
+}
+
+/// 把 RPC 返回的 BTC/kvB 费率解析为 FeeRate（不足 1 sat/kwu 的部分向上取整）
+fn parse_btc_per_kvb(value: &Value) -> Result<FeeRate, Box<dyn std::error::Error>> {
+    let sat_per_kvb = parse_btc_amount(value)?.to_sat();
+    Ok(FeeRate::from_sat_per_kwu(sat_per_kvb.div_ceil(4)))
+}
+
+/// =====================================================
+/// 测试用的 JSON-RPC mock server
+/// =====================================================
+#[cfg(test)]
+pub mod mock_rpc {
+    use super::AlchemyClient;
+    use serde_json::{Value, json};
+    use wiremock::matchers::{body_partial_json, method};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    /// 按 JSON-RPC method 挂载一个返回固定 result 的 mock
+    pub async fn mock_result(server: &MockServer, rpc_method: &str, result: Value) {
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "method": rpc_method })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": result,
+                "error": null
+            })))
+            .mount(server)
+            .await;
+    }
+
+    /// 按 JSON-RPC method + params 挂载 mock，同一 method 不同参数返回不同结果
+    pub async fn mock_result_with_params(
+        server: &MockServer,
+        rpc_method: &str,
+        params: Value,
+        result: Value,
+    ) {
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                json!({ "method": rpc_method, "params": params }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": result,
+                "error": null
+            })))
+            .mount(server)
+            .await;
+    }
+
+    /// 按 JSON-RPC method 挂载一个返回 error 对象的 mock（bitcoind 出错时返回 HTTP 500）
+    pub async fn mock_error(server: &MockServer, rpc_method: &str, code: i64, message: &str) {
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "method": rpc_method })))
+            .respond_with(ResponseTemplate::new(500).set_body_json(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": null,
+                "error": { "code": code, "message": message }
+            })))
+            .mount(server)
+            .await;
+    }
+
+    /// 挂载 batch 请求的 mock：对数组里的每个调用用 respond(method, params) 生成 result
+    pub async fn mock_batch(
+        server: &MockServer,
+        respond: impl Fn(&str, &Value) -> Value + Send + Sync + 'static,
+    ) {
+        Mock::given(method("POST"))
+            .respond_with(move |req: &Request| {
+                let calls: Vec<Value> = req.body_json().unwrap();
+                let responses: Vec<Value> = calls
+                    .iter()
+                    .map(|call| {
+                        let result = respond(call["method"].as_str().unwrap(), &call["params"]);
+                        json!({ "jsonrpc": "2.0", "id": call["id"], "result": result, "error": null })
+                    })
+                    .collect();
+                ResponseTemplate::new(200).set_body_json(responses)
+            })
+            .mount(server)
+            .await;
+    }
+
+    /// 指向 mock server（http://127.0.0.1:<port>）的 client
+    pub fn client_for(server: &MockServer) -> AlchemyClient {
+        AlchemyClient::new(&server.uri(), bitcoin::Network::Testnet)
+    }
+
+    /// 取出 mock server 收到的所有指定 method 的请求体
+    pub async fn requests_for(server: &MockServer, rpc_method: &str) -> Vec<Value> {
+        server
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .iter()
+            .map(|r| r.body_json::<Value>().unwrap())
+            .filter(|body| body["method"] == rpc_method)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock_rpc::*;
+    use super::{
+        AlchemyError, DEFAULT_MAX_FEE_RATE, MempoolInfo, PackageResult, PrevoutCache, TEST_TXID,
+        parse_btc_amount,
+    };
+    use crate::rune_decode::RuneId;
+    use bitcoin::{Amount, FeeRate, Network, OutPoint, Transaction};
+    use serde_json::{Value, json};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio_util::sync::CancellationToken;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_get_tx_out_returns_some() {
+        let server = MockServer::start().await;
+        mock_result(
+            &server,
+            "gettxout",
+            json!({
+                "bestblock": "0000000000000000000123",
+                "confirmations": 3,
+                "value": 0.0001,
+                "scriptPubKey": {
+                    "asm": "1 abcd",
+                    "hex": "5120abcd",
+                    "address": "tb1pqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesf3hn0c"
+                },
+                "coinbase": false
+            }),
+        )
+        .await;
+
+        let tx_out = client_for(&server)
+            .get_tx_out(TEST_TXID, 1, true)
+            .await
+            .unwrap()
+            .expect("expected Some(TxOut)");
+
+        assert_eq!(tx_out.value, 10_000);
+        assert_eq!(tx_out.confirmations, 3);
+        assert_eq!(tx_out.script_pubkey.script.to_hex_string(), "5120abcd");
+        assert_eq!(
+            tx_out.script_pubkey.address.unwrap().to_string(),
+            "tb1pqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesf3hn0c"
+        );
+        assert_eq!(tx_out.txid.to_string(), TEST_TXID);
+        assert_eq!(tx_out.vout, 1);
+        assert!(!tx_out.in_mempool);
+    }
+
+    #[tokio::test]
+    async fn test_get_tx_out_mempool_vs_confirmed() {
+        // mempool 中的 UTXO：0 确认，bestblock 仍是当前链顶
+        let server = MockServer::start().await;
+        mock_result(
+            &server,
+            "gettxout",
+            json!({
+                "bestblock": "0000000000000000000123",
+                "confirmations": 0,
+                "value": 0.0001,
+                "scriptPubKey": { "asm": "", "hex": "5120abcd" },
+                "coinbase": false
+            }),
+        )
+        .await;
+        let tx_out = client_for(&server)
+            .get_tx_out(TEST_TXID, 0, true)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(tx_out.confirmations, 0);
+        assert!(tx_out.in_mempool);
+
+        // 1 确认
+        let server = MockServer::start().await;
+        mock_result(
+            &server,
+            "gettxout",
+            json!({
+                "bestblock": "0000000000000000000123",
+                "confirmations": 1,
+                "value": 0.0001,
+                "scriptPubKey": { "asm": "", "hex": "5120abcd" },
+                "coinbase": false
+            }),
+        )
+        .await;
+        let tx_out = client_for(&server)
+            .get_tx_out(TEST_TXID, 0, true)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(tx_out.confirmations, 1);
+        assert!(!tx_out.in_mempool);
+    }
+
+    #[tokio::test]
+    async fn test_get_tx_out_missing_script_hex_is_error() {
+        let server = MockServer::start().await;
+        mock_result(
+            &server,
+            "gettxout",
+            json!({
+                "bestblock": "0000000000000000000123",
+                "confirmations": 1,
+                "value": 0.0001,
+                "scriptPubKey": { "asm": "" },
+                "coinbase": false
+            }),
+        )
+        .await;
+
+        let err = client_for(&server)
+            .get_tx_out(TEST_TXID, 0, true)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("scriptPubKey.hex"));
+    }
+
+    #[tokio::test]
+    async fn test_get_tx_out_parses_max_supply_value_exactly() {
+        let server = MockServer::start().await;
+        mock_result(
+            &server,
+            "gettxout",
+            json!({
+                "bestblock": "0000000000000000000123",
+                "confirmations": 1,
+                "value": 20999999.99999999,
+                "scriptPubKey": { "asm": "", "hex": "5120abcd" },
+                "coinbase": false
+            }),
+        )
+        .await;
+
+        let tx_out = client_for(&server)
+            .get_tx_out(TEST_TXID, 0, true)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(tx_out.value, 2_099_999_999_999_999);
+    }
+
+    #[test]
+    fn test_parse_btc_amount() {
+        // 超过 2100 万 BTC 上限
+        assert!(parse_btc_amount(&json!("21000000.00000001")).is_err());
+        assert_eq!(parse_btc_amount(&json!(0.00000546)).unwrap().to_sat(), 546);
+        assert_eq!(parse_btc_amount(&json!(1e-8)).unwrap().to_sat(), 1);
+        assert_eq!(
+            parse_btc_amount(&json!(50)).unwrap().to_sat(),
+            5_000_000_000
+        );
+        assert!(parse_btc_amount(&json!(-0.1)).is_err());
+
+        // 按 JSON 原文解析：f64 无法精确表示的 16 位有效数字也不丢精度
+        let near_max: Value = serde_json::from_str("20999999.99999999").unwrap();
+        assert_eq!(
+            parse_btc_amount(&near_max).unwrap().to_sat(),
+            2_099_999_999_999_999
+        );
+        let tiny: Value = serde_json::from_str("1.5E-7").unwrap();
+        assert_eq!(parse_btc_amount(&tiny).unwrap().to_sat(), 15);
+
+        // 缺失或非数字的值是错误，不当作 0
+        assert!(parse_btc_amount(&Value::Null).is_err());
+        assert!(parse_btc_amount(&json!(true)).is_err());
+        assert!(parse_btc_amount(&json!("abc")).is_err());
+    }
+
+    /// 父子两笔交易，父交易的 wtxid 按字符串排序在子交易之后，
+    /// 和 tx-results 对象 key 的顺序相反
+    fn package_txs() -> Vec<Transaction> {
+        let tx = |vout: u32| Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![bitcoin::TxIn {
+                previous_output: OutPoint {
+                    txid: TEST_TXID.parse().unwrap(),
+                    vout,
+                },
+                ..Default::default()
+            }],
+            output: vec![bitcoin::TxOut {
+                value: Amount::from_sat(10_000),
+                script_pubkey: bitcoin::ScriptBuf::new(),
+            }],
+        };
+        let mut txs: Vec<Transaction> = (0..2).map(tx).collect();
+        txs.sort_by_key(|tx| std::cmp::Reverse(tx.compute_wtxid().to_string()));
+        txs
+    }
+
+    #[test]
+    fn test_package_result_success() {
+        let txs = package_txs();
+        let (parent, child) = (&txs[0], &txs[1]);
+        let result = PackageResult::from_rpc_result(
+            &json!({
+                "package_msg": "success",
+                "tx-results": {
+                    child.compute_wtxid().to_string(): {
+                        "txid": child.compute_txid().to_string(),
+                        "vsize": 150,
+                        "fees": {
+                            "base": 0.00025989,
+                            "effective-feerate": 0.00010000
+                        }
+                    },
+                    parent.compute_wtxid().to_string(): {
+                        "txid": parent.compute_txid().to_string(),
+                        "vsize": 111,
+                        "fees": {
+                            "base": 0.00000111,
+                            "effective-feerate": 0.00010000
+                        }
+                    }
+                },
+                "replaced-transactions": []
+            }),
+            &txs,
+        )
+        .unwrap();
+
+        assert!(result.is_success());
+        assert_eq!(result.tx_results.len(), 2);
+        // 结果按提交顺序排列，而不是 tx-results 的 key 顺序
+        let parent_res = &result.tx_results[0];
+        assert_eq!(parent_res.wtxid, parent.compute_wtxid());
+        assert_eq!(parent_res.txid, parent.compute_txid());
+        assert_eq!(parent_res.vsize, Some(111));
+        assert_eq!(parent_res.base_fee, Some(Amount::from_sat(111)));
+        assert_eq!(
+            parent_res.effective_feerate_per_kvb,
+            Some(Amount::from_sat(10_000))
+        );
+        assert!(parent_res.error.is_none());
+        assert_eq!(result.tx_results[1].txid, child.compute_txid());
+        assert_eq!(result.tx_results[1].vsize, Some(150));
+    }
+
+    #[test]
+    fn test_package_result_rejected() {
+        let txs = package_txs();
+        let result = PackageResult::from_rpc_result(
+            &json!({
+                "package_msg": "transaction failed",
+                "tx-results": {
+                    txs[0].compute_wtxid().to_string(): {
+                        "txid": txs[0].compute_txid().to_string(),
+                        "error": "min relay fee not met, 111 < 222"
+                    }
+                },
+                "replaced-transactions": []
+            }),
+            &txs,
+        )
+        .unwrap();
+
+        assert!(!result.is_success());
+        assert_eq!(
+            result.tx_results[0].error.as_deref(),
+            Some("min relay fee not met, 111 < 222")
+        );
+        assert!(result.tx_results[0].base_fee.is_none());
+        // 子交易没有出现在 tx-results 中
+        assert_eq!(result.tx_results[1].txid, txs[1].compute_txid());
+        assert!(result.tx_results[1].error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_submit_package_topology_rejection() {
+        let server = MockServer::start().await;
+        mock_error(
+            &server,
+            "submitpackage",
+            -25,
+            "package-not-child-with-unconfirmed-parents",
+        )
+        .await;
+
+        let result = client_for(&server).submit_package(&[]).await.unwrap();
+
+        assert!(!result.is_success());
+        assert!(result.tx_results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_check_network_accepts_signet_and_regtest() {
+        for (chain, network) in [("signet", Network::Signet), ("regtest", Network::Regtest)] {
+            let server = MockServer::start().await;
+            mock_result(
+                &server,
+                "getblockchaininfo",
+                json!({ "chain": chain, "blocks": 1 }),
+            )
+            .await;
+
+            let client = client_for(&server);
+            assert!(client.check_network(network).await.is_ok());
+            assert!(client.check_network(Network::Testnet).await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rune_id_for_txid() {
+        let server = MockServer::start().await;
+        mock_result(
+            &server,
+            "getrawtransaction",
+            json!({ "txid": TEST_TXID, "blockhash": "00ab", "confirmations": 2 }),
+        )
+        .await;
+        mock_result(
+            &server,
+            "getblock",
+            json!({
+                "hash": "00ab",
+                "height": 2_500_123,
+                "tx": ["aa", "bb", TEST_TXID, "cc"]
+            }),
+        )
+        .await;
+
+        let rune_id = client_for(&server)
+            .rune_id_for_txid(TEST_TXID)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            rune_id,
+            RuneId {
+                block: 2_500_123,
+                tx: 2
+            }
+        );
+        assert_eq!(
+            requests_for(&server, "getblock").await[0]["params"],
+            json!(["00ab", 1])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rune_id_for_unconfirmed_txid_errors() {
+        let server = MockServer::start().await;
+        mock_result(
+            &server,
+            "getrawtransaction",
+            json!({ "txid": TEST_TXID, "hex": "02000000" }),
+        )
+        .await;
+
+        let err = client_for(&server)
+            .rune_id_for_txid(TEST_TXID)
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("not confirmed"));
+        assert!(requests_for(&server, "getblock").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_is_rbf_signaled() {
+        let non_signaling = "aa".repeat(32);
+        let server = MockServer::start().await;
+        mock_result_with_params(
+            &server,
+            "getrawtransaction",
+            json!([TEST_TXID, true]),
+            json!({
+                "txid": TEST_TXID,
+                "vin": [{ "sequence": 4294967295u64 }, { "sequence": 4294967293u64 }]
+            }),
+        )
+        .await;
+        mock_result_with_params(
+            &server,
+            "getrawtransaction",
+            json!([non_signaling, true]),
+            json!({
+                "txid": non_signaling,
+                "vin": [{ "sequence": 4294967295u64 }, { "sequence": 4294967294u64 }]
+            }),
+        )
+        .await;
+
+        let client = client_for(&server);
+        assert!(client.is_rbf_signaled(TEST_TXID).await.unwrap());
+        assert!(!client.is_rbf_signaled(&non_signaling).await.unwrap());
+        assert!(client.is_rbf_signaled("not a txid").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_mempool_info() {
+        let server = MockServer::start().await;
+        mock_result(
+            &server,
+            "getmempoolinfo",
+            json!({
+                "loaded": true,
+                "size": 1200,
+                "mempoolminfee": 0.00002,
+                "minrelaytxfee": 0.00001
+            }),
+        )
+        .await;
+
+        let info = client_for(&server).get_mempool_info().await.unwrap();
+
+        assert_eq!(info.mempool_min_fee, FeeRate::from_sat_per_vb(2).unwrap());
+        assert_eq!(info.min_relay_tx_fee, FeeRate::from_sat_per_vb(1).unwrap());
+        assert_eq!(info.relay_floor(), FeeRate::from_sat_per_vb(2).unwrap());
+    }
+
+    #[test]
+    fn test_fee_rate_below_relay_floor_is_bumped() {
+        let info = MempoolInfo {
+            mempool_min_fee: FeeRate::from_sat_per_kwu(250),
+            // 0.000015 BTC/kvB = 1.5 sat/vB
+            min_relay_tx_fee: FeeRate::from_sat_per_kwu(375),
+        };
+
+        assert_eq!(info.relay_floor(), FeeRate::from_sat_per_kwu(375));
+        assert_eq!(
+            info.floor_fee_rate(FeeRate::from_sat_per_kwu(100)),
+            FeeRate::from_sat_per_kwu(375)
+        );
+        // 高于下限的费率保持不变
+        assert_eq!(
+            info.floor_fee_rate(FeeRate::from_sat_per_vb(5).unwrap()),
+            FeeRate::from_sat_per_vb(5).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_call_parses_integer_result() {
+        let server = MockServer::start().await;
+        mock_result(&server, "getblockcount", json!(2_500_000)).await;
+
+        let result = client_for(&server)
+            .call("getblockcount", json!([]))
+            .await
+            .unwrap();
+
+        assert_eq!(result.as_u64(), Some(2_500_000));
+    }
+
+    #[tokio::test]
+    async fn test_call_surfaces_rpc_error() {
+        let server = MockServer::start().await;
+        mock_error(&server, "getmempoolentry", -5, "Transaction not in mempool").await;
+
+        let err = client_for(&server)
+            .call("getmempoolentry", json!([TEST_TXID]))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            AlchemyError::Rpc { code: -5, ref message } if message == "Transaction not in mempool"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_rejects_fee_above_local_cap() {
+        use bitcoin::transaction::Version;
+        use bitcoin::{OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness, absolute};
+
+        let server = MockServer::start().await;
+        let script_pubkey = ScriptBuf::from_hex(
+            "51203b82b2b2a9185315da6f80da5f06d0440d8a5e1457fa93387c2d919c86ec8786",
+        )
+        .unwrap();
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness: Witness::from_slice(&[[0u8; 64]]),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(1_000),
+                script_pubkey: script_pubkey.clone(),
+            }],
+        };
+        // 100_000 sat 的输入只给 1_000 sat 输出，费率远超 50 sat/vB
+        let prevouts = [TxOut {
+            value: Amount::from_sat(100_000),
+            script_pubkey,
+        }];
+
+        let err = client_for(&server)
+            .broadcast_tx_with_prevouts(&tx, &prevouts, FeeRate::from_sat_per_vb(50).unwrap())
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("exceeds cap"));
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+
+    fn dummy_tx() -> bitcoin::Transaction {
+        use bitcoin::transaction::Version;
+        use bitcoin::{ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness, absolute};
+
+        Transaction {
+            version: Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: outpoint(0),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness: Witness::from_slice(&[[0u8; 64]]),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(1_000),
+                script_pubkey: ScriptBuf::from_hex(
+                    "51203b82b2b2a9185315da6f80da5f06d0440d8a5e1457fa93387c2d919c86ec8786",
+                )
+                .unwrap(),
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_already_in_mempool_is_success() {
+        let tx = dummy_tx();
+
+        // 重试时节点返回 "已在 mempool"，视为成功并返回本地计算的 txid
+        let server = MockServer::start().await;
+        mock_error(&server, "sendrawtransaction", -26, "txn-already-in-mempool").await;
+        let txid = client_for(&server).broadcast_tx_default(&tx).await.unwrap();
+        assert_eq!(txid, tx.compute_txid().to_string());
+
+        // -27：已经上链
+        let server = MockServer::start().await;
+        mock_error(
+            &server,
+            "sendrawtransaction",
+            -27,
+            "Transaction outputs already in utxo set",
+        )
+        .await;
+        let txid = client_for(&server).broadcast_tx_default(&tx).await.unwrap();
+        assert_eq!(txid, tx.compute_txid().to_string());
+
+        // 其他拒绝原因仍然是错误
+        let server = MockServer::start().await;
+        mock_error(&server, "sendrawtransaction", -26, "min relay fee not met").await;
+        let err = client_for(&server)
+            .broadcast_tx_default(&tx)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("min relay fee not met"));
+    }
+
+    #[test]
+    fn test_default_max_fee_rate_matches_core_units() {
+        assert_eq!(DEFAULT_MAX_FEE_RATE.to_sat_per_vb_floor(), 10_000);
+    }
+
+    /// batch gettxout mock：倒序返回，vout 在 spent 中的返回 null
+    async fn mount_batch_gettxout(server: &MockServer, spent: &'static [u64]) {
+        Mock::given(method("POST"))
+            .respond_with(move |req: &Request| {
+                let calls: Vec<Value> = req.body_json().unwrap();
+                let responses: Vec<Value> = calls
+                    .iter()
+                    .rev()
+                    .map(|call| {
+                        let vout = call["params"][1].as_u64().unwrap();
+                        let result = if spent.contains(&vout) {
+                            Value::Null
+                        } else {
+                            json!({
+                                "bestblock": "0000000000000000000123",
+                                "confirmations": 1,
+                                "value": Amount::from_sat(10_000 * (vout + 1)).to_btc(),
+                                "scriptPubKey": { "asm": "", "hex": "5120abcd" },
+                                "coinbase": false
+                            })
+                        };
+                        json!({ "jsonrpc": "2.0", "id": call["id"], "result": result, "error": null })
+                    })
+                    .collect();
+                ResponseTemplate::new(200).set_body_json(responses)
+            })
+            .mount(server)
+            .await;
+    }
+
+    fn outpoint(vout: u32) -> OutPoint {
+        OutPoint {
+            txid: TEST_TXID.parse().unwrap(),
+            vout,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prevout_cache_fetches_all_inputs_in_one_batch() {
+        let server = MockServer::start().await;
+        mount_batch_gettxout(&server, &[]).await;
+        let alchemy = client_for(&server);
+
+        let outpoints: Vec<OutPoint> = (0..5).map(outpoint).collect();
+        let mut cache = PrevoutCache::new();
+        cache.fetch(&alchemy, &outpoints).await.unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].body_json::<Vec<Value>>().unwrap().len(), 5);
+
+        // 按 id 对应回去，而不是按响应顺序
+        for (vout, op) in outpoints.iter().enumerate() {
+            assert_eq!(
+                cache.get(op).unwrap().value,
+                Amount::from_sat(10_000 * (vout as u64 + 1))
+            );
+        }
+
+        // 已缓存的不再请求
+        cache.fetch(&alchemy, &outpoints[1..3]).await.unwrap();
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_prevouts_from_raw_transactions() {
+        let tx_with_values = |values: &[u64]| bitcoin::Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![bitcoin::TxIn::default()],
+            output: values
+                .iter()
+                .map(|value| bitcoin::TxOut {
+                    value: Amount::from_sat(*value),
+                    script_pubkey: bitcoin::ScriptBuf::new_op_return([*value as u8]),
+                })
+                .collect(),
+        };
+        let first = tx_with_values(&[1_000, 2_000]);
+        let second = tx_with_values(&[3_000, 4_000, 5_000]);
+
+        let server = MockServer::start().await;
+        let txs: HashMap<String, String> = [&first, &second]
+            .iter()
+            .map(|tx| {
+                (
+                    tx.compute_txid().to_string(),
+                    bitcoin::consensus::encode::serialize_hex(*tx),
+                )
+            })
+            .collect();
+        mock_batch(&server, move |_, params| {
+            json!(txs[params[0].as_str().unwrap()])
+        })
+        .await;
+        let alchemy = client_for(&server);
+
+        let op = |tx: &bitcoin::Transaction, vout| OutPoint {
+            txid: tx.compute_txid(),
+            vout,
+        };
+        let prevouts = alchemy
+            .fetch_prevouts(&[op(&first, 1), op(&second, 2), op(&first, 0)])
+            .await
+            .unwrap();
+        assert_eq!(
+            prevouts,
+            vec![
+                first.output[1].clone(),
+                second.output[2].clone(),
+                first.output[0].clone()
+            ]
+        );
+        // 同一笔交易只取一次，两笔交易一次 batch
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].body_json::<Vec<Value>>().unwrap().len(), 2);
+
+        let err = alchemy
+            .fetch_prevouts(&[op(&second, 0), op(&first, 2)])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("out of range"));
+    }
+
+    #[tokio::test]
+    async fn test_rpc_stats_count_calls_and_requests() {
+        // 单个调用的 mock 先挂载，batch（数组请求体）才会落到 mount_batch_gettxout
+        let server = MockServer::start().await;
+        mock_result(
+            &server,
+            "gettxout",
+            json!({
+                "bestblock": "00",
+                "confirmations": 1,
+                "value": 0.0001,
+                "scriptPubKey": { "asm": "", "hex": "5120abcd" },
+                "coinbase": false
+            }),
+        )
+        .await;
+        mount_batch_gettxout(&server, &[]).await;
+
+        // 默认不统计
+        assert!(client_for(&server).stats().is_none());
+
+        // 逐个请求：3 次调用，3 次 HTTP 请求
+        let alchemy = client_for(&server).with_stats();
+        alchemy
+            .get_multiple_tx_outs(&[(TEST_TXID, 0), (TEST_TXID, 1), (TEST_TXID, 2)])
+            .await
+            .unwrap();
+        let stats = alchemy.stats().unwrap();
+        assert_eq!(stats.method("gettxout").count, 3);
+        assert_eq!(stats.requests(), 3);
+
+        // batch：3 次调用，1 次 HTTP 请求
+        let alchemy = client_for(&server).with_stats();
+        let outpoints: Vec<OutPoint> = (0..3).map(outpoint).collect();
+        alchemy.get_tx_outs_batch(&outpoints).await.unwrap();
+        let stats = alchemy.stats().unwrap();
+        assert_eq!(stats.method("gettxout").count, 3);
+        assert_eq!(stats.requests(), 1);
+        assert_eq!(stats.method("sendrawtransaction").count, 0);
+        assert_eq!(
+            stats.snapshot().keys().collect::<Vec<_>>(),
+            vec!["gettxout"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prevout_cache_spent_prevout_is_error() {
+        let server = MockServer::start().await;
+        mount_batch_gettxout(&server, &[2]).await;
+
+        let outpoints: Vec<OutPoint> = (0..3).map(outpoint).collect();
+        let mut cache = PrevoutCache::new();
+        let err = cache
+            .fetch(&client_for(&server), &outpoints)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains(":2 is spent or does not exist"));
+    }
+
+    #[tokio::test]
+    async fn test_fee_recommendations_are_monotonic() {
+        // 节点返回倒挂的估算：6 个区块比 1 个区块还贵
+        let server = MockServer::start().await;
+        for (target, feerate) in [(1, 0.0001), (6, 0.0002), (144, 0.00005)] {
+            mock_result_with_params(
+                &server,
+                "estimatesmartfee",
+                json!([target]),
+                json!({ "feerate": feerate, "blocks": target }),
+            )
+            .await;
+        }
+
+        let fees = client_for(&server).fee_recommendations().await.unwrap();
+        assert_eq!(fees.fast, FeeRate::from_sat_per_vb(10).unwrap());
+        assert_eq!(fees.medium, FeeRate::from_sat_per_vb(10).unwrap());
+        assert_eq!(fees.slow, FeeRate::from_sat_per_vb(5).unwrap());
+
+        // 144 没有估算数据时使用 relay 下限（这里 2 sat/vB）
+        let server = MockServer::start().await;
+        for (target, feerate) in [(1, 0.0001), (6, 0.00003)] {
+            mock_result_with_params(
+                &server,
+                "estimatesmartfee",
+                json!([target]),
+                json!({ "feerate": feerate, "blocks": target }),
+            )
+            .await;
+        }
+        mock_result_with_params(
+            &server,
+            "estimatesmartfee",
+            json!([144]),
+            json!({ "errors": ["Insufficient data or no feerate found"], "blocks": 0 }),
+        )
+        .await;
+        mock_result(
+            &server,
+            "getmempoolinfo",
+            json!({ "mempoolminfee": 0.00002, "minrelaytxfee": 0.00001 }),
+        )
+        .await;
+
+        let fees = client_for(&server).fee_recommendations().await.unwrap();
+        assert_eq!(fees.fast, FeeRate::from_sat_per_vb(10).unwrap());
+        assert_eq!(fees.medium, FeeRate::from_sat_per_vb(3).unwrap());
+        assert_eq!(fees.slow, FeeRate::from_sat_per_vb(2).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_get_tx_out_spent_returns_none() {
+        let server = MockServer::start().await;
+        mock_result(&server, "gettxout", json!(null)).await;
+
+        let tx_out = client_for(&server)
+            .get_tx_out(TEST_TXID, 0, true)
+            .await
+            .unwrap();
+
+        assert!(tx_out.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_tx_out_rejects_malformed_txid() {
+        let server = MockServer::start().await;
+
+        let err = client_for(&server)
+            .get_tx_out("43e447c5cb", 0, true)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<AlchemyError>(),
+            Some(AlchemyError::InvalidTxid(txid)) if txid == "43e447c5cb"
+        ));
+        assert!(requests_for(&server, "gettxout").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_tx_out_rejects_malformed_script_hex() {
+        let server = MockServer::start().await;
+        mock_result(
+            &server,
+            "gettxout",
+            json!({
+                "bestblock": "0000000000000000000123",
+                "confirmations": 3,
+                "value": 0.0001,
+                "scriptPubKey": { "asm": "", "hex": "51zz" }
+            }),
+        )
+        .await;
+
+        let err = client_for(&server)
+            .get_tx_out(TEST_TXID, 0, true)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<AlchemyError>(),
+            Some(AlchemyError::InvalidScriptPubKey(hex)) if hex == "51zz"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_tx_out_error_returns_err() {
+        let server = MockServer::start().await;
+        mock_error(&server, "gettxout", -8, "Invalid parameter").await;
+
+        let result = client_for(&server).get_tx_out(TEST_TXID, 0, true).await;
+
+        assert!(result.is_err());
+    }
+
+    /// scantxoutset mock：start 延迟返回，status 每次进度 +25%（最多 99），abort 返回 true
+    async fn mock_scantxoutset(server: &MockServer, start_delay: Duration) {
+        let polls = Arc::new(AtomicUsize::new(0));
+        Mock::given(method("POST"))
+            .respond_with(move |req: &Request| {
+                let body: Value = req.body_json().unwrap();
+                let (result, delay) = match body["params"][0].as_str().unwrap() {
+                    "start" => (
+                        json!({
+                            "success": true,
+                            "height": 100,
+                            "unspents": [
+                                { "txid": TEST_TXID, "vout": 1, "amount": 0.0001, "height": 91 }
+                            ]
+                        }),
+                        start_delay,
+                    ),
+                    "status" => {
+                        let n = polls.fetch_add(1, Ordering::SeqCst) + 1;
+                        (
+                            json!({ "progress": (n as f64 * 25.0).min(99.0) }),
+                            Duration::ZERO,
+                        )
+                    }
+                    _ => (json!(true), Duration::ZERO),
+                };
+                ResponseTemplate::new(200)
+                    .set_body_json(
+                        json!({ "jsonrpc": "2.0", "id": 1, "result": result, "error": null }),
+                    )
+                    .set_delay(delay)
+            })
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_scan_utxos_with_progress_reports_progress() {
+        let server = MockServer::start().await;
+        mock_scantxoutset(&server, Duration::from_millis(300)).await;
+
+        let mut progress = Vec::new();
+        let utxos = client_for(&server)
+            .scan_utxos_with_progress(
+                &["addr(tb1qexample)".to_string()],
+                Duration::from_millis(50),
+                |p| progress.push(p),
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(utxos.len(), 1);
+        assert_eq!(utxos[0].value, 10_000);
+        assert_eq!(utxos[0].confirmations, Some(10));
+        assert!(progress.len() >= 2);
+        assert!(progress.windows(2).all(|w| w[0] <= w[1]));
+        assert!(progress[0] < progress[progress.len() - 1]);
+        assert!(
+            requests_for(&server, "scantxoutset")
+                .await
+                .iter()
+                .all(|b| b["params"][0] != "abort")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scan_utxos_with_progress_cancel_aborts() {
+        let server = MockServer::start().await;
+        mock_scantxoutset(&server, Duration::from_secs(2)).await;
+
+        let cancel = CancellationToken::new();
+        let canceller = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            canceller.cancel();
+        });
+
+        let err = client_for(&server)
+            .scan_utxos_with_progress(
+                &["addr(tb1qexample)".to_string()],
+                Duration::from_millis(30),
+                |_| {},
+                &cancel,
+            )
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("cancelled"));
+        let requests = requests_for(&server, "scantxoutset").await;
+        assert!(requests.iter().any(|b| b["params"] == json!(["abort"])));
     }
 }
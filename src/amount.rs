@@ -0,0 +1,208 @@
+/// =====================================================
+/// sat <-> BTC 十进制字符串转换
+/// =====================================================
+///
+/// 全程使用整数运算，不经过 f64，避免 0.1 + 0.2 一类的精度问题
+pub const SATS_PER_BTC: u64 = 100_000_000;
+
+/// 比特币总量上限：2100 万 BTC
+pub const MAX_MONEY_SATS: u64 = 21_000_000 * SATS_PER_BTC;
+
+const BTC_DECIMALS: usize = 8;
+
+/// 把聪格式化为 BTC 字符串，小数部分去掉末尾的 0
+///
+/// 1 sat -> "0.00000001"，10_000_000 sat -> "0.1"，100_000_000 sat -> "1"
+pub fn sat_to_btc_string(sats: u64) -> String {
+    let whole = sats / SATS_PER_BTC;
+    let frac = sats % SATS_PER_BTC;
+    if frac == 0 {
+        return whole.to_string();
+    }
+
+    let frac = format!("{:0width$}", frac, width = BTC_DECIMALS);
+    format!("{}.{}", whole, frac.trim_end_matches('0'))
+}
+
+/// 把 BTC 十进制字符串解析为聪
+///
+/// 只接受 `整数[.小数]`，小数最多 8 位，结果不能超过 2100 万 BTC
+pub fn btc_string_to_sat(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (whole, frac) = s.split_once('.').unwrap_or((s, ""));
+
+    if whole.is_empty() && frac.is_empty() {
+        return Err(format!("BTC 金额无效: {:?}", s));
+    }
+    if !whole.bytes().all(|b| b.is_ascii_digit()) || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("BTC 金额无效: {:?}", s));
+    }
+    if frac.len() > BTC_DECIMALS {
+        return Err(format!("BTC 金额最多 {} 位小数: {}", BTC_DECIMALS, s));
+    }
+
+    let whole: u64 = if whole.is_empty() {
+        0
+    } else {
+        whole
+            .parse()
+            .map_err(|_| format!("BTC 金额超出范围: {}", s))?
+    };
+    let frac: u64 = if frac.is_empty() {
+        0
+    } else {
+        format!("{:0<width$}", frac, width = BTC_DECIMALS)
+            .parse()
+            .map_err(|_| format!("BTC 金额无效: {:?}", s))?
+    };
+
+    let sats = whole
+        .checked_mul(SATS_PER_BTC)
+        .and_then(|w| w.checked_add(frac))
+        .filter(|sats| *sats <= MAX_MONEY_SATS)
+        .ok_or(format!("BTC 金额超过 2100 万 BTC 上限: {}", s))?;
+
+    Ok(sats)
+}
+
+/// =====================================================
+/// rune 数量 <-> 十进制字符串转换
+/// =====================================================
+///
+/// runestone 里的数量都是最小单位的整数，显示时按 etching 的 divisibility
+/// 在右起第 divisibility 位插入小数点。divisibility 最大 38，超出 f64 精度，
+/// 同样全程用 u128 和字符串运算
+///
+/// 100_000_000 @ 8 -> "1.00000000"，5 @ 2 -> "0.05"，42 @ 0 -> "42"
+pub fn format_rune_amount(raw: u128, divisibility: u8) -> String {
+    let digits = format!("{:0>width$}", raw, width = divisibility as usize + 1);
+    if divisibility == 0 {
+        return digits;
+    }
+    let (whole, frac) = digits.split_at(digits.len() - divisibility as usize);
+    format!("{}.{}", whole, frac)
+}
+
+/// 把十进制字符串解析为 rune 的最小单位数量
+///
+/// 只接受 `整数[.小数]`，小数位数不能超过 divisibility，结果不能超过 u128
+pub fn parse_rune_amount(s: &str, divisibility: u8) -> Result<u128, String> {
+    let s = s.trim();
+    let (whole, frac) = s.split_once('.').unwrap_or((s, ""));
+
+    if whole.is_empty() && frac.is_empty() {
+        return Err(format!("rune 数量无效: {:?}", s));
+    }
+    if !whole.bytes().all(|b| b.is_ascii_digit()) || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("rune 数量无效: {:?}", s));
+    }
+    if frac.len() > divisibility as usize {
+        return Err(format!("rune 数量最多 {} 位小数: {}", divisibility, s));
+    }
+
+    let digits = format!("{}{:0<width$}", whole, frac, width = divisibility as usize);
+    let digits = digits.trim_start_matches('0');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    digits
+        .parse()
+        .map_err(|_| format!("rune 数量超出范围: {}", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sat_to_btc_string() {
+        assert_eq!(sat_to_btc_string(0), "0");
+        assert_eq!(sat_to_btc_string(1), "0.00000001");
+        assert_eq!(sat_to_btc_string(10_000_000), "0.1");
+        assert_eq!(sat_to_btc_string(SATS_PER_BTC), "1");
+        assert_eq!(sat_to_btc_string(MAX_MONEY_SATS - 1), "20999999.99999999");
+        assert_eq!(sat_to_btc_string(MAX_MONEY_SATS), "21000000");
+    }
+
+    #[test]
+    fn test_btc_string_to_sat() {
+        assert_eq!(btc_string_to_sat("0.00000001"), Ok(1));
+        assert_eq!(btc_string_to_sat("0.1"), Ok(10_000_000));
+        assert_eq!(btc_string_to_sat(".1"), Ok(10_000_000));
+        assert_eq!(btc_string_to_sat("1."), Ok(SATS_PER_BTC));
+        assert_eq!(
+            btc_string_to_sat("20999999.99999999"),
+            Ok(MAX_MONEY_SATS - 1)
+        );
+        assert_eq!(btc_string_to_sat("21000000"), Ok(MAX_MONEY_SATS));
+
+        // 超过 8 位小数、超过上限、格式错误
+        assert!(btc_string_to_sat("0.000000001").is_err());
+        assert!(btc_string_to_sat("21000000.00000001").is_err());
+        assert!(btc_string_to_sat("99999999999999999999").is_err());
+        assert!(btc_string_to_sat("-1").is_err());
+        assert!(btc_string_to_sat("1e-8").is_err());
+        assert!(btc_string_to_sat(".").is_err());
+        assert!(btc_string_to_sat("").is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for sats in [1, 546, 10_000_000, 123_456_789, MAX_MONEY_SATS - 1] {
+            assert_eq!(btc_string_to_sat(&sat_to_btc_string(sats)), Ok(sats));
+        }
+    }
+
+    #[test]
+    fn test_format_rune_amount() {
+        assert_eq!(format_rune_amount(42, 0), "42");
+        assert_eq!(format_rune_amount(0, 0), "0");
+
+        assert_eq!(format_rune_amount(100_000_000, 8), "1.00000000");
+        assert_eq!(format_rune_amount(5, 8), "0.00000005");
+        assert_eq!(format_rune_amount(0, 8), "0.00000000");
+
+        assert_eq!(
+            format_rune_amount(1, 38),
+            "0.00000000000000000000000000000000000001"
+        );
+        assert_eq!(
+            format_rune_amount(u128::MAX, 38),
+            "3.40282366920938463463374607431768211455"
+        );
+    }
+
+    #[test]
+    fn test_parse_rune_amount() {
+        assert_eq!(parse_rune_amount("42", 0), Ok(42));
+        assert!(parse_rune_amount("4.2", 0).is_err());
+
+        assert_eq!(parse_rune_amount("1.00000000", 8), Ok(100_000_000));
+        assert_eq!(parse_rune_amount("1", 8), Ok(100_000_000));
+        assert_eq!(parse_rune_amount(".5", 8), Ok(50_000_000));
+        assert_eq!(parse_rune_amount("0.00000000", 8), Ok(0));
+        assert!(parse_rune_amount("0.000000001", 8).is_err());
+
+        assert_eq!(
+            parse_rune_amount("3.40282366920938463463374607431768211455", 38),
+            Ok(u128::MAX)
+        );
+        // 超过 u128
+        assert!(parse_rune_amount("3.40282366920938463463374607431768211456", 38).is_err());
+        assert!(parse_rune_amount("4", 38).is_err());
+
+        assert!(parse_rune_amount("-1", 8).is_err());
+        assert!(parse_rune_amount(".", 8).is_err());
+        assert!(parse_rune_amount("", 8).is_err());
+    }
+
+    #[test]
+    fn test_rune_amount_round_trip() {
+        for divisibility in [0, 8, 38] {
+            for raw in [0, 1, 100_000_000, u128::MAX] {
+                let formatted = format_rune_amount(raw, divisibility);
+                assert_eq!(parse_rune_amount(&formatted, divisibility), Ok(raw));
+            }
+        }
+    }
+}
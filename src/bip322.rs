@@ -0,0 +1,189 @@
+use bitcoin::base64::Engine;
+use bitcoin::base64::engine::general_purpose::STANDARD as BASE64;
+use bitcoin::consensus::encode;
+use bitcoin::hashes::{Hash, sha256, sha256t};
+use bitcoin::key::Secp256k1;
+use bitcoin::opcodes::OP_0;
+use bitcoin::opcodes::all::OP_RETURN;
+use bitcoin::script::Builder;
+use bitcoin::sighash::{Prevouts, SighashCache, TapSighashType};
+use bitcoin::transaction::Version;
+use bitcoin::{
+    Address, Amount, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+    XOnlyPublicKey, absolute,
+};
+
+use crate::tagged_hash::tagged_hash;
+
+/// =====================================================
+/// BIP322 simple 签名（P2TR 地址）
+/// =====================================================
+///
+/// 不花费任何币，证明自己控制某个地址（proof of reserves / 交易所地址验证）：
+///
+/// - to_spend：虚拟交易，唯一 input 的 scriptSig 承诺消息哈希，唯一输出为被证明的地址
+/// - to_sign：花费 to_spend 的输出，唯一输出为 OP_RETURN
+///
+/// 签名就是 to_sign 的 witness（consensus 编码后 base64），P2TR 下为 key-path 签名
+const BIP322_TAG: &str = "BIP0322-signed-message";
+
+/// BIP322 消息哈希的 tag，midstate 在编译期算好
+pub struct Bip322MessageTag;
+
+impl sha256t::Tag for Bip322MessageTag {
+    fn engine() -> sha256::HashEngine {
+        const MIDSTATE: sha256::Midstate = sha256::Midstate::hash_tag(BIP322_TAG.as_bytes());
+        sha256::HashEngine::from_midstate(MIDSTATE, 64)
+    }
+}
+
+/// 消息哈希：tagged hash，sha256(sha256(tag) || sha256(tag) || message)
+pub fn message_hash(message: &[u8]) -> [u8; 32] {
+    tagged_hash::<Bip322MessageTag>(message)
+}
+
+/// to_spend：version 0，input 花费 0000…:0xFFFFFFFF，scriptSig = OP_0 PUSH32[message_hash]
+pub fn to_spend_tx(script_pubkey: &ScriptBuf, message: &[u8]) -> Transaction {
+    Transaction {
+        version: Version(0),
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint {
+                txid: Txid::all_zeros(),
+                vout: 0xFFFFFFFF,
+            },
+            script_sig: Builder::new()
+                .push_opcode(OP_0)
+                .push_slice(message_hash(message))
+                .into_script(),
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: script_pubkey.clone(),
+        }],
+    }
+}
+
+/// to_sign：version 0，花费 to_spend 的输出 0，唯一输出为 OP_RETURN；witness 由签名方填入
+pub fn to_sign_tx(to_spend: &Transaction) -> Transaction {
+    Transaction {
+        version: Version(0),
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint {
+                txid: to_spend.compute_txid(),
+                vout: 0,
+            },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: Builder::new().push_opcode(OP_RETURN).into_script(),
+        }],
+    }
+}
+
+/// to_sign 唯一 input 的 key-path sighash
+pub fn to_sign_sighash(
+    to_spend: &Transaction,
+    to_sign: &Transaction,
+    sighash_type: TapSighashType,
+) -> Result<bitcoin::secp256k1::Message, Box<dyn std::error::Error>> {
+    let sighash = SighashCache::new(to_sign).taproot_key_spend_signature_hash(
+        0,
+        &Prevouts::All(&to_spend.output),
+        sighash_type,
+    )?;
+    Ok(bitcoin::secp256k1::Message::from_digest(
+        sighash.to_byte_array(),
+    ))
+}
+
+/// 把 to_sign 的 witness 编码为 BIP322 simple 签名
+pub fn encode_simple_signature(witness: &Witness) -> String {
+    BASE64.encode(encode::serialize(witness))
+}
+
+/// 验证 P2TR 地址的 BIP322 simple 签名；地址不是 P2TR 或签名格式错误时返回 false
+pub fn verify_message(address: &Address, message: &str, signature: &str) -> bool {
+    verify_simple(address, message, signature).unwrap_or(false)
+}
+
+fn verify_simple(
+    address: &Address,
+    message: &str,
+    signature: &str,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let script_pubkey = address.script_pubkey();
+    if !script_pubkey.is_p2tr() {
+        return Err("only P2TR addresses are supported".into());
+    }
+    let output_key = XOnlyPublicKey::from_slice(&script_pubkey.as_bytes()[2..34])?;
+
+    let witness: Witness = encode::deserialize(&BASE64.decode(signature.trim())?)?;
+    if witness.len() != 1 {
+        return Err("key-path witness must contain exactly one element".into());
+    }
+    let sig = bitcoin::taproot::Signature::from_slice(witness.nth(0).unwrap_or_default())?;
+
+    let to_spend = to_spend_tx(&script_pubkey, message.as_bytes());
+    let to_sign = to_sign_tx(&to_spend);
+    let msg = to_sign_sighash(&to_spend, &to_sign, sig.sighash_type)?;
+
+    Ok(Secp256k1::verification_only()
+        .verify_schnorr(&sig.signature, &msg, &output_key)
+        .is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_message_hash_vectors() {
+        assert_eq!(
+            hex::encode(message_hash(b"")),
+            "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1"
+        );
+        assert_eq!(
+            hex::encode(message_hash(b"Hello World")),
+            "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a"
+        );
+    }
+
+    #[test]
+    fn test_to_spend_and_to_sign_vectors() {
+        let address = Address::from_str("bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l")
+            .unwrap()
+            .assume_checked();
+
+        let to_spend = to_spend_tx(&address.script_pubkey(), b"Hello World");
+        assert_eq!(
+            to_spend.compute_txid().to_string(),
+            "b79d196740ad5217771c1098fc4a4b51e0535c32236c71f1ea4d61a2d603352b"
+        );
+        let to_sign = to_sign_tx(&to_spend);
+        assert_eq!(
+            to_sign.compute_txid().to_string(),
+            "88737ae86f2077145f93cc4b153ae9a1cb8d56afa511988c149c5c8c9d93bddf"
+        );
+    }
+
+    #[test]
+    fn test_verify_taproot_vector() {
+        let address =
+            Address::from_str("bc1ppv609nr0vr25u07u95waq5lucwfm6tde4nydujnu8npg4q75mr5sxq8lt3")
+                .unwrap()
+                .assume_checked();
+        let signature = "AUHd69PrJQEv+oKTfZ8l+WROBHuy9HKrbFCJu7U1iK2iiEy1vMU5EfMtjc+VSHM7aU0SDbak5IUZRVno2P5mjSafAQ==";
+
+        assert!(verify_message(&address, "Hello World", signature));
+        assert!(!verify_message(&address, "Hello World!", signature));
+        assert!(!verify_message(&address, "Hello World", "not base64"));
+    }
+}
@@ -0,0 +1,184 @@
+use bitcoin::{Amount, Denomination};
+
+use crate::amount::btc_string_to_sat;
+use clap::{Parser, Subcommand};
+
+/// =====================================================
+/// 命令行参数
+/// =====================================================
+#[derive(Debug, Parser)]
+#[command(
+    name = "bitcoin-taproot-start",
+    about = "Taproot 钱包 / Inscription / Runes 工具"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// 打印钱包的 Taproot 地址
+    Address,
+
+    /// 查询地址余额（scantxoutset）
+    Balance { address: String },
+
+    /// 向地址转账，金额需带单位：0.001btc / 1000sat
+    Send {
+        address: String,
+        #[arg(value_parser = parse_amount)]
+        amount: Amount,
+        /// 费率（sat/vB）
+        #[arg(long, default_value_t = 2)]
+        fee_rate: u64,
+        /// funding UTXO 至少需要的确认数
+        #[arg(long, default_value_t = 1)]
+        min_confirmations: u32,
+        /// 只构造、签名并用 testmempoolaccept 检查，不广播
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// 发行（etch）一个 rune
+    Etch {
+        name: String,
+        #[arg(long, default_value_t = 0)]
+        divisibility: u8,
+        #[arg(long, default_value_t = 0)]
+        premine: u128,
+        #[arg(long)]
+        cap: u128,
+        /// 费率（sat/vB）
+        #[arg(long, default_value_t = 2)]
+        fee_rate: u64,
+        /// funding UTXO 至少需要的确认数
+        #[arg(long, default_value_t = 1)]
+        min_confirmations: u32,
+        /// 只构造、签名并用 testmempoolaccept 检查，不广播
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// 解析 runestone 脚本 hex
+    DecodeRunestone { hex: String },
+
+    /// 解析原始交易 hex，列出 inputs / outputs 及其中的 runestone、inscription
+    InspectTx { hex: String },
+
+    /// 离线验证某个 input 的 Taproot 签名
+    VerifySignature { txid: String, vout: u32 },
+}
+
+/// 解析带单位的金额：`btc` 后缀按 BTC（最多 8 位小数），`sat` / `sats` 后缀按聪
+pub fn parse_amount(s: &str) -> Result<Amount, String> {
+    let s = s.trim().to_lowercase();
+    if let Some(n) = s.strip_suffix("btc") {
+        btc_string_to_sat(n).map(Amount::from_sat)
+    } else if let Some(n) = s.strip_suffix("sats").or_else(|| s.strip_suffix("sat")) {
+        Amount::from_str_in(n.trim(), Denomination::Satoshi).map_err(|e| format!("金额无效: {}", e))
+    } else {
+        Err(format!("金额需要带单位 btc 或 sat: {}", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_send() {
+        let cli = Cli::try_parse_from([
+            "bitcoin-taproot-start",
+            "send",
+            "tb1pqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesf3hn0c",
+            "0.001btc",
+            "--fee-rate",
+            "5",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Command::Send {
+                address,
+                amount,
+                fee_rate,
+                min_confirmations,
+                dry_run,
+            } => {
+                assert!(address.starts_with("tb1p"));
+                assert_eq!(amount, Amount::from_sat(100_000));
+                assert_eq!(fee_rate, 5);
+                assert_eq!(min_confirmations, 1);
+                assert!(!dry_run);
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+
+        let cli = Cli::try_parse_from([
+            "bitcoin-taproot-start",
+            "send",
+            "tb1pxyz",
+            "1500sat",
+            "--dry-run",
+        ])
+        .unwrap();
+        match cli.command {
+            Command::Send {
+                amount,
+                fee_rate,
+                dry_run,
+                ..
+            } => {
+                assert_eq!(amount, Amount::from_sat(1_500));
+                assert_eq!(fee_rate, 2);
+                assert!(dry_run);
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+
+        // 没有单位或小数位过多都拒绝
+        assert!(Cli::try_parse_from(["bitcoin-taproot-start", "send", "tb1pxyz", "1000"]).is_err());
+        assert!(
+            Cli::try_parse_from(["bitcoin-taproot-start", "send", "tb1pxyz", "0.000000001btc"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_etch() {
+        let cli = Cli::try_parse_from([
+            "bitcoin-taproot-start",
+            "etch",
+            "TEST",
+            "--divisibility",
+            "2",
+            "--premine",
+            "1000",
+            "--cap",
+            "21000000",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Command::Etch {
+                name,
+                divisibility,
+                premine,
+                cap,
+                fee_rate,
+                ..
+            } => {
+                assert_eq!(name, "TEST");
+                assert_eq!(divisibility, 2);
+                assert_eq!(premine, 1_000);
+                assert_eq!(cap, 21_000_000);
+                assert_eq!(fee_rate, 2);
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+
+        // cap 是必填参数
+        assert!(Cli::try_parse_from(["bitcoin-taproot-start", "etch", "TEST"]).is_err());
+    }
+}
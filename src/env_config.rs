@@ -1,10 +1,15 @@
+use std::str::FromStr;
 use std::sync::LazyLock;
 
+use bitcoin::Network;
+use zeroize::Zeroizing;
+
 #[derive(Debug, Clone)]
 
 pub struct EnvConfigs {
     pub alchemy_api_url: String,
-    pub mnemonic: String,
+    /// BITCOIN_NETWORK：bitcoin / testnet / testnet4 / signet / regtest，默认 testnet
+    pub network: Network,
 }
 
 pub static ENV_CONFIGS: LazyLock<EnvConfigs> = LazyLock::new(|| {
@@ -12,6 +17,93 @@ pub static ENV_CONFIGS: LazyLock<EnvConfigs> = LazyLock::new(|| {
 
     EnvConfigs {
         alchemy_api_url: std::env::var("ALCHEMY_API_URL").expect("ALCHEMY_API_URL must be set"),
-        mnemonic: std::env::var("MNEMONIC").expect("MNEMONIC must be set"),
+        network: std::env::var("BITCOIN_NETWORK")
+            .map(|n| Network::from_str(&n).expect("BITCOIN_NETWORK is invalid"))
+            .unwrap_or(Network::Testnet),
     }
 });
+
+/// =====================================================
+/// 读取助记词
+/// =====================================================
+///
+/// 助记词不放进 ENV_CONFIGS（static 里的 String 无法清零），每次使用时读取，
+/// 返回的 Zeroizing<String> drop 时自动清零：
+///   - MNEMONIC_FILE：助记词文件路径，内容去掉首尾空白（推荐，不会出现在 shell 历史和进程列表里）
+///   - MNEMONIC：直接写在环境变量里
+///
+/// 两者同时设置时报错，避免不清楚到底用了哪一个
+pub fn load_mnemonic() -> Result<Zeroizing<String>, Box<dyn std::error::Error>> {
+    dotenvy::dotenv().ok();
+    resolve_mnemonic(
+        std::env::var("MNEMONIC").ok().map(Zeroizing::new),
+        std::env::var("MNEMONIC_FILE").ok(),
+    )
+}
+
+fn resolve_mnemonic(
+    inline: Option<Zeroizing<String>>,
+    file: Option<String>,
+) -> Result<Zeroizing<String>, Box<dyn std::error::Error>> {
+    match (inline, file) {
+        (Some(_), Some(_)) => Err("MNEMONIC and MNEMONIC_FILE are both set; use only one".into()),
+        (None, Some(path)) => {
+            let contents = Zeroizing::new(
+                std::fs::read_to_string(&path)
+                    .map_err(|e| format!("failed to read MNEMONIC_FILE {}: {}", path, e))?,
+            );
+            let mnemonic = Zeroizing::new(contents.trim().to_string());
+            if mnemonic.is_empty() {
+                return Err(format!("MNEMONIC_FILE {} is empty", path).into());
+            }
+            Ok(mnemonic)
+        }
+        (Some(inline), None) => Ok(inline),
+        (None, None) => Err("MNEMONIC or MNEMONIC_FILE must be set".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn temp_file(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_resolve_mnemonic_from_file() {
+        let path = temp_file("mnemonic.txt", &format!("\n  {}  \n", PHRASE));
+
+        assert_eq!(
+            resolve_mnemonic(None, Some(path.clone())).unwrap().as_str(),
+            PHRASE
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        // 文件不存在
+        assert!(resolve_mnemonic(None, Some(path)).is_err());
+        // 只设置 MNEMONIC
+        assert_eq!(
+            resolve_mnemonic(Some(Zeroizing::new(PHRASE.to_string())), None)
+                .unwrap()
+                .as_str(),
+            PHRASE
+        );
+        assert!(resolve_mnemonic(None, None).is_err());
+    }
+
+    #[test]
+    fn test_resolve_mnemonic_both_set_is_error() {
+        let path = temp_file("mnemonic-conflict.txt", PHRASE);
+
+        let err = resolve_mnemonic(Some(Zeroizing::new(PHRASE.to_string())), Some(path.clone()))
+            .unwrap_err();
+        assert!(err.to_string().contains("both set"));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,1084 @@
+use std::str::FromStr;
+
+use bitcoin::consensus::encode::VarInt;
+use bitcoin::key::TapTweak;
+use bitcoin::taproot::TAPROOT_CONTROL_BASE_SIZE;
+use bitcoin::{Amount, FeeRate, ScriptBuf, Transaction, TxOut, Weight, XOnlyPublicKey};
+
+use crate::alchemy_client::{TxOut as AlchemyTxOut, UtxoInfo};
+use crate::utils::build_inscription_script;
+
+/// =====================================================
+/// Dust 限制
+/// =====================================================
+///
+/// 按 Bitcoin Core 的 GetDustThreshold 规则（dustRelayFee = 3 sat/vB）
+/// 计算某个 scriptPubKey 的最小非 dust 金额：
+///   - P2TR:   330 sats
+///   - P2WPKH: 294 sats
+///   - P2PKH:  546 sats
+///   - OP_RETURN: 0
+///
+/// 低于这个值的输出会被节点以 "dust" 拒绝转发
+pub fn dust_limit(script_pubkey: &ScriptBuf) -> Amount {
+    script_pubkey.minimal_non_dust()
+}
+
+/// 输出金额是否低于 dust 限制
+pub fn is_dust(value: Amount, script_pubkey: &ScriptBuf) -> bool {
+    value < dust_limit(script_pubkey)
+}
+
+/// 检查一个（非找零）输出是否满足 dust 限制，不满足时直接报错
+pub fn ensure_not_dust(output: &TxOut) -> Result<(), Box<dyn std::error::Error>> {
+    if is_dust(output.value, &output.script_pubkey) {
+        return Err(format!(
+            "output value {} sat is below dust limit {} sat",
+            output.value.to_sat(),
+            dust_limit(&output.script_pubkey).to_sat()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// =====================================================
+/// 交易大小（vsize）估算
+/// =====================================================
+///
+/// 每个 input 的花费方式，决定了它的 witness 大小
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpendKind {
+    /// key-path：witness 只有一个 64 字节的 schnorr 签名（SIGHASH_DEFAULT）
+    KeyPath,
+    /// script-path：witness 为 [签名, 叶子脚本, control block]
+    ScriptPath {
+        script_len: usize,
+        control_block_len: usize,
+    },
+}
+
+/// schnorr 签名长度（SIGHASH_DEFAULT 不附加 sighash 字节）
+const SCHNORR_SIG_LEN: usize = 64;
+
+impl SpendKind {
+    /// 该 input 的 witness 序列化字节数（包括元素个数和每个元素的长度前缀）
+    fn witness_size(&self) -> usize {
+        let items: &[usize] = match self {
+            SpendKind::KeyPath => &[SCHNORR_SIG_LEN],
+            SpendKind::ScriptPath {
+                script_len,
+                control_block_len,
+            } => &[SCHNORR_SIG_LEN, *script_len, *control_block_len],
+        };
+
+        VarInt(items.len() as u64).size()
+            + items
+                .iter()
+                .map(|len| VarInt(*len as u64).size() + len)
+                .sum::<usize>()
+    }
+}
+
+/// segwit 序列化中 witness 部分的 weight（witness 字节按 1 WU 计，即 1/4 折扣）：
+///   - marker + flag（2 字节）：只有至少一个 input 带 witness 时才出现
+///   - 每个 input 的 witness：元素个数（compact size）+ 每个元素的长度前缀（compact size）+ 内容
+///
+/// 漏掉每个元素的长度前缀是常见错误，每个 input 会少算几个字节
+pub fn witness_weight(inputs: &[SpendKind]) -> Weight {
+    if inputs.is_empty() {
+        return Weight::ZERO;
+    }
+    let marker_flag = 2;
+    Weight::from_wu(
+        (marker_flag + inputs.iter().map(SpendKind::witness_size).sum::<usize>()) as u64,
+    )
+}
+
+/// 估算已签名交易的 weight：非 witness 字节 * 4 + witness_weight
+pub fn estimate_tx_weight(inputs: &[SpendKind], outputs: &[TxOut]) -> Weight {
+    // version(4) + locktime(4) + input/output 个数
+    let mut base_size =
+        4 + 4 + VarInt(inputs.len() as u64).size() + VarInt(outputs.len() as u64).size();
+
+    // 每个 input：outpoint(36) + 空 scriptSig(1) + sequence(4)
+    base_size += inputs.len() * (36 + 1 + 4);
+
+    // 每个 output：value(8) + scriptPubKey（带长度前缀）
+    base_size += outputs
+        .iter()
+        .map(|out| {
+            let spk_len = out.script_pubkey.len();
+            8 + VarInt(spk_len as u64).size() + spk_len
+        })
+        .sum::<usize>();
+
+    Weight::from_non_witness_data_size(base_size as u64) + witness_weight(inputs)
+}
+
+/// 估算已签名交易的 vsize：ceil(weight / 4)
+pub fn estimate_tx_vsize(inputs: &[SpendKind], outputs: &[TxOut]) -> u64 {
+    estimate_tx_weight(inputs, outputs).to_vbytes_ceil()
+}
+
+/// 按费率和估算的 vsize 计算手续费
+pub fn estimate_fee(
+    inputs: &[SpendKind],
+    outputs: &[TxOut],
+    fee_rate: FeeRate,
+) -> Result<Amount, Box<dyn std::error::Error>> {
+    let vsize = estimate_tx_vsize(inputs, outputs);
+    fee_rate
+        .fee_vb(vsize)
+        .ok_or_else(|| "fee calculation overflow".into())
+}
+
+/// commit-reveal 中 commit 输出至少需要的金额：reveal 交易的手续费 + reveal 输出的 postage
+///
+/// reveal 交易只有一个 input（reveal_spend）和一个输出（reveal_output）
+pub fn min_commit_value(
+    reveal_spend: SpendKind,
+    reveal_output: &ScriptBuf,
+    postage: Amount,
+    fee_rate: FeeRate,
+) -> Result<Amount, Box<dyn std::error::Error>> {
+    let reveal_fee = estimate_fee(
+        &[reveal_spend],
+        &[TxOut {
+            value: Amount::ZERO,
+            script_pubkey: reveal_output.clone(),
+        }],
+        fee_rate,
+    )?;
+    reveal_fee
+        .checked_add(postage)
+        .ok_or_else(|| "commit value overflow".into())
+}
+
+/// 估算任意 inscription 的 reveal 手续费，用于在 commit 之前确定 commit 输出金额
+///
+/// 按 content_len 字节的 body 构造 envelope（超过 520 字节自动拆分，每段多出 push 前缀），
+/// reveal 交易为一个 script-path input（[签名, inscription script, control block]）
+/// 加一个 P2TR 输出。脚本长度与公钥无关，这里用一个占位公钥
+pub fn estimate_reveal_fee(content_len: usize, content_type: &str, fee_rate: FeeRate) -> Amount {
+    let placeholder_key = placeholder_xonly();
+    let script = build_inscription_script(placeholder_key, content_type, &vec![0u8; content_len]);
+
+    estimate_fee(
+        &[SpendKind::ScriptPath {
+            script_len: script.len(),
+            // 只有一个叶子：control block 不带 merkle path
+            control_block_len: TAPROOT_CONTROL_BASE_SIZE,
+        }],
+        &[placeholder_p2tr_output()],
+        fee_rate,
+    )
+    .expect("fee calculation overflow")
+}
+
+/// 估算时使用的占位公钥（generator 点）；脚本和输出的大小与具体公钥无关
+fn placeholder_xonly() -> XOnlyPublicKey {
+    XOnlyPublicKey::from_str("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+        .expect("valid x-only pubkey")
+}
+
+fn placeholder_p2tr_output() -> TxOut {
+    TxOut {
+        value: Amount::ZERO,
+        script_pubkey: ScriptBuf::new_p2tr_tweaked(placeholder_xonly().dangerous_assume_tweaked()),
+    }
+}
+
+/// =====================================================
+/// inscription 总成本估算（commit + reveal）
+/// =====================================================
+///
+/// 在用户确认之前展示“铭刻这个内容一共要花多少”：
+///   - commit_fee：input_count 个 key-path input，输出为 [commit, change] 两个 P2TR
+///   - reveal_fee：见 estimate_reveal_fee
+///   - postage：reveal 输出里留给 inscription 的金额（仍属于自己，但不能再当普通余额花）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InscriptionCost {
+    pub commit_fee: Amount,
+    pub reveal_fee: Amount,
+    pub postage: Amount,
+    pub total: Amount,
+}
+
+/// 按选中的 funding input 个数估算 inscription 的总成本
+pub fn estimate_inscription_cost(
+    content_len: usize,
+    content_type: &str,
+    fee_rate: FeeRate,
+    postage: Amount,
+    input_count: usize,
+) -> Result<InscriptionCost, Box<dyn std::error::Error>> {
+    if input_count == 0 {
+        return Err("commit transaction needs at least one funding input".into());
+    }
+
+    let commit_fee = estimate_fee(
+        &vec![SpendKind::KeyPath; input_count],
+        &[placeholder_p2tr_output(), placeholder_p2tr_output()],
+        fee_rate,
+    )?;
+    let reveal_fee = estimate_reveal_fee(content_len, content_type, fee_rate);
+    let total = commit_fee
+        .checked_add(reveal_fee)
+        .and_then(|fees| fees.checked_add(postage))
+        .ok_or("inscription cost overflow")?;
+
+    Ok(InscriptionCost {
+        commit_fee,
+        reveal_fee,
+        postage,
+        total,
+    })
+}
+
+/// postage 低于这个值时提示：546 sat 是最保守（P2PKH）的 dust 限制
+pub const MIN_ADVISED_POSTAGE: Amount = Amount::from_sat(546);
+
+/// postage 高于这个值时提示：多出来的 sat 跟着 inscription 走，很难再当普通余额花
+pub const MAX_ADVISED_POSTAGE: Amount = Amount::from_sat(10_000);
+
+/// =====================================================
+/// inscription 输出中被“锁住”的 postage
+/// =====================================================
+///
+/// 每个 inscription 的 reveal 输出都带 postage 个 sat，这部分不是手续费，
+/// 但会跟 inscription 绑在一起，批量铭刻时按数量成倍增加
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InscriptionPlan {
+    pub postage: Amount,
+    pub inscription_count: usize,
+}
+
+impl InscriptionPlan {
+    pub fn new(postage: Amount, inscription_count: usize) -> Self {
+        InscriptionPlan {
+            postage,
+            inscription_count,
+        }
+    }
+
+    /// 所有 reveal 输出里被锁住的 sat 总数：postage × inscription 数量
+    pub fn trapped_value(&self) -> Amount {
+        self.postage
+            .checked_mul(self.inscription_count as u64)
+            .unwrap_or(Amount::MAX_MONEY)
+    }
+
+    /// postage 低于 546 sat（可能是 dust）或高于 10_000 sat（浪费）时返回提示
+    pub fn postage_warning(&self) -> Option<String> {
+        if self.postage < MIN_ADVISED_POSTAGE {
+            Some(format!(
+                "postage {} sat is below {} sat and may be rejected as dust",
+                self.postage.to_sat(),
+                MIN_ADVISED_POSTAGE.to_sat()
+            ))
+        } else if self.postage > MAX_ADVISED_POSTAGE {
+            Some(format!(
+                "postage {} sat is above {} sat; {} sat in total will be tied up with the inscriptions",
+                self.postage.to_sat(),
+                MAX_ADVISED_POSTAGE.to_sat(),
+                self.trapped_value().to_sat()
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// 标准交易的大小上限：400_000 WU，即 100_000 vB；超过的交易节点不转发
+pub const MAX_STANDARD_TX_VSIZE: u64 = 100_000;
+
+/// =====================================================
+/// 拆分大批量归集（consolidation）
+/// =====================================================
+///
+/// 几百个 546 sat 的 UTXO 一次归集会超过标准交易大小上限。按顺序贪心装箱，
+/// 每批对应一笔交易：n 个 key-path input + 一个 P2TR 输出，估算 vsize 不超过 max_vsize。
+///
+/// - 金额不够支付自身 input 手续费的 UTXO（花掉反而亏）直接跳过
+/// - 扣除整笔手续费后输出会低于 dust 的批次丢弃
+pub fn plan_consolidation(
+    utxos: &[AlchemyTxOut],
+    fee_rate: FeeRate,
+    max_vsize: u64,
+) -> Vec<Vec<AlchemyTxOut>> {
+    let output = [placeholder_p2tr_output()];
+    let vsize_with = |inputs: usize| estimate_tx_vsize(&vec![SpendKind::KeyPath; inputs], &output);
+    // 单个 key-path input 带来的 vsize（向上取整）
+    let input_vsize = vsize_with(1) - vsize_with(0);
+    let input_fee = fee_rate.fee_vb(input_vsize).unwrap_or(Amount::MAX_MONEY);
+    if vsize_with(1) > max_vsize {
+        return Vec::new();
+    }
+
+    let mut batches: Vec<Vec<AlchemyTxOut>> = Vec::new();
+    let mut batch: Vec<AlchemyTxOut> = Vec::new();
+    for utxo in utxos
+        .iter()
+        .filter(|utxo| Amount::from_sat(utxo.value) > input_fee)
+    {
+        if !batch.is_empty() && vsize_with(batch.len() + 1) > max_vsize {
+            batches.push(std::mem::take(&mut batch));
+        }
+        batch.push(utxo.clone());
+    }
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+
+    let dust = dust_limit(&output[0].script_pubkey);
+    batches.retain(|batch| {
+        let total: u64 = batch.iter().map(|utxo| utxo.value).sum();
+        fee_rate
+            .fee_vb(vsize_with(batch.len()))
+            .and_then(|fee| Amount::from_sat(total).checked_sub(fee))
+            .is_some_and(|value| value >= dust)
+    });
+    batches
+}
+
+/// BnB 最多尝试的搜索节点数（与 Bitcoin Core 相同），超过后放弃，回退到 largest-first
+const BNB_MAX_TRIES: usize = 100_000;
+
+/// =====================================================
+/// Branch-and-Bound 选币
+/// =====================================================
+///
+/// 输入按 key-path P2TR 估算手续费，输出为一个 P2TR 支付输出。每个 UTXO 的
+/// 有效金额 = value - 自身 input 的手续费，要凑的目标 = target + 交易基础部分的手续费。
+///
+/// BnB 搜索有效金额之和落在 [目标, 目标 + 找零成本] 内的组合：多出的部分不够
+/// 做一个非 dust 的找零（找零成本 = dust + 找零输出的手续费），直接并入手续费，
+/// 交易不需要找零输出，更便宜也更隐私。有多个解时选多付最少的。
+///
+/// 找不到时回退到 largest-first（会产生找零）；余额不够时返回 None
+pub fn select_utxos_bnb(
+    utxos: &[UtxoInfo],
+    target: Amount,
+    fee_rate: FeeRate,
+) -> Option<Vec<UtxoInfo>> {
+    let output = placeholder_p2tr_output();
+    select_utxos_bnb_for_outputs(
+        utxos,
+        &[TxOut {
+            value: target,
+            script_pubkey: output.script_pubkey.clone(),
+        }],
+        Some(&output.script_pubkey),
+        fee_rate,
+    )
+}
+
+/// 按交易真实的输出做 BnB 选币：outputs 为除找零外的所有输出（金额之和即目标，
+/// 手续费按它们的实际大小估算，包括 runestone 等 OP_RETURN 输出），
+/// change_script 为找零输出的脚本，用来计算找零成本。
+///
+/// 没有找零输出（change_script 为 None）时，多出的部分全部作为手续费
+pub fn select_utxos_bnb_for_outputs(
+    utxos: &[UtxoInfo],
+    outputs: &[TxOut],
+    change_script: Option<&ScriptBuf>,
+    fee_rate: FeeRate,
+) -> Option<Vec<UtxoInfo>> {
+    let mut with_change = outputs.to_vec();
+    with_change.extend(change_script.map(|script| TxOut {
+        value: Amount::ZERO,
+        script_pubkey: script.clone(),
+    }));
+    let vsize_with = |inputs: usize, outputs: &[TxOut]| {
+        estimate_tx_vsize(&vec![SpendKind::KeyPath; inputs], outputs)
+    };
+    let fee = |vsize: u64| fee_rate.fee_vb(vsize).map_or(u64::MAX, Amount::to_sat);
+
+    let input_fee = fee(vsize_with(1, outputs) - vsize_with(0, outputs));
+    let change_fee = fee(vsize_with(0, &with_change) - vsize_with(0, outputs));
+    let target = outputs
+        .iter()
+        .try_fold(0u64, |total, output| {
+            total.checked_add(output.value.to_sat())
+        })?
+        .checked_add(fee(vsize_with(0, outputs)))?;
+    let cost_of_change = change_script.map_or(0, |script| dust_limit(script).to_sat() + change_fee);
+
+    // 只考虑有效金额为正的 UTXO，按有效金额从大到小排序
+    let mut candidates: Vec<(u64, &UtxoInfo)> = utxos
+        .iter()
+        .filter(|utxo| utxo.value > input_fee)
+        .map(|utxo| (utxo.value - input_fee, utxo))
+        .collect();
+    candidates.sort_by_key(|(value, _)| std::cmp::Reverse(*value));
+
+    // remaining[i] = candidates[i..] 的有效金额之和，用于剪枝
+    let mut remaining = vec![0u64; candidates.len() + 1];
+    for i in (0..candidates.len()).rev() {
+        remaining[i] = remaining[i + 1] + candidates[i].0;
+    }
+    if remaining[0] < target {
+        return None;
+    }
+
+    let mut best: Option<(u64, Vec<usize>)> = None;
+    let mut selected = Vec::new();
+    let mut tries = 0;
+    bnb_search(
+        &candidates,
+        &remaining,
+        target,
+        target + cost_of_change,
+        0,
+        0,
+        &mut selected,
+        &mut best,
+        &mut tries,
+    );
+    if let Some((_, indexes)) = best {
+        return Some(indexes.iter().map(|i| candidates[*i].1.clone()).collect());
+    }
+
+    // 回退：largest-first，需要额外支付找零输出的手续费
+    let mut sum = 0;
+    let mut selection = Vec::new();
+    for (value, utxo) in &candidates {
+        selection.push((*utxo).clone());
+        sum += value;
+        if sum >= target + change_fee {
+            return Some(selection);
+        }
+    }
+    None
+}
+
+/// BnB 深度优先搜索：每个 candidate 依次尝试“选”和“不选”，
+/// 当前和超过上限或剩余全选也不够下限时剪枝；best 记录多付最少的组合
+#[allow(clippy::too_many_arguments)]
+fn bnb_search(
+    candidates: &[(u64, &UtxoInfo)],
+    remaining: &[u64],
+    lower: u64,
+    upper: u64,
+    index: usize,
+    sum: u64,
+    selected: &mut Vec<usize>,
+    best: &mut Option<(u64, Vec<usize>)>,
+    tries: &mut usize,
+) {
+    *tries += 1;
+    if *tries > BNB_MAX_TRIES || sum > upper || sum + remaining[index] < lower {
+        return;
+    }
+    if sum >= lower {
+        let excess = sum - lower;
+        if best
+            .as_ref()
+            .is_none_or(|(best_excess, _)| excess < *best_excess)
+        {
+            *best = Some((excess, selected.clone()));
+        }
+        return;
+    }
+    if index == candidates.len() {
+        return;
+    }
+
+    selected.push(index);
+    bnb_search(
+        candidates,
+        remaining,
+        lower,
+        upper,
+        index + 1,
+        sum + candidates[index].0,
+        selected,
+        best,
+        tries,
+    );
+    selected.pop();
+    if best.as_ref().is_some_and(|(excess, _)| *excess == 0) {
+        return;
+    }
+    bnb_search(
+        candidates,
+        remaining,
+        lower,
+        upper,
+        index + 1,
+        sum,
+        selected,
+        best,
+        tries,
+    );
+}
+
+/// 根据 prevouts 计算已签名交易的实际手续费和费率（sat/vB，向上取整）
+pub fn actual_fee_rate(
+    tx: &Transaction,
+    prevouts: &[TxOut],
+) -> Result<(Amount, FeeRate), Box<dyn std::error::Error>> {
+    if prevouts.len() != tx.input.len() {
+        return Err(format!(
+            "prevouts length mismatch: prevouts={} tx.inputs={}",
+            prevouts.len(),
+            tx.input.len()
+        )
+        .into());
+    }
+
+    let total_in: Amount = prevouts.iter().map(|p| p.value).sum();
+    let total_out: Amount = tx.output.iter().map(|o| o.value).sum();
+    let fee = total_in
+        .checked_sub(total_out)
+        .ok_or("outputs exceed inputs")?;
+
+    let vsize = tx.vsize() as u64;
+    let fee_rate =
+        FeeRate::from_sat_per_vb(fee.to_sat().div_ceil(vsize)).ok_or("fee rate overflow")?;
+
+    Ok((fee, fee_rate))
+}
+
+/// =====================================================
+/// 签名后按实际 vsize 校正手续费
+/// =====================================================
+///
+/// 手续费是签名前按预估的 vsize 算的，签名后用 tx.vsize() 重新计算
+/// target_rate × vsize，把差额计入 change_vout 的找零，返回校正后的手续费。
+///
+/// 输出改变后原签名失效，调用方必须重新签名（taproot 签名长度固定，重签不改变 vsize）；
+/// 找零会低于 dust 时报错，不修改交易
+pub fn true_up_fee(
+    tx: &mut Transaction,
+    prevouts: &[TxOut],
+    change_vout: usize,
+    target_rate: FeeRate,
+) -> Result<Amount, Box<dyn std::error::Error>> {
+    let (fee, _) = actual_fee_rate(tx, prevouts)?;
+    let target_fee = target_rate
+        .fee_vb(tx.vsize() as u64)
+        .ok_or("fee overflow")?;
+
+    let change = tx
+        .output
+        .get_mut(change_vout)
+        .ok_or(format!("change output {} does not exist", change_vout))?;
+    let new_value = (change.value + fee)
+        .checked_sub(target_fee)
+        .filter(|value| !is_dust(*value, &change.script_pubkey))
+        .ok_or(format!(
+            "true-up to {} sat fee would leave change output {} below dust",
+            target_fee.to_sat(),
+            change_vout
+        ))?;
+    change.value = new_value;
+
+    Ok(target_fee)
+}
+
+/// =====================================================
+/// RBF 替换的最低手续费（BIP125 规则 3、4）
+/// =====================================================
+///
+/// 替换交易必须：
+///   - 规则 3：绝对手续费不低于被替换交易
+///   - 规则 4：额外支付的手续费足够支付自身带宽，即 min_relay × replacement_vsize
+///
+/// 所以最低手续费 = original_fee + min_relay × replacement_vsize，
+/// 低于它节点会返回 "insufficient fee, rejecting replacement"
+pub fn min_replacement_fee(
+    original_fee: Amount,
+    replacement_vsize: u64,
+    min_relay: FeeRate,
+) -> Amount {
+    min_relay
+        .fee_vb(replacement_vsize)
+        .and_then(|relay_fee| original_fee.checked_add(relay_fee))
+        .unwrap_or(Amount::MAX_MONEY)
+}
+
+/// =====================================================
+/// RBF 提高手续费
+/// =====================================================
+///
+/// 按 new_rate 用 true_up_fee 重新计算手续费（差额从 change_vout 的找零扣除），
+/// 新手续费低于 min_replacement_fee 时报错，返回新的手续费。
+///
+/// 出错时不修改交易；成功后调用方必须重新签名
+pub fn bump_fee(
+    tx: &mut Transaction,
+    prevouts: &[TxOut],
+    change_vout: usize,
+    original_fee: Amount,
+    new_rate: FeeRate,
+    min_relay: FeeRate,
+) -> Result<Amount, Box<dyn std::error::Error>> {
+    let mut replacement = tx.clone();
+    let new_fee = true_up_fee(&mut replacement, prevouts, change_vout, new_rate)?;
+
+    let min_fee = min_replacement_fee(original_fee, replacement.vsize() as u64, min_relay);
+    if new_fee < min_fee {
+        return Err(format!(
+            "insufficient fee, rejecting replacement: new fee {} sat < minimum {} sat (original {} sat + {} sat/vB × {} vB)",
+            new_fee.to_sat(),
+            min_fee.to_sat(),
+            original_fee.to_sat(),
+            min_relay.to_sat_per_vb_ceil(),
+            replacement.vsize()
+        )
+        .into());
+    }
+
+    *tx = replacement;
+    Ok(new_fee)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::key::{Keypair, Secp256k1};
+    use bitcoin::taproot::{LeafVersion, TaprootBuilder};
+    use bitcoin::transaction::Version;
+    use bitcoin::{OutPoint, Sequence, Transaction, TxIn, WPubkeyHash, Witness, hashes::Hash};
+
+    use crate::utils::{TEXT_PLAIN_UTF8, brc20_deploy_body, build_inscription_script};
+
+    fn dummy_tx(witness: Witness, outputs: Vec<TxOut>) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness,
+            }],
+            output: outputs,
+        }
+    }
+
+    #[test]
+    fn test_dust_limit_p2tr() {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_seckey_slice(&secp, &[1u8; 32]).unwrap();
+        let script = ScriptBuf::new_p2tr(&secp, keypair.x_only_public_key().0, None);
+
+        assert_eq!(dust_limit(&script), Amount::from_sat(330));
+        assert!(is_dust(Amount::from_sat(329), &script));
+        assert!(!is_dust(Amount::from_sat(330), &script));
+    }
+
+    #[test]
+    fn test_dust_limit_p2wpkh() {
+        let script = ScriptBuf::new_p2wpkh(&WPubkeyHash::all_zeros());
+
+        assert_eq!(dust_limit(&script), Amount::from_sat(294));
+    }
+
+    #[test]
+    fn test_estimate_vsize_key_path() {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_seckey_slice(&secp, &[1u8; 32]).unwrap();
+        let script = ScriptBuf::new_p2tr(&secp, keypair.x_only_public_key().0, None);
+        let outputs = vec![
+            TxOut {
+                value: Amount::from_sat(10_000),
+                script_pubkey: script.clone(),
+            },
+            TxOut {
+                value: Amount::from_sat(5_000),
+                script_pubkey: script,
+            },
+        ];
+
+        let mut witness = Witness::new();
+        witness.push([0u8; 64]);
+        let tx = dummy_tx(witness, outputs.clone());
+
+        assert_eq!(
+            estimate_tx_vsize(&[SpendKind::KeyPath], &outputs),
+            tx.vsize() as u64
+        );
+    }
+
+    #[test]
+    fn test_estimate_weight_two_input_taproot_tx() {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_seckey_slice(&secp, &[1u8; 32]).unwrap();
+        let script = ScriptBuf::new_p2tr(&secp, keypair.x_only_public_key().0, None);
+        let outputs = vec![
+            TxOut {
+                value: Amount::from_sat(30_000),
+                script_pubkey: script.clone(),
+            },
+            TxOut {
+                value: Amount::from_sat(5_000),
+                script_pubkey: script,
+            },
+        ];
+
+        // 两个 key-path input，各带一个 64 字节的 schnorr 签名（SIGHASH_DEFAULT）
+        let msg = bitcoin::secp256k1::Message::from_digest([7u8; 32]);
+        let signature = secp.sign_schnorr_no_aux_rand(&msg, &keypair);
+        let mut tx = dummy_tx(Witness::from_slice(&[signature.as_ref()]), outputs.clone());
+        tx.input.push(tx.input[0].clone());
+
+        let inputs = [SpendKind::KeyPath, SpendKind::KeyPath];
+        assert_eq!(estimate_tx_weight(&inputs, &outputs), tx.weight());
+        assert_eq!(estimate_tx_vsize(&inputs, &outputs), tx.vsize() as u64);
+
+        // witness 部分：marker + flag + 2 * (元素个数 1 + 长度前缀 1 + 64)
+        assert_eq!(witness_weight(&inputs), Weight::from_wu(2 + 2 * 66));
+        assert_eq!(witness_weight(&[]), Weight::ZERO);
+    }
+
+    #[test]
+    fn test_estimate_vsize_script_path_reveal() {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_seckey_slice(&secp, &[1u8; 32]).unwrap();
+        let xonly = keypair.x_only_public_key().0;
+
+        let inscription_script =
+            build_inscription_script(xonly, TEXT_PLAIN_UTF8, &brc20_deploy_body());
+        let spend_info = TaprootBuilder::new()
+            .add_leaf(0, inscription_script.clone())
+            .unwrap()
+            .finalize(&secp, xonly)
+            .unwrap();
+        let control_block = spend_info
+            .control_block(&(inscription_script.clone(), LeafVersion::TapScript))
+            .unwrap();
+
+        let outputs = vec![TxOut {
+            value: Amount::from_sat(9_800),
+            script_pubkey: ScriptBuf::new_p2tr(&secp, xonly, None),
+        }];
+
+        let mut witness = Witness::new();
+        witness.push([0u8; 64]);
+        witness.push(inscription_script.as_bytes());
+        witness.push(control_block.serialize());
+        let tx = dummy_tx(witness, outputs.clone());
+
+        let kind = SpendKind::ScriptPath {
+            script_len: inscription_script.len(),
+            control_block_len: control_block.size(),
+        };
+        assert_eq!(estimate_tx_vsize(&[kind], &outputs), tx.vsize() as u64);
+    }
+
+    #[test]
+    fn test_min_commit_value() {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_seckey_slice(&secp, &[1u8; 32]).unwrap();
+        let reveal_output = ScriptBuf::new_p2tr(&secp, keypair.x_only_public_key().0, None);
+        let reveal_spend = SpendKind::ScriptPath {
+            script_len: 100,
+            control_block_len: 33,
+        };
+
+        // 非 witness：4 + 4 + 1 + 1 + 41 + 43 = 94 字节
+        // witness：2 + 1 + (1 + 64) + (1 + 100) + (1 + 33) = 203 字节
+        // weight = 94 * 4 + 203 = 579，vsize = 145，2 sat/vB 手续费 290
+        let value = min_commit_value(
+            reveal_spend,
+            &reveal_output,
+            Amount::from_sat(546),
+            FeeRate::from_sat_per_vb(2).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(value, Amount::from_sat(290 + 546));
+
+        // postage 为 0 时就是 reveal 手续费本身
+        assert_eq!(
+            min_commit_value(
+                reveal_spend,
+                &reveal_output,
+                Amount::ZERO,
+                FeeRate::from_sat_per_vb(2).unwrap()
+            )
+            .unwrap(),
+            Amount::from_sat(290)
+        );
+    }
+
+    #[test]
+    fn test_estimate_inscription_cost() {
+        let fee_rate = FeeRate::from_sat_per_vb(5).unwrap();
+        let postage = Amount::from_sat(10_000);
+
+        let one = estimate_inscription_cost(200, TEXT_PLAIN_UTF8, fee_rate, postage, 1).unwrap();
+        assert_eq!(one.total, one.commit_fee + one.reveal_fee + one.postage);
+        assert_eq!(one.postage, postage);
+        assert_eq!(
+            one.reveal_fee,
+            estimate_reveal_fee(200, TEXT_PLAIN_UTF8, fee_rate)
+        );
+        // 1 个 key-path input + 2 个 P2TR 输出：154 vB
+        assert_eq!(one.commit_fee, Amount::from_sat(154 * 5));
+
+        // 每多一个 funding input，commit 手续费多 57.5 vB
+        let three = estimate_inscription_cost(200, TEXT_PLAIN_UTF8, fee_rate, postage, 3).unwrap();
+        assert_eq!(
+            three.total,
+            three.commit_fee + three.reveal_fee + three.postage
+        );
+        assert_eq!(three.commit_fee, Amount::from_sat(269 * 5));
+        assert_eq!(three.reveal_fee, one.reveal_fee);
+
+        assert!(estimate_inscription_cost(200, TEXT_PLAIN_UTF8, fee_rate, postage, 0).is_err());
+    }
+
+    #[test]
+    fn test_plan_consolidation_splits_by_vsize() {
+        let utxos: Vec<AlchemyTxOut> = (0..300)
+            .map(|vout| AlchemyTxOut::fixture(placeholder_p2tr_output().script_pubkey, 546, vout))
+            .collect();
+        let fee_rate = FeeRate::from_sat_per_vb(2).unwrap();
+
+        // 100 个 key-path input + 1 个 P2TR 输出：5804 vB，101 个：5861 vB
+        let batches = plan_consolidation(&utxos, fee_rate, 5_804);
+        assert_eq!(batches.len(), 3);
+        assert!(batches.iter().all(|batch| batch.len() == 100));
+        assert_eq!(batches[2][99].vout, 299);
+
+        let batches = plan_consolidation(&utxos, fee_rate, 5_803);
+        assert_eq!(batches.len(), 4);
+        assert!(batches.iter().all(|batch| estimate_tx_vsize(
+            &vec![SpendKind::KeyPath; batch.len()],
+            &[placeholder_p2tr_output()]
+        ) <= 5_803));
+
+        // 一整批都放进标准交易上限以内
+        assert_eq!(
+            plan_consolidation(&utxos, fee_rate, MAX_STANDARD_TX_VSIZE).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_plan_consolidation_skips_uneconomical_inputs() {
+        // 10 sat/vB 时花一个 546 sat 的 input 要 580 sat，不值得
+        let mut utxos: Vec<AlchemyTxOut> = (0..10)
+            .map(|vout| AlchemyTxOut::fixture(placeholder_p2tr_output().script_pubkey, 546, vout))
+            .collect();
+        let fee_rate = FeeRate::from_sat_per_vb(10).unwrap();
+        assert!(plan_consolidation(&utxos, fee_rate, MAX_STANDARD_TX_VSIZE).is_empty());
+
+        utxos.push(AlchemyTxOut::fixture(
+            placeholder_p2tr_output().script_pubkey,
+            50_000,
+            10,
+        ));
+        let batches = plan_consolidation(&utxos, fee_rate, MAX_STANDARD_TX_VSIZE);
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].len(), 1);
+        assert_eq!(batches[0][0].vout, 10);
+    }
+    #[test]
+    fn test_true_up_fee_hits_target_rate() {
+        use crate::transactions::{compute_keypath_sighashes, finalize_keypath_tx};
+        use bitcoin::key::TapTweak;
+        use bitcoin::sighash::TapSighashType;
+
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_seckey_slice(&secp, &[1u8; 32]).unwrap();
+        let tweaked = keypair.tap_tweak(&secp, None).to_keypair();
+        let script = ScriptBuf::new_p2tr(&secp, keypair.x_only_public_key().0, None);
+        let prevouts = vec![
+            TxOut {
+                value: Amount::from_sat(20_000),
+                script_pubkey: script.clone(),
+            };
+            2
+        ];
+        let sign = |tx: Transaction| {
+            let signatures = compute_keypath_sighashes(&tx, &prevouts, TapSighashType::Default)
+                .unwrap()
+                .into_iter()
+                .map(|sighash| {
+                    let msg = bitcoin::secp256k1::Message::from(sighash);
+                    secp.sign_schnorr_no_aux_rand(&msg, &tweaked)
+                        .as_ref()
+                        .to_vec()
+                })
+                .collect();
+            finalize_keypath_tx(tx, signatures).unwrap()
+        };
+
+        // 故意多付手续费：支付 25_000，找零 10_000，手续费 5_000
+        let mut unsigned = dummy_tx(
+            Witness::new(),
+            vec![
+                TxOut {
+                    value: Amount::from_sat(25_000),
+                    script_pubkey: script.clone(),
+                },
+                TxOut {
+                    value: Amount::from_sat(10_000),
+                    script_pubkey: script.clone(),
+                },
+            ],
+        );
+        unsigned.input.push(TxIn {
+            previous_output: OutPoint {
+                vout: 1,
+                ..OutPoint::null()
+            },
+            ..unsigned.input[0].clone()
+        });
+        let mut tx = sign(unsigned);
+        let vsize = tx.vsize() as u64;
+
+        let target = FeeRate::from_sat_per_vb(3).unwrap();
+        let fee = true_up_fee(&mut tx, &prevouts, 1, target).unwrap();
+        assert_eq!(fee, Amount::from_sat(3 * vsize));
+        assert_eq!(tx.output[1].value, Amount::from_sat(15_000 - 3 * vsize));
+
+        // 重新签名后 vsize 不变，实际费率与目标一致（向上取整后误差不超过 1 sat/vB）
+        let tx = sign(tx);
+        assert_eq!(tx.vsize() as u64, vsize);
+        let (actual_fee, actual_rate) = actual_fee_rate(&tx, &prevouts).unwrap();
+        assert_eq!(actual_fee, fee);
+        assert!(actual_rate.to_sat_per_vb_ceil().abs_diff(3) <= 1);
+
+        // 目标费率过高，找零会低于 dust：报错且不修改交易
+        let mut too_expensive = tx.clone();
+        let high = FeeRate::from_sat_per_vb(95).unwrap();
+        assert!(true_up_fee(&mut too_expensive, &prevouts, 1, high).is_err());
+        assert_eq!(too_expensive, tx);
+        assert!(true_up_fee(&mut too_expensive, &prevouts, 2, target).is_err());
+    }
+
+    #[test]
+    fn test_min_replacement_fee() {
+        let relay = FeeRate::from_sat_per_vb(1).unwrap();
+        assert_eq!(
+            min_replacement_fee(Amount::from_sat(1_000), 150, relay),
+            Amount::from_sat(1_150)
+        );
+        assert_eq!(
+            min_replacement_fee(Amount::from_sat(1_000), 150, FeeRate::ZERO),
+            Amount::from_sat(1_000)
+        );
+    }
+
+    #[test]
+    fn test_bump_fee_enforces_min_replacement_fee() {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_seckey_slice(&secp, &[1u8; 32]).unwrap();
+        let script = ScriptBuf::new_p2tr(&secp, keypair.x_only_public_key().0, None);
+        let prevouts = vec![TxOut {
+            value: Amount::from_sat(20_000),
+            script_pubkey: script.clone(),
+        }];
+        let tx = dummy_tx(
+            Witness::from_slice(&[[0u8; 64]]),
+            vec![TxOut {
+                value: Amount::from_sat(19_000),
+                script_pubkey: script,
+            }],
+        );
+        let vsize = tx.vsize() as u64;
+        // 原交易 9 sat/vB，最低替换手续费正好是 10 sat/vB
+        let original_fee = Amount::from_sat(9 * vsize);
+        let relay = FeeRate::from_sat_per_vb(1).unwrap();
+        let min_fee = min_replacement_fee(original_fee, vsize, relay);
+        assert_eq!(min_fee, Amount::from_sat(10 * vsize));
+
+        // 刚好等于最低手续费：接受
+        let mut bumped = tx.clone();
+        let at_min = FeeRate::from_sat_per_vb(10).unwrap();
+        let fee = bump_fee(&mut bumped, &prevouts, 0, original_fee, at_min, relay).unwrap();
+        assert_eq!(fee, min_fee);
+        assert_eq!(bumped.output[0].value, Amount::from_sat(20_000) - fee);
+
+        // 原交易多付 1 sat，10 sat/vB 就比最低手续费差 1 sat：拒绝且不修改交易
+        let original_fee = original_fee + Amount::from_sat(1);
+        assert_eq!(
+            min_replacement_fee(original_fee, vsize, relay),
+            fee + Amount::from_sat(1)
+        );
+        let mut rejected = tx.clone();
+        let err = bump_fee(&mut rejected, &prevouts, 0, original_fee, at_min, relay).unwrap_err();
+        assert!(err.to_string().contains("insufficient fee"));
+        assert_eq!(rejected, tx);
+    }
+
+    fn utxo_info(value: u64) -> UtxoInfo {
+        UtxoInfo {
+            txid: format!("{:064x}", value),
+            vout: 0,
+            value,
+            confirmations: Some(6),
+        }
+    }
+
+    #[test]
+    fn test_select_utxos_bnb_finds_changeless_solution() {
+        let fee_rate = FeeRate::from_sat_per_vb(2).unwrap();
+        let utxos: Vec<UtxoInfo> = [40_000, 30_000, 20_000, 7_000]
+            .into_iter()
+            .map(utxo_info)
+            .collect();
+
+        // 30_000 + 20_000 扣掉 2 个 input、1 个输出的手续费后正好等于 target
+        let fee = estimate_fee(
+            &[SpendKind::KeyPath; 2],
+            &[placeholder_p2tr_output()],
+            fee_rate,
+        )
+        .unwrap();
+        let target = Amount::from_sat(50_000) - fee;
+
+        let selected = select_utxos_bnb(&utxos, target, fee_rate).unwrap();
+        let mut values: Vec<u64> = selected.iter().map(|u| u.value).collect();
+        values.sort();
+        assert_eq!(values, vec![20_000, 30_000]);
+    }
+
+    #[test]
+    fn test_select_utxos_bnb_falls_back_to_largest_first() {
+        let fee_rate = FeeRate::from_sat_per_vb(2).unwrap();
+        let utxos: Vec<UtxoInfo> = [20_000, 60_000, 30_000]
+            .into_iter()
+            .map(utxo_info)
+            .collect();
+
+        // 任何组合都会多出远超找零成本的金额，没有不找零的解
+        let selected = select_utxos_bnb(&utxos, Amount::from_sat(10_000), fee_rate).unwrap();
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].value, 60_000);
+
+        // 余额不够
+        assert!(select_utxos_bnb(&utxos, Amount::from_sat(110_000), fee_rate).is_none());
+    }
+
+    #[test]
+    fn test_inscription_plan_trapped_value() {
+        let postage = Amount::from_sat(1_000);
+        assert_eq!(InscriptionPlan::new(postage, 1).trapped_value(), postage);
+        let batch = InscriptionPlan::new(postage, 25);
+        assert_eq!(batch.trapped_value(), Amount::from_sat(25_000));
+        assert_eq!(batch.postage_warning(), None);
+
+        assert!(
+            InscriptionPlan::new(Amount::from_sat(330), 1)
+                .postage_warning()
+                .unwrap()
+                .contains("dust")
+        );
+        assert!(
+            InscriptionPlan::new(Amount::from_sat(50_000), 3)
+                .postage_warning()
+                .unwrap()
+                .contains("150000 sat")
+        );
+        assert_eq!(
+            InscriptionPlan::new(MAX_ADVISED_POSTAGE, 1).postage_warning(),
+            None
+        );
+    }
+}
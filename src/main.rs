@@ -1,47 +1,133 @@
 mod alchemy_client;
+mod amount;
+mod bip322;
+mod cli;
 mod env_config;
+mod fees;
+mod psbt;
 mod rune_decode;
 mod runes_builder;
+mod serde_u128;
+mod tagged_hash;
 mod transactions;
+mod tx_template;
 mod txs;
 mod utils;
 mod wallets;
 
 use bitcoin::key::Secp256k1;
+use bitcoin::{Address, FeeRate};
+use clap::Parser;
 
-use crate::{
-    alchemy_client::AlchemyClient, transactions::create_commit_tx, utils::build_inscription_script,
-};
+use crate::alchemy_client::AlchemyClient;
+use crate::cli::{Cli, Command};
+use crate::rune_decode::RunesParser;
 use env_config::ENV_CONFIGS;
 use wallets::create_taproot_wallet;
 
-#[tokio::main]
-async fn main() {
-    let alchemy = AlchemyClient::new(&ENV_CONFIGS.alchemy_api_url);
+/// 连接节点，并确认节点所在的链与钱包网络一致
+async fn connect() -> Result<AlchemyClient, Box<dyn std::error::Error>> {
+    let alchemy = AlchemyClient::new(&ENV_CONFIGS.alchemy_api_url, ENV_CONFIGS.network);
+    alchemy.check_network(ENV_CONFIGS.network).await?;
+    Ok(alchemy)
+}
+
+fn parse_address(address: &str) -> Result<Address, Box<dyn std::error::Error>> {
+    Ok(transactions::parse_address(address, ENV_CONFIGS.network)?)
+}
+
+fn parse_fee_rate(sat_per_vb: u64) -> Result<FeeRate, Box<dyn std::error::Error>> {
+    Ok(FeeRate::from_sat_per_vb(sat_per_vb).ok_or("fee rate overflow")?)
+}
 
+fn broadcast_mode(dry_run: bool) -> txs::BroadcastMode {
+    if dry_run {
+        txs::BroadcastMode::DryRun
+    } else {
+        txs::BroadcastMode::Broadcast
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
     let secp = Secp256k1::<bitcoin::secp256k1::All>::new();
-    let taproot_wallet = create_taproot_wallet(&secp).unwrap();
-
-    // let txid1 = "aaeb4cde567a87b332bbc9bf983e1059abea623470a40aff43d886493a32067c";
-    // let txid2 = "ec2a26543197c61dfebed3c05f95c78d30b500cf260e7a0ee8697e42505f0ba0";
-    // let txid3 = "b1a49c7d0b2ce71a606c3cc2d74f0feac9b749d0d4aa1e4ce7659f7e682b45eb";
-
-    let txid4 = "86f80251d4ff271863bf7ce7f6ce1ba2e9551110ca2d86f5cbdcfda12111df37";
-    let txid5 = "43e447c5cb23868653680858a51dce44f1e08a84dbf79a29194f618c70eb3826";
-    let txid6 = "bce080d10728e82a20f50e861580e6d6da9a116d493026348ad36aca981d510e";
-
-    // txs::tx_inscription_commit(&alchemy, &secp, &taproot_wallet, txid4, 1).await;
-    // txs::tx_brc20_deploy(&alchemy, &secp, &taproot_wallet, txid5, 0).await;
-    // txs::tx_rune_deploy(&alchemy, &secp, &taproot_wallet, txid6, 0).await;
-
-    txs::verify_signature(
-        &alchemy,
-        &secp,
-        &taproot_wallet,
-        "6a67c15f9baf65814d5215039a8108a66f688bcb8c6a82bd474c62e5bb2c9049",
-        2,
-    )
-    .await;
+
+    match cli.command {
+        Command::Address => {
+            let taproot_wallet = create_taproot_wallet(&secp)?;
+            println!("{}", taproot_wallet.get_internal_address());
+        }
+        Command::Balance { address } => {
+            let address = parse_address(&address)?;
+            let alchemy = connect().await?;
+            let balance = txs::balance(&alchemy, &address).await?;
+            println!(
+                "  💰 Balance: {} sat ({} BTC)",
+                balance.to_sat(),
+                amount::sat_to_btc_string(balance.to_sat())
+            );
+        }
+        Command::Send {
+            address,
+            amount,
+            fee_rate,
+            min_confirmations,
+            dry_run,
+        } => {
+            let destination = parse_address(&address)?;
+            let taproot_wallet = create_taproot_wallet(&secp)?;
+            let alchemy = connect().await?;
+            txs::send(
+                &alchemy,
+                &secp,
+                &taproot_wallet,
+                &destination,
+                amount,
+                parse_fee_rate(fee_rate)?,
+                min_confirmations,
+                broadcast_mode(dry_run),
+            )
+            .await?;
+        }
+        Command::Etch {
+            name,
+            divisibility,
+            premine,
+            cap,
+            fee_rate,
+            min_confirmations,
+            dry_run,
+        } => {
+            let taproot_wallet = create_taproot_wallet(&secp)?;
+            let alchemy = connect().await?;
+            txs::etch_rune(
+                &alchemy,
+                &secp,
+                &taproot_wallet,
+                &name,
+                divisibility,
+                premine,
+                cap,
+                parse_fee_rate(fee_rate)?,
+                min_confirmations,
+                broadcast_mode(dry_run),
+            )
+            .await?;
+        }
+        Command::DecodeRunestone { hex } => match RunesParser::parse_script_hex(&hex)? {
+            Some(runestone) => println!("{:#?}", runestone.decoded()?),
+            None => println!("❌ 不是 runestone 脚本"),
+        },
+        Command::InspectTx { hex } => println!("{:#?}", utils::inspect_transaction(&hex)?),
+        Command::VerifySignature { txid, vout } => {
+            let taproot_wallet = create_taproot_wallet(&secp)?;
+            let alchemy = connect().await?;
+            txs::verify_signature(&alchemy, &secp, &taproot_wallet, &txid, vout).await;
+        }
+    }
+
+    Ok(())
 }
 
 // async fn main() {
@@ -0,0 +1,274 @@
+use std::str::FromStr;
+
+use bitcoin::{Psbt, Transaction, Txid};
+
+use crate::alchemy_client::{AlchemyClient, PrevoutCache};
+
+/// =====================================================
+/// PSBT base64 序列化 / 解析
+/// =====================================================
+///
+/// Sparrow、硬件钱包等都使用 BIP174 的 base64 文本格式交换 PSBT
+pub fn psbt_to_base64(psbt: &Psbt) -> String {
+    psbt.to_string()
+}
+
+pub fn psbt_from_base64(s: &str) -> Result<Psbt, Box<dyn std::error::Error>> {
+    Ok(Psbt::from_str(s.trim())?)
+}
+
+/// =====================================================
+/// 合并多个签名方的 PSBT
+/// =====================================================
+///
+/// 两个 PSBT 必须基于同一笔未签名交易，合并后包含双方的签名和元数据
+pub fn combine_psbts(mut a: Psbt, b: Psbt) -> Result<Psbt, Box<dyn std::error::Error>> {
+    a.combine(b)?;
+    Ok(a)
+}
+
+/// =====================================================
+/// 从未签名交易构造 PSBT
+/// =====================================================
+///
+/// 每个 input 的 witness_utxo 来自 PrevoutCache（一次 batch gettxout 取回缺失的 prevout）。
+///
+/// include_non_witness_utxo 为 true 时，再用一次 batch getrawtransaction 取回所有父交易，
+/// 填入 non_witness_utxo：部分硬件钱包（如 Trezor）即使是 segwit input 也要求完整父交易，
+/// 用来防止篡改 prevout 金额的手续费攻击。父交易可能很大，所以默认不取
+pub async fn build_psbt(
+    alchemy: &AlchemyClient,
+    tx: Transaction,
+    prevouts: &mut PrevoutCache,
+    include_non_witness_utxo: bool,
+) -> Result<Psbt, Box<dyn std::error::Error>> {
+    let outpoints: Vec<_> = tx.input.iter().map(|input| input.previous_output).collect();
+    prevouts.fetch(alchemy, &outpoints).await?;
+    let witness_utxos = prevouts.prevouts_for(&tx)?;
+
+    let mut psbt = Psbt::from_unsigned_tx(tx)?;
+    for (input, witness_utxo) in psbt.inputs.iter_mut().zip(witness_utxos) {
+        input.witness_utxo = Some(witness_utxo);
+    }
+
+    if include_non_witness_utxo {
+        // 多个 input 可能来自同一笔父交易，只取一次
+        let mut txids: Vec<Txid> = outpoints.iter().map(|op| op.txid).collect();
+        txids.sort();
+        txids.dedup();
+        let parents = alchemy.get_raw_transactions_batch(&txids).await?;
+
+        for (input, outpoint) in psbt.inputs.iter_mut().zip(&outpoints) {
+            let index = txids
+                .binary_search(&outpoint.txid)
+                .map_err(|_| "missing parent")?;
+            input.non_witness_utxo = Some(parents[index].clone());
+        }
+    }
+
+    Ok(psbt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::key::Secp256k1;
+    use bitcoin::secp256k1::schnorr;
+    use bitcoin::sighash::TapSighashType;
+    use bitcoin::transaction::Version;
+    use bitcoin::{
+        Amount, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness, absolute, taproot,
+    };
+
+    use crate::alchemy_client::mock_rpc::{client_for, mock_batch};
+    use crate::wallets::{TEST_MNEMONIC, create_taproot_wallet_from_mnemonic};
+    use bitcoin::consensus::encode::serialize_hex;
+    use serde_json::{Value, json};
+    use wiremock::MockServer;
+
+    fn unsigned_psbt() -> Psbt {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, bitcoin::Network::Testnet)
+                .unwrap();
+
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(9_000),
+                script_pubkey: wallet.get_internal_address().script_pubkey(),
+            }],
+        };
+
+        let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(TxOut {
+            value: Amount::from_sat(10_000),
+            script_pubkey: wallet.get_internal_address().script_pubkey(),
+        });
+        psbt.inputs[0].tap_internal_key = Some(wallet.internal_xonly());
+        psbt
+    }
+
+    #[test]
+    fn test_psbt_base64_round_trip() {
+        let psbt = unsigned_psbt();
+
+        let encoded = psbt_to_base64(&psbt);
+        assert!(encoded.starts_with("cHNidP8"));
+
+        let decoded = psbt_from_base64(&encoded).unwrap();
+        assert_eq!(decoded.serialize(), psbt.serialize());
+        assert_eq!(decoded, psbt);
+    }
+
+    #[test]
+    fn test_combine_psbts_merges_signatures() {
+        let base = unsigned_psbt();
+
+        let mut signed = base.clone();
+        signed.inputs[0].tap_key_sig = Some(taproot::Signature {
+            signature: schnorr::Signature::from_slice(&[1u8; 64]).unwrap(),
+            sighash_type: TapSighashType::Default,
+        });
+
+        let combined = combine_psbts(base, signed.clone()).unwrap();
+        assert_eq!(combined, signed);
+
+        // 不同交易的 PSBT 不能合并
+        let mut other = unsigned_psbt();
+        other.unsigned_tx.output[0].value = Amount::from_sat(8_000);
+        let other = Psbt::from_unsigned_tx(other.unsigned_tx).unwrap();
+        assert!(combine_psbts(signed, other).is_err());
+    }
+
+    /// 父交易：outputs 个 P2TR 输出，金额依次为 10_000、20_000 …
+    fn parent_tx(lock_time: u32, outputs: u64) -> Transaction {
+        let script_pubkey = unsigned_psbt().inputs[0]
+            .witness_utxo
+            .clone()
+            .unwrap()
+            .script_pubkey;
+        Transaction {
+            version: Version::TWO,
+            lock_time: absolute::LockTime::from_consensus(lock_time),
+            input: vec![TxIn::default()],
+            output: (1..=outputs)
+                .map(|i| TxOut {
+                    value: Amount::from_sat(10_000 * i),
+                    script_pubkey: script_pubkey.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// 两个父交易、三个 input 的 PSBT 环境，mock 按父交易回答 gettxout / getrawtransaction
+    async fn psbt_fixture() -> (MockServer, Vec<Transaction>, Transaction) {
+        let parents = vec![parent_tx(1, 2), parent_tx(2, 1)];
+        let server = MockServer::start().await;
+        let lookup = parents.clone();
+        mock_batch(&server, move |rpc_method, params| {
+            let txid: Txid = params[0].as_str().unwrap().parse().unwrap();
+            let parent = lookup.iter().find(|tx| tx.compute_txid() == txid).unwrap();
+            match rpc_method {
+                "gettxout" => {
+                    let output = &parent.output[params[1].as_u64().unwrap() as usize];
+                    json!({
+                        "bestblock": "0000000000000000000123",
+                        "confirmations": 1,
+                        "value": output.value.to_btc(),
+                        "scriptPubKey": { "asm": "", "hex": output.script_pubkey.to_hex_string() },
+                        "coinbase": false
+                    })
+                }
+                "getrawtransaction" => json!(serialize_hex(parent)),
+                other => panic!("unexpected method {}", other),
+            }
+        })
+        .await;
+
+        let spend = |tx: &Transaction, vout| TxIn {
+            previous_output: OutPoint {
+                txid: tx.compute_txid(),
+                vout,
+            },
+            ..TxIn::default()
+        };
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![
+                spend(&parents[0], 0),
+                spend(&parents[0], 1),
+                spend(&parents[1], 0),
+            ],
+            output: vec![TxOut {
+                value: Amount::from_sat(35_000),
+                script_pubkey: ScriptBuf::new(),
+            }],
+        };
+        (server, parents, tx)
+    }
+
+    async fn batch_methods(server: &MockServer) -> Vec<Vec<String>> {
+        server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .map(|req| {
+                req.body_json::<Vec<Value>>()
+                    .unwrap()
+                    .iter()
+                    .map(|call| call["method"].as_str().unwrap().to_string())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_build_psbt_with_non_witness_utxo() {
+        let (server, parents, tx) = psbt_fixture().await;
+        let alchemy = client_for(&server);
+
+        let psbt = build_psbt(&alchemy, tx, &mut PrevoutCache::new(), true)
+            .await
+            .unwrap();
+
+        let expected = [(&parents[0], 0), (&parents[0], 1), (&parents[1], 0)];
+        for (input, (parent, vout)) in psbt.inputs.iter().zip(expected) {
+            assert_eq!(input.witness_utxo.as_ref(), Some(&parent.output[vout]));
+            assert_eq!(input.non_witness_utxo.as_ref(), Some(parent));
+        }
+
+        // gettxout 一次 batch，两个不同的父交易一次 batch
+        assert_eq!(
+            batch_methods(&server).await,
+            vec![vec!["gettxout"; 3], vec!["getrawtransaction"; 2],]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_build_psbt_without_non_witness_utxo() {
+        let (server, _, tx) = psbt_fixture().await;
+        let alchemy = client_for(&server);
+
+        let psbt = build_psbt(&alchemy, tx, &mut PrevoutCache::new(), false)
+            .await
+            .unwrap();
+
+        assert!(psbt.inputs.iter().all(|input| input.witness_utxo.is_some()));
+        assert!(
+            psbt.inputs
+                .iter()
+                .all(|input| input.non_witness_utxo.is_none())
+        );
+        assert_eq!(batch_methods(&server).await, vec![vec!["gettxout"; 3]]);
+    }
+}
@@ -8,10 +8,16 @@
 /// 3. 之后才是实际的 Runestone 数据
 ///
 use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
 
 /// =====================================================
 /// VarInt 解码器
 /// =====================================================
+/// u128 的 LEB128 编码最多 19 个字节（19 × 7 = 133 位）
+pub const MAX_VARINT_LEN: usize = 19;
+
 pub struct VarIntDecoder {
     data: Vec<u8>,
     pos: usize,
@@ -23,53 +29,62 @@ impl VarIntDecoder {
     }
 
     /// 解码单个 VarInt
+    ///
+    /// 按 Runes 规范使用 LEB128：每字节低 7 位是数据（低位在前），最高位为 1 表示后面还有字节。
+    /// u128 最多 19 个字节，第 19 个字节只能用低 2 位；超出、缺少结束字节都视为错误
     pub fn decode_varint(&mut self) -> Result<u128, String> {
-        if self.pos >= self.data.len() {
-            return Err("超过数据长度".to_string());
-        }
+        let mut value: u128 = 0;
 
-        let byte = self.data[self.pos];
-        self.pos += 1;
+        for i in 0..MAX_VARINT_LEN {
+            let Some(&byte) = self.data.get(self.pos + i) else {
+                return Err("VarInt 没有结束字节".to_string());
+            };
 
-        match byte {
-            // 0-252: 直接值
-            0..=252 => Ok(byte as u128),
-            // 0xFD: 下 2 字节小端序
-            0xFD => {
-                if self.pos + 1 >= self.data.len() {
-                    return Err("VarInt 数据不足 (0xFD)".to_string());
-                }
-                let bytes = [self.data[self.pos], self.data[self.pos + 1]];
-                self.pos += 2;
-                Ok(u16::from_le_bytes(bytes) as u128)
+            if i == MAX_VARINT_LEN - 1 && byte & 0b0111_1100 != 0 {
+                return Err("VarInt 超出 u128 范围".to_string());
             }
-            // 0xFE: 下 4 字节小端序
-            0xFE => {
-                if self.pos + 3 >= self.data.len() {
-                    return Err("VarInt 数据不足 (0xFE)".to_string());
-                }
-                let mut bytes = [0u8; 4];
-                bytes.copy_from_slice(&self.data[self.pos..self.pos + 4]);
-                self.pos += 4;
-                Ok(u32::from_le_bytes(bytes) as u128)
-            }
-            // 0xFF: 下 8 字节小端序
-            0xFF => {
-                if self.pos + 7 >= self.data.len() {
-                    return Err("VarInt 数据不足 (0xFF)".to_string());
-                }
-                let mut bytes = [0u8; 8];
-                bytes.copy_from_slice(&self.data[self.pos..self.pos + 8]);
-                self.pos += 8;
-                Ok(u64::from_le_bytes(bytes) as u128)
+            value |= u128::from(byte & 0x7F) << (7 * i);
+
+            if byte & 0x80 == 0 {
+                self.pos += i + 1;
+                return Ok(value);
             }
         }
+
+        Err(format!("VarInt 超过 {} 字节", MAX_VARINT_LEN))
     }
 
     pub fn is_eof(&self) -> bool {
         self.pos >= self.data.len()
     }
 
+    /// 查看下一个字节但不前进，已到末尾时返回 None
+    pub fn peek_byte(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    /// 尚未解码的剩余字节（不前进）
+    pub fn remaining(&self) -> &[u8] {
+        &self.data[self.pos.min(self.data.len())..]
+    }
+
+    /// 连续解码 n 个 VarInt（如 edict 的 4 元组）；
+    /// 数据不足 n 个时返回错误，且位置回到调用前，不会只消费一部分
+    pub fn decode_n_varints(&mut self, n: usize) -> Result<Vec<u128>, String> {
+        let start = self.pos;
+        let mut values = Vec::with_capacity(n);
+        for _ in 0..n {
+            match self.decode_varint() {
+                Ok(value) => values.push(value),
+                Err(e) => {
+                    self.pos = start;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(values)
+    }
+
     pub fn position(&self) -> usize {
         self.pos
     }
@@ -78,9 +93,311 @@ impl VarIntDecoder {
 /// =====================================================
 /// Runes 数据结构
 /// =====================================================
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Runestone {
+    #[serde(with = "crate::serde_u128::map")]
     pub fields: HashMap<u128, u128>,
+    /// MINT (Tag 3) 出现两次，依次为 block 和 tx，单独存放
+    pub mint: Option<RuneId>,
+    /// BODY 之后的 edicts（已还原 delta 编码）
+    pub edicts: Vec<Edict>,
+    /// 解析中发现的格式问题，存在时该 runestone 为 cenotaph
+    pub flaw: Option<String>,
+}
+
+/// =====================================================
+/// FLAGS 位域
+/// =====================================================
+///
+/// bit 0 = etching，bit 1 = terms，bit 2 = turbo（规范中的位置）；
+/// 其它被置位的 bit 记入 unrecognized，出现时 runestone 为 cenotaph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Flags {
+    pub etching: bool,
+    pub terms: bool,
+    pub turbo: bool,
+    #[serde(with = "crate::serde_u128")]
+    pub unrecognized: u128,
+}
+
+impl Flags {
+    const ETCHING: u128 = 1 << 0;
+    const TERMS: u128 = 1 << 1;
+    const TURBO: u128 = 1 << 2;
+
+    pub fn from_bits(bits: u128) -> Flags {
+        Flags {
+            etching: bits & Self::ETCHING != 0,
+            terms: bits & Self::TERMS != 0,
+            turbo: bits & Self::TURBO != 0,
+            unrecognized: bits & !(Self::ETCHING | Self::TERMS | Self::TURBO),
+        }
+    }
+}
+
+/// 把 RUNE 字段的整数还原为名称（与 runes_builder::rune_name_to_integer 相反：
+/// 每个字节一个字母，A=1 ... Z=26，0 为分隔符 •）
+pub fn rune_integer_to_name(mut value: u128) -> String {
+    let mut name = String::new();
+    while value != 0 {
+        let ch = match (value & 0xFF) as u8 {
+            0 => '•',
+            b @ 1..=26 => (b'A' + b - 1) as char,
+            _ => '?',
+        };
+        name.push(ch);
+        value >>= 8;
+    }
+    name
+}
+
+/// etching 相关的字段（FLAGS 中设置了 etching 时才有）
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Etching {
+    pub rune: Option<String>,
+    pub divisibility: Option<u8>,
+    #[serde(with = "crate::serde_u128::option")]
+    pub premine: Option<u128>,
+    pub symbol: Option<char>,
+    pub spacers: Option<u32>,
+    #[serde(with = "crate::serde_u128::option")]
+    pub cap: Option<u128>,
+    #[serde(with = "crate::serde_u128::option")]
+    pub amount: Option<u128>,
+    pub turbo: bool,
+}
+
+/// =====================================================
+/// mint 条件（etching 的 terms）
+/// =====================================================
+///
+/// height 为绝对区块高度窗口（规范 tag 12/14），offset 为相对 etching 所在区块的窗口
+/// （tag 16/18）；两者都是 [start, end)，未设置的一端不限制。
+/// 本项目的 tag 表中 12 已用于 DIVISIBILITY，parser 目前不解码这些字段，由调用方填入
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Terms {
+    pub amount: Option<u128>,
+    pub cap: Option<u128>,
+    pub height: (Option<u64>, Option<u64>),
+    pub offset: (Option<u64>, Option<u64>),
+}
+
+/// current_height（mint 交易被打包的高度）是否在 terms 的 mint 窗口内；
+/// 窗口外的 mint 不会得到任何 rune
+pub fn mint_is_open(terms: &Terms, etching_height: u64, current_height: u64) -> bool {
+    let (height_start, height_end) = terms.height;
+    let (offset_start, offset_end) = terms.offset;
+
+    if height_start.is_some_and(|start| current_height < start) {
+        return false;
+    }
+    if height_end.is_some_and(|end| current_height >= end) {
+        return false;
+    }
+    // 相对窗口溢出 u64 时：start 永远到不了，end 永远不会结束
+    if offset_start.is_some_and(|start| {
+        etching_height
+            .checked_add(start)
+            .is_none_or(|start| current_height < start)
+    }) {
+        return false;
+    }
+    if offset_end.is_some_and(|end| {
+        etching_height
+            .checked_add(end)
+            .is_some_and(|end| current_height >= end)
+    }) {
+        return false;
+    }
+
+    true
+}
+
+/// 两个解码结果中不同的字段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDiff {
+    pub field: &'static str,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl fmt::Display for FieldDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} differs: expected {} got {}",
+            self.field, self.expected, self.actual
+        )
+    }
+}
+
+/// 解码后的 runestone 中转账相关的字段
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodedRunestone {
+    pub edicts: Vec<Edict>,
+    /// 未分配的 rune 转入的输出序号，未设置时为第一个非 OP_RETURN 输出
+    pub pointer: Option<u32>,
+    /// FLAGS 字段，terms 为 true 时才需要关心 AMOUNT / CAP
+    pub flags: Flags,
+    /// 本交易 mint 的 rune
+    pub mint: Option<RuneId>,
+    pub etching: Option<Etching>,
+    pub cenotaph: bool,
+}
+
+impl DecodedRunestone {
+    /// 逐个字段比较，返回所有不同的字段（self 为期望值）
+    pub fn diff(&self, other: &DecodedRunestone) -> Vec<FieldDiff> {
+        fn check<T: fmt::Debug + PartialEq>(
+            diffs: &mut Vec<FieldDiff>,
+            field: &'static str,
+            expected: &T,
+            actual: &T,
+        ) {
+            if expected != actual {
+                diffs.push(FieldDiff {
+                    field,
+                    expected: format!("{:?}", expected),
+                    actual: format!("{:?}", actual),
+                });
+            }
+        }
+
+        let mut diffs = Vec::new();
+        check(&mut diffs, "edicts", &self.edicts, &other.edicts);
+        check(&mut diffs, "pointer", &self.pointer, &other.pointer);
+        check(&mut diffs, "flags", &self.flags, &other.flags);
+        check(&mut diffs, "mint", &self.mint, &other.mint);
+        check(&mut diffs, "cenotaph", &self.cenotaph, &other.cenotaph);
+
+        match (&self.etching, &other.etching) {
+            (Some(a), Some(b)) => {
+                check(&mut diffs, "etching.rune", &a.rune, &b.rune);
+                check(
+                    &mut diffs,
+                    "etching.divisibility",
+                    &a.divisibility,
+                    &b.divisibility,
+                );
+                check(&mut diffs, "etching.premine", &a.premine, &b.premine);
+                check(&mut diffs, "etching.symbol", &a.symbol, &b.symbol);
+                check(&mut diffs, "etching.spacers", &a.spacers, &b.spacers);
+                check(&mut diffs, "etching.cap", &a.cap, &b.cap);
+                check(&mut diffs, "etching.amount", &a.amount, &b.amount);
+                check(&mut diffs, "etching.turbo", &a.turbo, &b.turbo);
+            }
+            (a, b) => check(&mut diffs, "etching", a, b),
+        }
+
+        diffs
+    }
+}
+
+impl Runestone {
+    /// POINTER 超出 u32 时返回错误（输出序号不可能这么大），不会悄悄丢掉
+    pub fn decoded(&self) -> Result<DecodedRunestone, String> {
+        let pointer = self
+            .fields
+            .get(&POINTER)
+            .map(|p| u32::try_from(*p).map_err(|_| format!("POINTER {} overflows u32", p)))
+            .transpose()?;
+
+        Ok(DecodedRunestone {
+            edicts: self.edicts.clone(),
+            pointer,
+            flags: self.flags(),
+            mint: self.mint,
+            etching: self.etching(),
+            cenotaph: self.is_cenotaph(),
+        })
+    }
+
+    /// FLAGS 中设置了 etching 时，取出 etching 相关字段
+    pub fn etching(&self) -> Option<Etching> {
+        let flags = self.flags();
+        if !flags.etching {
+            return None;
+        }
+        let field = |tag: u128| self.fields.get(&tag).copied();
+
+        Some(Etching {
+            rune: field(RUNE).map(rune_integer_to_name),
+            divisibility: field(DIVISIBILITY).and_then(|d| u8::try_from(d).ok()),
+            premine: field(PREMINE),
+            symbol: field(SYMBOL)
+                .and_then(|s| u32::try_from(s).ok())
+                .and_then(char::from_u32),
+            spacers: field(SPACERS).and_then(|s| u32::try_from(s).ok()),
+            cap: field(CAP),
+            amount: field(AMOUNT),
+            turbo: flags.turbo || field(TURBO).is_some(),
+        })
+    }
+
+    pub fn flags(&self) -> Flags {
+        Flags::from_bits(self.fields.get(&FLAGS).copied().unwrap_or(0))
+    }
+
+    /// 是否为 cenotaph（无效 runestone，输入中的所有 rune 都会被烧掉）
+    ///
+    /// 出现 CENOTAPH 标签、任何未识别的偶数标签、未识别的 FLAGS 位或 edict 格式问题即为 cenotaph；
+    /// 未识别的奇数标签按规范忽略
+    pub fn is_cenotaph(&self) -> bool {
+        self.flaw.is_some()
+            || self.flags().unrecognized != 0
+            || self
+                .fields
+                .keys()
+                .any(|tag| *tag == CENOTAPH || (tag % 2 == 0 && !KNOWN_TAGS.contains(tag)))
+    }
+}
+
+/// =====================================================
+/// Rune ID
+/// =====================================================
+///
+/// etching 交易所在的区块高度 + 该交易在区块中的序号，显示为 `block:tx`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct RuneId {
+    pub block: u64,
+    pub tx: u32,
+}
+
+impl RuneId {
+    /// edict 中的 0:0 指本交易 etching 的 rune：etching 确认前还没有 id，
+    /// 这样才能在同一笔交易里 etch 并分发
+    pub const ETCHED: RuneId = RuneId { block: 0, tx: 0 };
+
+    pub fn is_etched(&self) -> bool {
+        *self == Self::ETCHED
+    }
+}
+
+impl fmt::Display for RuneId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.block, self.tx)
+    }
+}
+
+/// =====================================================
+/// Edict：把某种 rune 的一部分余额转到指定输出
+/// =====================================================
+///
+/// amount 为 0 表示「该 rune 的全部剩余余额」；
+/// output 等于交易输出数量时表示平分到所有非 OP_RETURN 输出
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Edict {
+    pub id: RuneId,
+    #[serde(with = "crate::serde_u128")]
+    pub amount: u128,
+    pub output: u32,
+}
+
+/// 交易输入中携带的某种 rune 余额
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuneBalance {
+    pub id: RuneId,
+    pub amount: u128,
 }
 
 /// =====================================================
@@ -88,20 +405,44 @@ pub struct Runestone {
 /// =====================================================
 pub struct RunesParser;
 
-// 标签定义
-const BODY: u128 = 0;
-const FLAGS: u128 = 2;
-const RUNE: u128 = 4;
-const SPACERS: u128 = 5;
-const SYMBOL: u128 = 6;
-const PREMINE: u128 = 7;
-const AMOUNT: u128 = 1;
-const CAP: u128 = 11;
-const MINT: u128 = 3;
-const POINTER: u128 = 8;
-const DIVISIBILITY: u128 = 12;
-const TERMS: u128 = 9;
-const TURBO: u128 = 10;
+// 标签定义（RunesBuilder 编码时使用同一组常量）
+pub const BODY: u128 = 0;
+pub const FLAGS: u128 = 2;
+pub const RUNE: u128 = 4;
+pub const SPACERS: u128 = 5;
+pub const SYMBOL: u128 = 6;
+pub const PREMINE: u128 = 7;
+pub const AMOUNT: u128 = 1;
+pub const CAP: u128 = 11;
+pub const MINT: u128 = 3;
+pub const POINTER: u128 = 8;
+pub const DIVISIBILITY: u128 = 12;
+pub const TERMS: u128 = 9;
+pub const TURBO: u128 = 10;
+pub const CENOTAPH: u128 = 126;
+
+/// 解码前的数据长度上限。标准交易的 OP_RETURN 只有 80 字节，RunesBuilder 会把更大的数据
+/// 拆成多个 520 字节的 push；这里留足余量，只用来挡住恶意构造的超长输出
+const MAX_RUNESTONE_DATA_LEN: usize = 4096;
+
+/// tag-value 对的个数上限（BODY 之前的部分）
+const MAX_RUNESTONE_FIELDS: usize = 128;
+
+const KNOWN_TAGS: [u128; 13] = [
+    BODY,
+    AMOUNT,
+    FLAGS,
+    MINT,
+    RUNE,
+    SPACERS,
+    SYMBOL,
+    PREMINE,
+    POINTER,
+    TERMS,
+    TURBO,
+    CAP,
+    DIVISIBILITY,
+];
 
 impl RunesParser {
     /// 从脚本 hex 解析
@@ -207,10 +548,20 @@ impl RunesParser {
                     runestone_data.extend_from_slice(&bytes[pos..pos + len]);
                     pos += len;
                 }
-                // 其他操作码（可能是结束或多重推送的结束）
+                // OP_0：空 push
+                0x00 => {
+                    println!("  OP_0: push 0 字节");
+                }
+                // 规范：OP_PUSHNUM_13 之后只能是数据 push，出现其他操作码时为 cenotaph
                 _ => {
-                    println!("  其他操作码: 0x{:02x}, 停止解析", op);
-                    break;
+                    let flaw = format!("OP_RETURN 中出现非 push 操作码 0x{:02x}", op);
+                    println!("⚠️ Flaw: {}", flaw);
+                    return Ok(Some(Runestone {
+                        fields: HashMap::new(),
+                        mint: None,
+                        edicts: Vec::new(),
+                        flaw: Some(flaw),
+                    }));
                 }
             }
         }
@@ -223,33 +574,79 @@ impl RunesParser {
     }
 
     /// 解析 Runestone 数据
+    ///
+    /// 数据来自任意链上脚本，不可信：超过 MAX_RUNESTONE_DATA_LEN 字节时不解码，
+    /// tag-value 对超过 MAX_RUNESTONE_FIELDS 时停止解码，两者都直接作为 cenotaph 返回
     pub fn parse_runestone_data(data: Vec<u8>) -> Result<Option<Runestone>, String> {
+        if data.len() > MAX_RUNESTONE_DATA_LEN {
+            let flaw = format!(
+                "Runestone 数据过长: {} 字节（上限 {}）",
+                data.len(),
+                MAX_RUNESTONE_DATA_LEN
+            );
+            println!("⚠️ Flaw: {}", flaw);
+            return Ok(Some(Runestone {
+                fields: HashMap::new(),
+                mint: None,
+                edicts: Vec::new(),
+                flaw: Some(flaw),
+            }));
+        }
+
         let mut decoder = VarIntDecoder::new(data);
         let mut fields: HashMap<u128, u128> = HashMap::new();
+        let mut mint_values = Vec::new();
+        let mut edicts = Vec::new();
+        let mut flaw = None;
 
         println!("📖 解析 Tag-Value 对:");
         println!("─────────────────────────────────");
 
         let mut pair_count = 0;
         while !decoder.is_eof() {
+            if pair_count == MAX_RUNESTONE_FIELDS {
+                flaw = Some(format!("Tag-Value 对超过 {} 个", MAX_RUNESTONE_FIELDS));
+                break;
+            }
+
             let tag = decoder.decode_varint()?;
             pair_count += 1;
 
             println!("\n对 {}:", pair_count);
             println!("  Tag: {}", Self::tag_name(tag));
 
-            // Tag 0 = BODY，结束
+            // Tag 0 = BODY，之后全部是 edicts
             if tag == BODY {
                 println!("  → 结束符");
+                (edicts, flaw) = Self::parse_edicts(&mut decoder);
                 break;
             }
 
             let value = decoder.decode_varint()?;
             println!("  值: {} (0x{:x})", value, value);
 
-            fields.insert(tag, value);
+            if tag == MINT {
+                mint_values.push(value);
+            } else {
+                fields.insert(tag, value);
+            }
         }
 
+        let mint = match mint_values[..] {
+            [] => None,
+            [block, tx] => match (u64::try_from(block), u32::try_from(tx)) {
+                (Ok(block), Ok(tx)) => Some(RuneId { block, tx }),
+                _ => {
+                    flaw.get_or_insert("MINT 字段溢出".to_string());
+                    None
+                }
+            },
+            _ => {
+                flaw.get_or_insert("MINT 字段必须是 block 和 tx 两个值".to_string());
+                None
+            }
+        };
+
         println!("\n✅ 解析完成\n");
 
         println!("📊 字段汇总:");
@@ -258,10 +655,81 @@ impl RunesParser {
             println!("{}: {} (0x{:x})", Self::tag_name(*tag), value, value);
         }
 
-        let runestone = Runestone { fields };
+        if let Some(mint) = &mint {
+            println!("MINT: {}", mint);
+        }
+
+        for edict in &edicts {
+            println!(
+                "EDICT: {} -> output {}, amount {}",
+                edict.id, edict.output, edict.amount
+            );
+        }
+
+        if let Some(flaw) = &flaw {
+            println!("⚠️ Flaw: {}", flaw);
+        }
+
+        let runestone = Runestone {
+            fields,
+            mint,
+            edicts,
+            flaw,
+        };
         Ok(Some(runestone))
     }
 
+    /// 解析 BODY 之后的 edicts：每 4 个 VarInt 一组 (block, tx, amount, output)，
+    /// block 相对上一个 edict 做 delta；block delta 为 0 时 tx 也是 delta
+    ///
+    /// 数据不完整或溢出不算解析错误，而是 flaw（runestone 成为 cenotaph）
+    fn parse_edicts(decoder: &mut VarIntDecoder) -> (Vec<Edict>, Option<String>) {
+        let mut edicts = Vec::new();
+        let mut block: u64 = 0;
+        let mut tx: u32 = 0;
+
+        while !decoder.is_eof() {
+            let Ok(values) = decoder.decode_n_varints(4) else {
+                return (edicts, Some("Edict 数据不完整".to_string()));
+            };
+            let (block_delta, tx_delta, amount, output) =
+                (values[0], values[1], values[2], values[3]);
+
+            let (Ok(block_delta), Ok(tx_delta), Ok(output)) = (
+                u64::try_from(block_delta),
+                u32::try_from(tx_delta),
+                u32::try_from(output),
+            ) else {
+                return (edicts, Some("Edict 字段溢出".to_string()));
+            };
+
+            if block_delta == 0 {
+                match tx.checked_add(tx_delta) {
+                    Some(next) => tx = next,
+                    None => return (edicts, Some("Edict tx 溢出".to_string())),
+                }
+            } else {
+                match block.checked_add(block_delta) {
+                    Some(next) => block = next,
+                    None => return (edicts, Some("Edict block 溢出".to_string())),
+                }
+                tx = tx_delta;
+            }
+            // block 为 0 时只有 0:0（本交易 etching 的 rune）是合法的
+            if block == 0 && tx != 0 {
+                return (edicts, Some(format!("Edict rune id 0:{} 无效", tx)));
+            }
+
+            edicts.push(Edict {
+                id: RuneId { block, tx },
+                amount,
+                output,
+            });
+        }
+
+        (edicts, None)
+    }
+
     fn tag_name(tag: u128) -> String {
         match tag {
             0 => "BODY".to_string(),
@@ -277,6 +745,7 @@ impl RunesParser {
             10 => "TURBO".to_string(),
             11 => "CAP".to_string(),
             12 => "DIVISIBILITY".to_string(),
+            126 => "CENOTAPH".to_string(),
             _ => format!("TAG_{}", tag),
         }
     }
@@ -316,4 +785,391 @@ mod tests {
         assert_eq!(decoder.decode_varint().unwrap(), 7);
         assert_eq!(decoder.decode_varint().unwrap(), 4);
     }
+
+    #[test]
+    fn test_varint_leb128_errors() {
+        // 最高位为 1 但后面没有字节
+        assert!(VarIntDecoder::new(vec![0x80]).decode_varint().is_err());
+
+        // 第 19 个字节超出 u128 的 2 位
+        let mut overflow = vec![0xff; 18];
+        overflow.push(0x04);
+        assert!(VarIntDecoder::new(overflow).decode_varint().is_err());
+
+        // 超过 19 个字节
+        let mut overlong = vec![0x80; 19];
+        overlong.push(0x00);
+        assert!(VarIntDecoder::new(overlong).decode_varint().is_err());
+
+        // 多余的 0x80 前缀仍在 19 字节内时按值解码
+        let mut decoder = VarIntDecoder::new(vec![0x81, 0x80, 0x00]);
+        assert_eq!(decoder.decode_varint().unwrap(), 1);
+        assert!(decoder.is_eof());
+    }
+
+    #[test]
+    fn test_varint_peek_and_remaining_do_not_advance() {
+        let mut decoder = VarIntDecoder::new(vec![0x80, 0x02, 0x05]);
+        assert_eq!(decoder.peek_byte(), Some(0x80));
+        assert_eq!(decoder.peek_byte(), Some(0x80));
+        assert_eq!(decoder.remaining(), &[0x80, 0x02, 0x05]);
+
+        assert_eq!(decoder.decode_varint().unwrap(), 0x0100);
+        assert_eq!(decoder.peek_byte(), Some(0x05));
+        assert_eq!(decoder.remaining(), &[0x05]);
+
+        assert_eq!(decoder.decode_varint().unwrap(), 5);
+        assert_eq!(decoder.peek_byte(), None);
+        assert!(decoder.remaining().is_empty());
+    }
+
+    #[test]
+    fn test_decode_n_varints_boundaries() {
+        let mut decoder = VarIntDecoder::new(vec![0x01, 0x02, 0x03, 0x04, 0x05]);
+        assert_eq!(decoder.decode_n_varints(0).unwrap(), Vec::<u128>::new());
+        assert_eq!(decoder.decode_n_varints(4).unwrap(), vec![1, 2, 3, 4]);
+
+        // 只剩 1 个，要 4 个：报错且不消费
+        assert!(decoder.decode_n_varints(4).is_err());
+        assert_eq!(decoder.remaining(), &[0x05]);
+
+        // 恰好取完
+        assert_eq!(decoder.decode_n_varints(1).unwrap(), vec![5]);
+        assert!(decoder.is_eof());
+        assert_eq!(decoder.decode_n_varints(0).unwrap(), Vec::<u128>::new());
+        assert!(decoder.decode_n_varints(1).is_err());
+
+        // 最后一个 VarInt 被截断
+        let mut decoder = VarIntDecoder::new(vec![0x01, 0x80, 0x80]);
+        assert!(decoder.decode_n_varints(2).is_err());
+        assert_eq!(decoder.peek_byte(), Some(0x01));
+    }
+
+    #[test]
+    fn test_flags_from_bits() {
+        assert_eq!(Flags::from_bits(0), Flags::default());
+
+        let etching = Flags::from_bits(1);
+        assert!(etching.etching && !etching.terms && !etching.turbo);
+        assert_eq!(etching.unrecognized, 0);
+
+        let etching_terms = Flags::from_bits(3);
+        assert!(etching_terms.etching && etching_terms.terms && !etching_terms.turbo);
+
+        // turbo 在规范中是 bit 2（值 4）
+        let turbo = Flags::from_bits(4);
+        assert!(turbo.turbo && !turbo.etching);
+        assert_eq!(turbo.unrecognized, 0);
+
+        // 0x80 不是已知的 flag，归入 unrecognized
+        let unknown = Flags::from_bits(0x80 | 1);
+        assert!(unknown.etching && !unknown.turbo);
+        assert_eq!(unknown.unrecognized, 0x80);
+    }
+
+    #[test]
+    fn test_unrecognized_flags_make_cenotaph() {
+        let mut fields = HashMap::new();
+        fields.insert(FLAGS, 0x80);
+        let runestone = Runestone {
+            fields,
+            mint: None,
+            edicts: Vec::new(),
+            flaw: None,
+        };
+        assert!(runestone.is_cenotaph());
+        assert!(runestone.decoded().unwrap().cenotaph);
+    }
+
+    #[test]
+    fn test_decoded_runestone_diff_names_field() {
+        use crate::runes_builder::RunesBuilder;
+
+        let decode = |name: &str| {
+            let script = RunesBuilder::new()
+                .with_flags(1)
+                .with_rune(name)
+                .with_divisibility(2)
+                .with_premine(1_000)
+                .build()
+                .unwrap();
+            RunesParser::parse_script_hex(&script.to_hex_string())
+                .unwrap()
+                .unwrap()
+                .decoded()
+                .unwrap()
+        };
+
+        let expected = decode("TEST");
+        assert_eq!(expected, decode("TEST"));
+        assert!(expected.diff(&decode("TEST")).is_empty());
+
+        let etching = expected.etching.as_ref().unwrap();
+        assert_eq!(etching.rune.as_deref(), Some("TEST"));
+        assert_eq!(etching.divisibility, Some(2));
+        assert_eq!(etching.premine, Some(1_000));
+
+        let diffs = expected.diff(&decode("TESTB"));
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].field, "etching.rune");
+        assert_eq!(
+            diffs[0].to_string(),
+            "etching.rune differs: expected Some(\"TEST\") got Some(\"TESTB\")"
+        );
+    }
+
+    #[test]
+    fn test_rune_integer_to_name() {
+        use crate::runes_builder::rune_name_to_integer;
+
+        for name in ["A", "Z", "TEST", "UNCOMMON•GOODS"] {
+            assert_eq!(rune_integer_to_name(rune_name_to_integer(name)), name);
+        }
+        assert_eq!(rune_integer_to_name(0), "");
+    }
+
+    #[test]
+    fn test_decoded_pointer_overflow_is_error() {
+        let runestone = Runestone {
+            fields: HashMap::from([(POINTER, u32::MAX as u128 + 1)]),
+            mint: None,
+            edicts: vec![],
+            flaw: None,
+        };
+        let err = runestone.decoded().unwrap_err();
+        assert!(err.contains("overflows u32"));
+
+        let runestone = Runestone {
+            fields: HashMap::from([(POINTER, u32::MAX as u128)]),
+            ..runestone
+        };
+        assert_eq!(runestone.decoded().unwrap().pointer, Some(u32::MAX));
+    }
+
+    #[test]
+    fn test_decoded_runestone_json_round_trip() {
+        let runestone = Runestone {
+            fields: HashMap::from([(FLAGS, 1), (RUNE, 0x14131420), (PREMINE, u128::MAX)]),
+            mint: None,
+            edicts: vec![Edict {
+                id: RuneId {
+                    block: 840_000,
+                    tx: 3,
+                },
+                amount: u64::MAX as u128 + 1,
+                output: 1,
+            }],
+            flaw: None,
+        };
+        let decoded = runestone.decoded().unwrap();
+
+        let json = serde_json::to_value(&decoded).unwrap();
+        // u128 以字符串输出，超出 f64 精度的值也不会丢失
+        assert_eq!(json["edicts"][0]["amount"], "18446744073709551616");
+        assert_eq!(json["etching"]["premine"], u128::MAX.to_string());
+        assert_eq!(json["etching"]["cap"], serde_json::Value::Null);
+
+        let parsed: DecodedRunestone = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, decoded);
+
+        // Runestone 的 fields 以字符串 key / value 的 map 输出
+        let json = serde_json::to_string(&runestone).unwrap();
+        let parsed: Runestone = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.fields, runestone.fields);
+        assert_eq!(parsed.edicts, runestone.edicts);
+    }
+
+    /// Edict 测试向量：(runestone 脚本 hex, 脚本中的整数序列, 期望的 edicts (block, tx, amount, output))
+    ///
+    /// 这些向量不是主网交易：hex 和整数序列按规范（LEB128、edict delta 规则）手工推导，
+    /// 不经过 encode_varint，用来检查解码和 delta 还原。主网转账的 OP_RETURN 脚本
+    /// （单个 edict、同区块、跨区块，注明 txid）仍需补上
+    type EdictFixture = (
+        &'static str,
+        &'static [u128],
+        &'static [(u64, u32, u128, u32)],
+    );
+
+    const SYNTHETIC_EDICT_FIXTURES: &[EdictFixture] = &[
+        // 单个 edict：BODY，840000:3 的 1000 到 output 1
+        (
+            "6a5d0800c0a23303e80701",
+            &[0, 840_000, 3, 1_000, 1],
+            &[(840_000, 3, 1_000, 1)],
+        ),
+        // 同一区块：block delta 为 0 时 tx 也是 delta，同一 rune 再次出现时 tx delta 为 0
+        (
+            "6a5d1500c0a23303f403000019c41301000080c8afa02502",
+            &[
+                0,
+                840_000,
+                3,
+                500,
+                0,
+                0,
+                25,
+                2_500,
+                1,
+                0,
+                0,
+                10_000_000_000,
+                2,
+            ],
+            &[
+                (840_000, 3, 500, 0),
+                (840_000, 28, 2_500, 1),
+                (840_000, 28, 10_000_000_000, 2),
+            ],
+        ),
+        // 跨区块：block delta 非 0 时 tx 是绝对值
+        (
+            "6a5d1200c0a2330364000a01c80101fe2607ac0202",
+            &[0, 840_000, 3, 100, 0, 10, 1, 200, 1, 4_990, 7, 300, 2],
+            &[
+                (840_000, 3, 100, 0),
+                (840_010, 1, 200, 1),
+                (845_000, 7, 300, 2),
+            ],
+        ),
+    ];
+
+    #[test]
+    fn test_edict_fixtures() {
+        for (script_hex, integers, expected) in SYNTHETIC_EDICT_FIXTURES {
+            // OP_RETURN OP_PUSHNUM_13 <len> 之后的整数序列
+            let data = hex::decode(&script_hex[6..]).unwrap();
+            let mut decoder = VarIntDecoder::new(data);
+            let mut decoded = Vec::new();
+            while !decoder.is_eof() {
+                decoded.push(decoder.decode_varint().unwrap());
+            }
+            assert_eq!(decoded, *integers, "{}", script_hex);
+
+            let runestone = RunesParser::parse_script_hex(script_hex).unwrap().unwrap();
+            let expected: Vec<Edict> = expected
+                .iter()
+                .map(|&(block, tx, amount, output)| Edict {
+                    id: RuneId { block, tx },
+                    amount,
+                    output,
+                })
+                .collect();
+
+            assert_eq!(runestone.edicts, expected, "{}", script_hex);
+            assert!(!runestone.is_cenotaph());
+
+            // 重新编码得到同样的字节
+            let rebuilt = crate::runes_builder::RunesBuilder::from_runestone(&runestone)
+                .build()
+                .unwrap();
+            assert_eq!(rebuilt.to_hex_string(), *script_hex);
+        }
+    }
+
+    #[test]
+    fn test_truncated_edict_is_cenotaph() {
+        // 最后一个 edict 缺少 output
+        let runestone = RunesParser::parse_script_hex("6a5d0700c0a23303e807")
+            .unwrap()
+            .unwrap();
+        assert!(runestone.edicts.is_empty());
+        assert!(runestone.flaw.is_some());
+        assert!(runestone.is_cenotaph());
+    }
+
+    #[test]
+    fn test_edict_block_zero_nonzero_tx_is_cenotaph() {
+        // BODY，edict (0, 5, 100, 1)：block 0 只能配 tx 0
+        let runestone = RunesParser::parse_runestone_data(vec![0x00, 0x00, 0x05, 0x64, 0x01])
+            .unwrap()
+            .unwrap();
+        assert!(runestone.edicts.is_empty());
+        assert!(runestone.is_cenotaph());
+
+        // 0:0 是本交易 etching 的 rune
+        let runestone = RunesParser::parse_runestone_data(vec![0x00, 0x00, 0x00, 0x64, 0x01])
+            .unwrap()
+            .unwrap();
+        assert!(runestone.edicts[0].id.is_etched());
+        assert!(runestone.flaw.is_none());
+    }
+
+    #[test]
+    fn test_oversized_runestone_is_cenotaph() {
+        // 远超上限的数据：不解码，直接作为 cenotaph
+        let runestone = RunesParser::parse_runestone_data(vec![0x7f; 1_000_000])
+            .unwrap()
+            .unwrap();
+        assert!(runestone.fields.is_empty());
+        assert!(runestone.edicts.is_empty());
+        assert!(runestone.is_cenotaph());
+
+        // 长度在上限内，但 tag-value 对过多（SYMBOL=1 重复出现）
+        let runestone = RunesParser::parse_runestone_data([0x06, 0x01].repeat(500))
+            .unwrap()
+            .unwrap();
+        assert!(runestone.flaw.unwrap().contains("128"));
+    }
+
+    #[test]
+    fn test_mint_is_open_absolute_heights() {
+        let terms = Terms {
+            height: (Some(840_100), Some(840_200)),
+            ..Default::default()
+        };
+
+        assert!(!mint_is_open(&terms, 840_000, 840_099));
+        assert!(mint_is_open(&terms, 840_000, 840_100));
+        assert!(mint_is_open(&terms, 840_000, 840_199));
+        // end 不包含
+        assert!(!mint_is_open(&terms, 840_000, 840_200));
+
+        // 没有任何窗口时一直开放
+        assert!(mint_is_open(&Terms::default(), 840_000, 0));
+    }
+
+    #[test]
+    fn test_mint_is_open_offsets() {
+        let terms = Terms {
+            offset: (Some(10), Some(100)),
+            ..Default::default()
+        };
+
+        assert!(!mint_is_open(&terms, 840_000, 840_009));
+        assert!(mint_is_open(&terms, 840_000, 840_010));
+        assert!(mint_is_open(&terms, 840_000, 840_099));
+        assert!(!mint_is_open(&terms, 840_000, 840_100));
+
+        // 绝对窗口和相对窗口同时存在时取交集
+        let both = Terms {
+            height: (None, Some(840_050)),
+            ..terms
+        };
+        assert!(mint_is_open(&both, 840_000, 840_049));
+        assert!(!mint_is_open(&both, 840_000, 840_050));
+
+        // offset 溢出：start 永远到不了
+        let overflow = Terms {
+            offset: (Some(u64::MAX), None),
+            ..Default::default()
+        };
+        assert!(!mint_is_open(&overflow, 840_000, u64::MAX));
+    }
+
+    #[test]
+    fn test_non_push_opcode_is_cenotaph() {
+        // push [0x02, 0x01] 之后是 OP_NOP：后面的数据不能被静默丢弃
+        let runestone = RunesParser::parse_script_hex("6a5d0202016101")
+            .unwrap()
+            .unwrap();
+        assert!(runestone.fields.is_empty());
+        assert!(runestone.is_cenotaph());
+
+        // OP_0 是合法的空 push
+        let runestone = RunesParser::parse_script_hex("6a5d00020201")
+            .unwrap()
+            .unwrap();
+        assert!(!runestone.is_cenotaph());
+        assert!(runestone.flags().etching);
+    }
 }
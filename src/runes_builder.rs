@@ -1,55 +1,28 @@
-use bitcoin::{
-    opcodes::all::{OP_PUSHNUM_13, OP_RETURN},
-    script::{Builder, ScriptBuf},
-};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 
-enum Tag {
-    Body = 0,
-    Flags = 2,
-    Rune = 4,
-    Premine = 6,
-    Cap = 8,
-    Amount = 10,
-    HeightStart = 12,
-    HeightEnd = 14,
-    OffsetStart = 16,
-    OffsetEnd = 18,
-    Mint = 20,
-    Pointer = 22,
-    Cenotaph = 126,
-
-    Divisibility = 1,
-    Spacers = 3,
-    Symbol = 5,
-    Nop = 127,
-}
+use bitcoin::{TxOut, opcodes::all::OP_PUSHNUM_13, script::ScriptBuf};
+
+use crate::rune_decode::{
+    AMOUNT, BODY, CAP, CENOTAPH, DIVISIBILITY, DecodedRunestone, Edict, FLAGS, MINT, POINTER,
+    PREMINE, RUNE, RuneBalance, RuneId, RunesParser, Runestone, SPACERS, SYMBOL, TERMS,
+};
+use crate::transactions::MAX_OP_RETURN_RELAY;
+use crate::utils::DataCarrierBuilder;
 
 /// =====================================================
 /// VarInt 编码器
 /// =====================================================
+///
+/// Runes 规范的 LEB128：每字节 7 位数据，低位在前，最高位为 1 表示后面还有字节
 pub fn encode_varint(mut value: u128) -> Vec<u8> {
     let mut result = Vec::new();
 
-    match value {
-        0..=252 => {
-            result.push(value as u8);
-        }
-        253..=65535 => {
-            result.push(0xFD);
-            let bytes = (value as u16).to_le_bytes();
-            result.extend_from_slice(&bytes);
-        }
-        65536..=4294967295 => {
-            result.push(0xFE);
-            let bytes = (value as u32).to_le_bytes();
-            result.extend_from_slice(&bytes);
-        }
-        _ => {
-            result.push(0xFF);
-            let bytes = value.to_le_bytes();
-            result.extend_from_slice(&bytes);
-        }
+    while value >> 7 > 0 {
+        result.push(value as u8 & 0x7F | 0x80);
+        value >>= 7;
     }
+    result.push(value as u8);
 
     result
 }
@@ -83,21 +56,189 @@ pub fn rune_name_to_integer(name: &str) -> u128 {
     result
 }
 
+/// =====================================================
+/// 符文名称解锁时间表（主网）
+/// =====================================================
+///
+/// Runes 从第 4 次减半（区块 840000）激活，此前最短可用名称为 13 个字母；
+/// 之后每 17500 个区块（一个减半周期的 1/12）缩短一个字母，到 1050000 时所有名称都可用。
+/// 每个区间内最小可用名称按区块线性下降，比较的是规范中的 base-26 名称值
+/// （A=0, Z=25, AA=26 ...），与 rune_name_to_integer 的编码无关
+const SUBSIDY_HALVING_INTERVAL: u64 = 210_000;
+const FIRST_RUNE_HEIGHT: u64 = 4 * SUBSIDY_HALVING_INTERVAL;
+const UNLOCK_INTERVAL: u64 = SUBSIDY_HALVING_INTERVAL / 12;
+
+/// 不指定名称的 etching 会被分配 >= 该值的名称（27 个 A 起），不能手动 etch
+const RESERVED_RUNE_VALUE: u128 = 6_402_364_363_415_443_603_228_541_259_936_211_926;
+
+/// 长度为 len 的最小名称（"A" * len）的名称值
+fn first_rune_of_length(len: usize) -> u128 {
+    (1..len as u32).map(|i| 26u128.pow(i)).sum()
+}
+
+/// 规范中的名称值；忽略分隔符，非字母或超出 u128 时返回 None
+fn rune_name_value(name: &str) -> Option<u128> {
+    let mut value: u128 = 0;
+    for (i, ch) in name.chars().filter(|c| !matches!(c, '•' | '.')).enumerate() {
+        if !ch.is_ascii_alphabetic() {
+            return None;
+        }
+        if i > 0 {
+            value = value.checked_add(1)?;
+        }
+        value = value
+            .checked_mul(26)?
+            .checked_add((ch.to_ascii_uppercase() as u8 - b'A') as u128)?;
+    }
+    Some(value)
+}
+
+/// 在 height 高度可以 etch 的最小名称值
+fn minimum_rune_value_at_height(height: u64) -> u128 {
+    // etching 交易最早被打包进下一个区块
+    let offset = height.saturating_add(1);
+    let end = FIRST_RUNE_HEIGHT + SUBSIDY_HALVING_INTERVAL;
+
+    if offset < FIRST_RUNE_HEIGHT {
+        return first_rune_of_length(13);
+    }
+    if offset >= end {
+        return 0;
+    }
+
+    let progress = offset - FIRST_RUNE_HEIGHT;
+    let length = 13 - (progress / UNLOCK_INTERVAL) as usize;
+    let start = first_rune_of_length(length);
+    let end = first_rune_of_length(length - 1);
+    let remainder = (progress % UNLOCK_INTERVAL) as u128;
+
+    start - (start - end) * remainder / UNLOCK_INTERVAL as u128
+}
+
+/// height 高度可以 etch 的名称的最短长度
+pub fn rune_name_minimum_length(height: u64) -> usize {
+    let minimum = minimum_rune_value_at_height(height);
+    (1..=13)
+        .rev()
+        .find(|len| first_rune_of_length(*len) <= minimum)
+        .unwrap_or(1)
+}
+
+/// 名称在 height 高度是否还不能 etch（尚未解锁，或属于保留名称）
+pub fn rune_name_is_reserved(name: &str, current_height: u64) -> bool {
+    match rune_name_value(name) {
+        Some(value) => {
+            value < minimum_rune_value_at_height(current_height) || value >= RESERVED_RUNE_VALUE
+        }
+        None => true,
+    }
+}
+
+/// =====================================================
+/// Runestone 字段校验
+/// =====================================================
+/// divisibility 上限（规范：u128 最多 38 位十进制）
+pub const MAX_DIVISIBILITY: u128 = 38;
+
+// FLAGS 中的 etching / terms / turbo 位（与 rune_decode::Flags 一致），with_flags 时组合使用
+pub const FLAG_ETCHING: u128 = 1 << 0;
+pub const FLAG_TERMS: u128 = 1 << 1;
+pub const FLAG_TURBO: u128 = 1 << 2;
+
+/// 属于 terms 的字段：AMOUNT (Tag 1)、TERMS (Tag 9)、CAP (Tag 11)
+const TERMS_TAGS: [u128; 3] = [AMOUNT, TERMS, CAP];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    /// DIVISIBILITY 超过 38
+    DivisibilityTooLarge(u128),
+    /// SYMBOL 不是合法的 Unicode 字符
+    InvalidSymbol(u128),
+    /// 设置了 CAP 时 PREMINE 不能超过 CAP
+    PremineExceedsCap { premine: u128, cap: u128 },
+    /// etching 带了 terms 字段，但 FLAGS 没有设置 terms 位（这些字段会被忽略）
+    TermsWithoutFlag,
+    /// edict 引用了本交易 etching 的 rune（0:0），但 FLAGS 没有设置 etching 位
+    EtchedEdictWithoutEtching,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::DivisibilityTooLarge(divisibility) => write!(
+                f,
+                "Divisibility {} exceeds maximum {}",
+                divisibility, MAX_DIVISIBILITY
+            ),
+            BuildError::InvalidSymbol(symbol) => {
+                write!(f, "Symbol 0x{:x} is not a valid char", symbol)
+            }
+            BuildError::PremineExceedsCap { premine, cap } => {
+                write!(f, "Premine {} exceeds cap {}", premine, cap)
+            }
+            BuildError::TermsWithoutFlag => {
+                write!(f, "Etching has terms fields but the terms flag is not set")
+            }
+            BuildError::EtchedEdictWithoutEtching => write!(
+                f,
+                "Edict references the etched rune (0:0) but the etching flag is not set"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
 /// =====================================================
 /// Runes 构建器
 /// =====================================================
 pub struct RunesBuilder {
     fields: Vec<(u128, u128)>, // (tag, value) pairs
+    mint: Option<RuneId>,
+    edicts: Vec<Edict>,
+    /// build() 时在 FLAGS 中置 turbo 位
+    turbo: bool,
 }
 
 impl RunesBuilder {
     pub fn new() -> Self {
-        RunesBuilder { fields: Vec::new() }
+        RunesBuilder {
+            fields: Vec::new(),
+            mint: None,
+            edicts: Vec::new(),
+            turbo: false,
+        }
+    }
+
+    /// 从解码结果重新构造 builder（字段按 tag 排序，保证编码结果稳定）
+    pub fn from_runestone(runestone: &Runestone) -> Self {
+        let mut fields: Vec<(u128, u128)> =
+            runestone.fields.iter().map(|(t, v)| (*t, *v)).collect();
+        fields.sort();
+        RunesBuilder {
+            fields,
+            mint: runestone.mint,
+            edicts: runestone.edicts.clone(),
+            turbo: false,
+        }
+    }
+
+    /// ⚠️ 构造一个故意无效的 runestone（cenotaph），用于烧掉交易输入中的所有 rune
+    ///
+    /// 只包含 CENOTAPH 标签（Tag 126），build() 仍生成标准的 OP_RETURN 脚本，可正常中继。
+    /// 这不是普通转账，不要在需要保留 rune 的交易里使用
+    pub fn cenotaph() -> Self {
+        RunesBuilder {
+            fields: vec![(CENOTAPH, 0)],
+            mint: None,
+            edicts: Vec::new(),
+            turbo: false,
+        }
     }
 
     /// 添加 FLAGS (Tag 2)
     pub fn with_flags(mut self, flags: u128) -> Self {
-        self.fields.push((2, flags));
+        self.fields.push((FLAGS, flags));
         self
     }
 
@@ -107,119 +248,238 @@ impl RunesBuilder {
         println!("🔄 符文名称转换:");
         println!("  输入: {}", rune_name);
         println!("  整数值: {} (0x{:x})", rune_value, rune_value);
-        self.fields.push((4, rune_value));
+        self.fields.push((RUNE, rune_value));
         self
     }
 
     /// 添加 SPACERS (Tag 5)
     pub fn with_spacers(mut self, spacers: u128) -> Self {
-        self.fields.push((5, spacers));
+        self.fields.push((SPACERS, spacers));
         self
     }
 
     /// 添加 SYMBOL (Tag 6) - 符号字符
     pub fn with_symbol(mut self, symbol: char) -> Self {
         let symbol_value = symbol as u128;
-        self.fields.push((6, symbol_value));
+        self.fields.push((SYMBOL, symbol_value));
         self
     }
 
     /// 添加 PREMINE (Tag 7) - 预挖数量
     pub fn with_premine(mut self, premine: u128) -> Self {
-        self.fields.push((7, premine));
+        self.fields.push((PREMINE, premine));
         self
     }
 
     /// 添加 POINTER (Tag 8)
     pub fn with_pointer(mut self, pointer: u32) -> Self {
-        self.fields.push((8, pointer as u128));
+        self.fields.push((POINTER, pointer as u128));
         self
     }
 
     /// 添加 TERMS (Tag 9)
     pub fn with_terms(mut self, terms: u128) -> Self {
-        self.fields.push((9, terms));
+        self.fields.push((TERMS, terms));
         self
     }
 
-    /// 添加 TURBO (Tag 10)
+    /// 设置 turbo：规范中 turbo 是 FLAGS 的 bit 2，不是单独的 tag，
+    /// build() 时并入 FLAGS（没有 FLAGS 字段时新增一个）
     pub fn with_turbo(mut self) -> Self {
-        self.fields.push((10, 0));
+        self.turbo = true;
         self
     }
 
     /// 添加 CAP (Tag 11) - 供应上限
     pub fn with_cap(mut self, cap: u128) -> Self {
-        self.fields.push((11, cap));
+        self.fields.push((CAP, cap));
         self
     }
 
     /// 添加 DIVISIBILITY (Tag 12) - 小数位
     pub fn with_divisibility(mut self, divisibility: u8) -> Self {
-        self.fields.push((12, divisibility as u128));
+        self.fields.push((DIVISIBILITY, divisibility as u128));
         self
     }
 
     /// 添加 AMOUNT (Tag 1)
     pub fn with_amount(mut self, amount: u128) -> Self {
-        self.fields.push((1, amount));
+        self.fields.push((AMOUNT, amount));
+        self
+    }
+
+    /// 添加 MINT (Tag 3)：从已开放 mint 的 etching 中领取
+    ///
+    /// 编码为两个 Tag 3 字段，依次为 block 和 tx
+    pub fn mint(mut self, rune_id: RuneId) -> Self {
+        self.mint = Some(rune_id);
         self
     }
 
     /// 添加 MINT (Tag 3)
-    pub fn with_mint(mut self, block: u64, tx: u32) -> Self {
-        // MINT 编码为 [block, tx]（两个 VarInt）
-        let mint_value = (block as u128) << 32 | (tx as u128);
-        self.fields.push((3, mint_value));
+    pub fn with_mint(self, block: u64, tx: u32) -> Self {
+        self.mint(RuneId { block, tx })
+    }
+
+    /// 添加 EDICT（写在 BODY 之后）：把 amount 个 id 转到第 output 个输出
+    pub fn with_edict(mut self, id: RuneId, amount: u128, output: u32) -> Self {
+        self.edicts.push(Edict { id, amount, output });
         self
     }
 
-    /// 构建脚本
-    pub fn build(self) -> Result<ScriptBuf, Box<dyn std::error::Error>> {
-        println!("\n🔨 构建 Runes 脚本");
-        println!("─────────────────────────────────");
+    fn field(&self, tag: u128) -> Option<u128> {
+        self.fields.iter().find(|(t, _)| *t == tag).map(|(_, v)| *v)
+    }
 
-        let mut data = Vec::new();
+    /// 检查字段之间是否一致，一次返回所有问题；build() 会先调用它
+    ///
+    /// 这些 etching 在链上会被 ord 判为无效，提前在构建时发现
+    pub fn validate(&self) -> Result<(), Vec<BuildError>> {
+        let mut errors = Vec::new();
+
+        if let Some(divisibility) = self.field(DIVISIBILITY)
+            && divisibility > MAX_DIVISIBILITY
+        {
+            errors.push(BuildError::DivisibilityTooLarge(divisibility));
+        }
+        if let Some(symbol) = self.field(SYMBOL)
+            && u32::try_from(symbol)
+                .ok()
+                .and_then(char::from_u32)
+                .is_none()
+        {
+            errors.push(BuildError::InvalidSymbol(symbol));
+        }
+        if let (Some(premine), Some(cap)) = (self.field(PREMINE), self.field(CAP))
+            && premine > cap
+        {
+            errors.push(BuildError::PremineExceedsCap { premine, cap });
+        }
+        let flags = self.field(FLAGS).unwrap_or(0);
+        if flags & FLAG_ETCHING != 0
+            && flags & FLAG_TERMS == 0
+            && TERMS_TAGS.iter().any(|tag| self.field(*tag).is_some())
+        {
+            errors.push(BuildError::TermsWithoutFlag);
+        }
+        if flags & FLAG_ETCHING == 0 && self.edicts.iter().any(|edict| edict.id.is_etched()) {
+            errors.push(BuildError::EtchedEdictWithoutEtching);
+        }
 
-        // 排序字段（可选，但有助于一致性）
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// 编码用的字段：turbo 并入 FLAGS，MINT 拆成 block / tx 两个 Tag 3，按 tag 稳定排序
+    fn sorted_fields(&self) -> Vec<(u128, u128)> {
         let mut fields = self.fields.clone();
+        if self.turbo {
+            match fields.iter_mut().find(|(tag, _)| *tag == FLAGS) {
+                Some((_, flags)) => *flags |= FLAG_TURBO,
+                None => fields.push((FLAGS, FLAG_TURBO)),
+            }
+        }
+        if let Some(mint) = self.mint {
+            fields.push((MINT, mint.block as u128));
+            fields.push((MINT, mint.tx as u128));
+        }
+        // 稳定排序：两个 MINT 字段保持 block 在前
         fields.sort_by_key(|f| f.0);
+        fields
+    }
 
-        println!("字段数: {}\n", fields.len());
+    /// runestone 数据（OP_RETURN OP_13 之后 push 的内容）
+    fn payload(&self) -> Vec<u8> {
+        let mut data = Vec::new();
 
-        // 编码每个 Tag-Value 对
-        for (tag, value) in fields {
-            println!("编码 Tag {}: {}", tag, value);
+        // 编码每个 Tag-Value 对，然后是 BODY 终止符 (Tag 0)
+        for (tag, value) in self.sorted_fields() {
+            data.extend_from_slice(&encode_varint(tag));
+            data.extend_from_slice(&encode_varint(value));
+        }
+        data.extend_from_slice(&encode_varint(BODY));
 
-            // 编码 tag
-            let tag_bytes = encode_varint(tag);
-            data.extend_from_slice(&tag_bytes);
-            println!("  Tag 编码: {}", hex::encode(&tag_bytes));
+        // 编码 edicts：按 RuneId 排序后做 delta 编码
+        let mut edicts = self.edicts.clone();
+        edicts.sort_by_key(|e| e.id);
+        let mut previous = RuneId { block: 0, tx: 0 };
+        for edict in edicts {
+            let block_delta = edict.id.block - previous.block;
+            let tx_delta = if block_delta == 0 {
+                edict.id.tx - previous.tx
+            } else {
+                edict.id.tx
+            };
+            for value in [
+                block_delta as u128,
+                tx_delta as u128,
+                edict.amount,
+                edict.output as u128,
+            ] {
+                data.extend_from_slice(&encode_varint(value));
+            }
+            previous = edict.id;
+        }
+
+        data
+    }
 
-            // 编码 value
-            let value_bytes = encode_varint(value);
-            data.extend_from_slice(&value_bytes);
-            println!("  Value 编码: {}", hex::encode(&value_bytes));
+    /// runestone 数据的字节数，不构造脚本，可在 commit 之前检查。
+    /// 完整脚本还要加上 OP_RETURN、OP_13 和 push 操作码（3 ~ 4 字节）
+    pub fn encoded_len(&self) -> usize {
+        self.payload().len()
+    }
+
+    /// 构建脚本
+    pub fn build(self) -> Result<ScriptBuf, Box<dyn std::error::Error>> {
+        println!("\n🔨 构建 Runes 脚本");
+        println!("─────────────────────────────────");
+
+        if let Err(errors) = self.validate() {
+            let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+            return Err(format!("invalid runestone: {}", messages.join("; ")).into());
+        }
+        // block 为 0 的 rune id 不存在（0:0 也不是合法的 mint 目标）
+        if let Some(mint) = self.mint
+            && mint.block == 0
+        {
+            return Err(format!("invalid mint rune id {}", mint).into());
         }
 
-        // 添加 BODY 终止符 (Tag 0)
-        println!("编码 BODY 终止符");
-        let body_bytes = encode_varint(0);
-        data.extend_from_slice(&body_bytes);
-        println!("  编码: {}\n", hex::encode(&body_bytes));
+        let fields = self.sorted_fields();
+        println!("字段数: {}\n", fields.len());
+        for (tag, value) in &fields {
+            println!("编码 Tag {}: {}", tag, value);
+        }
+        for edict in &self.edicts {
+            println!(
+                "编码 EDICT {}: amount {}, output {}",
+                edict.id, edict.amount, edict.output
+            );
+        }
 
+        let data = self.payload();
         println!("✓ Runestone 数据已生成: {} 字节", data.len());
         println!("Hex: {}\n", hex::encode(&data));
 
-        // 构造脚本
-        let mut pb = bitcoin::script::PushBytesBuf::new();
-        pb.extend_from_slice(&data)?;
+        // 构造脚本：OP_RETURN OP_13 <data>，超过 520 字节时拆成多个 push
+        let script = DataCarrierBuilder::new()
+            .with_marker(OP_PUSHNUM_13)
+            .with_payload(&data)
+            .build()?;
 
-        let script = Builder::new()
-            .push_opcode(OP_RETURN)
-            .push_opcode(OP_PUSHNUM_13)
-            .push_slice(pb)
-            .into_script();
+        // 超过标准 OP_RETURN 大小的 runestone 共识上有效，但大多数节点不转发
+        if script.len() > MAX_OP_RETURN_RELAY {
+            println!(
+                "⚠️ runestone 为 {} 字节（脚本 {} 字节），超过 {} 字节的 OP_RETURN 标准限制，大多数节点不会转发",
+                data.len(),
+                script.len(),
+                MAX_OP_RETURN_RELAY
+            );
+        }
 
         println!("✓ 完整脚本 Hex:");
         println!("{}\n", script.to_hex_string());
@@ -276,9 +536,334 @@ pub fn example_with_symbol() -> Result<ScriptBuf, Box<dyn std::error::Error>> {
     Ok(script)
 }
 
+/// =====================================================
+/// 输出重新排序后更新 runestone
+/// =====================================================
+///
+/// new_index[旧序号] = 新序号。edict 的 output 和 POINTER 按新顺序改写，
+/// output 等于输出数量（平分）的 edict 保持不变。不是 runestone 的脚本原样返回
+pub fn remap_runestone_outputs(
+    runestone: &ScriptBuf,
+    new_index: &[usize],
+) -> Result<ScriptBuf, Box<dyn std::error::Error>> {
+    let Some(decoded) = RunesParser::parse_script_hex(&runestone.to_hex_string())? else {
+        return Ok(runestone.clone());
+    };
+
+    let remap = |output: u128| -> u128 {
+        match usize::try_from(output) {
+            Ok(i) if i < new_index.len() => new_index[i] as u128,
+            _ => output,
+        }
+    };
+
+    let mut builder = RunesBuilder::from_runestone(&decoded);
+    for (tag, value) in builder.fields.iter_mut() {
+        if *tag == POINTER {
+            *value = remap(*value);
+        }
+    }
+    for edict in builder.edicts.iter_mut() {
+        edict.output = remap(edict.output as u128) as u32;
+    }
+
+    builder.build()
+}
+
+/// =====================================================
+/// Edict 校验
+/// =====================================================
+///
+/// edict output 和 POINTER 都是交易输出的序号（包含 runestone 所在的 OP_RETURN），
+/// 与 validate_premine_allocation 等一致；output == 输出个数表示平分到所有非 OP_RETURN 输出
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EdictError {
+    /// 某种 rune 的 edict 总额超过输入余额（多出的部分会被烧掉）
+    Overspend {
+        id: RuneId,
+        requested: u128,
+        available: u128,
+    },
+    /// edict 的 output 超出交易输出范围
+    OutputOutOfRange { output: u32, output_count: usize },
+}
+
+impl fmt::Display for EdictError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EdictError::Overspend {
+                id,
+                requested,
+                available,
+            } => write!(
+                f,
+                "Rune {} overspent: edicts request {}, inputs hold {}",
+                id, requested, available
+            ),
+            EdictError::OutputOutOfRange {
+                output,
+                output_count,
+            } => write!(
+                f,
+                "Edict output {} out of range ({} outputs)",
+                output, output_count
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EdictError {}
+
+/// 构建转账前检查 edicts 不会超额花费输入中的 rune，outputs 为交易的全部输出
+///
+/// - 按 rune 汇总 edict 金额，不能超过输入余额之和
+/// - amount 为 0（规范中的「全部剩余」）或 u128::MAX 不计入汇总
+/// - output 可以等于 outputs.len()（平分到所有非 OP_RETURN 输出），更大则越界；
+///   平分时每个非 OP_RETURN 输出都收到 amount，按 amount × 非 OP_RETURN 输出个数计入汇总
+pub fn validate_edicts(
+    edicts: &[Edict],
+    inputs: &[RuneBalance],
+    outputs: &[TxOut],
+) -> Result<(), EdictError> {
+    let output_count = outputs.len();
+    let split_count = outputs
+        .iter()
+        .filter(|o| !o.script_pubkey.is_op_return())
+        .count();
+
+    let mut available: HashMap<RuneId, u128> = HashMap::new();
+    for balance in inputs {
+        let entry = available.entry(balance.id).or_insert(0);
+        *entry = entry.saturating_add(balance.amount);
+    }
+
+    let mut requested: HashMap<RuneId, u128> = HashMap::new();
+    for edict in edicts {
+        if edict.output as usize > output_count {
+            return Err(EdictError::OutputOutOfRange {
+                output: edict.output,
+                output_count,
+            });
+        }
+
+        // 0:0 由本交易的 premine 提供，不从输入中扣
+        if edict.amount == 0 || edict.amount == u128::MAX || edict.id.is_etched() {
+            continue;
+        }
+
+        let amount = if edict.output as usize == output_count {
+            edict.amount.checked_mul(split_count as u128)
+        } else {
+            Some(edict.amount)
+        };
+        let entry = requested.entry(edict.id).or_insert(0);
+        *entry = amount.map_or(u128::MAX, |amount| entry.saturating_add(amount));
+    }
+
+    for (id, requested) in requested {
+        let available = available.get(&id).copied().unwrap_or(0);
+        if requested > available {
+            return Err(EdictError::Overspend {
+                id,
+                requested,
+                available,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// =====================================================
+/// 模拟 runestone 的转账结果
+/// =====================================================
+///
+/// 按 indexer 的规则把输入中的 rune 分配到交易的各个输出（outputs 为交易的全部输出，
+/// 序号与 edict output / POINTER 相同），返回每个输出收到的余额：
+///
+/// - edicts 按顺序执行，每条最多转出该 rune 的剩余未分配余额
+///   （amount 为 0 或 u128::MAX 即「全部剩余」）
+/// - output == outputs.len()：分到所有非 OP_RETURN 输出；amount 为 0 时平分，
+///   余数从第一个非 OP_RETURN 输出起每个多分 1
+/// - 剩余未分配的 rune 转到 pointer，未设置时转到第一个非 OP_RETURN 输出
+/// - 分到 OP_RETURN 输出的 rune 被烧掉，对应的结果始终为空
+/// - etching 的 premine 记在 RuneId::ETCHED（0:0）下，edict 用 0:0 分发它
+/// - cenotaph、edict output 或 pointer 越界时，输入中的 rune 全部烧掉（每个输出都为空）
+pub fn apply_runestone(
+    inputs: Vec<RuneBalance>,
+    runestone: &DecodedRunestone,
+    outputs: &[TxOut],
+) -> Vec<Vec<RuneBalance>> {
+    let num_outputs = outputs.len();
+    let mut balances: Vec<BTreeMap<RuneId, u128>> = vec![BTreeMap::new(); num_outputs];
+    let to_vec = |balances: Vec<BTreeMap<RuneId, u128>>| {
+        balances
+            .into_iter()
+            .map(|balances| {
+                balances
+                    .into_iter()
+                    .map(|(id, amount)| RuneBalance { id, amount })
+                    .collect()
+            })
+            .collect()
+    };
+
+    let out_of_range = runestone
+        .edicts
+        .iter()
+        .any(|edict| edict.output as usize > num_outputs)
+        || runestone
+            .pointer
+            .is_some_and(|pointer| pointer as usize >= num_outputs);
+    if runestone.cenotaph || out_of_range {
+        return to_vec(balances);
+    }
+
+    let spendable: Vec<usize> = (0..num_outputs)
+        .filter(|i| !outputs[*i].script_pubkey.is_op_return())
+        .collect();
+
+    let mut unallocated: BTreeMap<RuneId, u128> = BTreeMap::new();
+    if let Some(premine) = runestone
+        .etching
+        .as_ref()
+        .and_then(|etching| etching.premine)
+    {
+        unallocated.insert(RuneId::ETCHED, premine);
+    }
+    for balance in inputs {
+        let entry = unallocated.entry(balance.id).or_insert(0);
+        *entry = entry.saturating_add(balance.amount);
+    }
+
+    // 分到 OP_RETURN 的部分只扣减余额，不记入任何输出（烧掉）
+    let mut allocate = |balance: &mut u128, amount: u128, output: usize, id: RuneId| {
+        let amount = amount.min(*balance);
+        if amount > 0 {
+            *balance -= amount;
+            if !outputs[output].script_pubkey.is_op_return() {
+                let entry = balances[output].entry(id).or_insert(0);
+                *entry = entry.saturating_add(amount);
+            }
+        }
+    };
+
+    for edict in &runestone.edicts {
+        let Some(balance) = unallocated.get_mut(&edict.id) else {
+            continue;
+        };
+        let output = edict.output as usize;
+
+        if output == num_outputs {
+            if spendable.is_empty() {
+                continue;
+            }
+            if edict.amount == 0 {
+                let share = *balance / spendable.len() as u128;
+                let remainder = (*balance % spendable.len() as u128) as usize;
+                for (n, i) in spendable.iter().enumerate() {
+                    let extra = if n < remainder { 1 } else { 0 };
+                    allocate(balance, share + extra, *i, edict.id);
+                }
+            } else {
+                for i in &spendable {
+                    allocate(balance, edict.amount, *i, edict.id);
+                }
+            }
+        } else {
+            let amount = if edict.amount == 0 {
+                *balance
+            } else {
+                edict.amount
+            };
+            allocate(balance, amount, output, edict.id);
+        }
+    }
+
+    // 没有 pointer 也没有非 OP_RETURN 输出时，剩余的 rune 被烧掉
+    let fallback = runestone
+        .pointer
+        .map(|pointer| pointer as usize)
+        .or(spendable.first().copied());
+    if let Some(fallback) = fallback {
+        for (id, mut balance) in unallocated {
+            allocate(&mut balance, u128::MAX, fallback, id);
+        }
+    }
+
+    to_vec(balances)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use bitcoin::Amount;
+    use bitcoin::blockdata::constants::MAX_SCRIPT_ELEMENT_SIZE;
+
+    const TEST_RUNE: RuneId = RuneId {
+        block: 840_000,
+        tx: 1,
+    };
+
+    #[test]
+    fn test_validate_divisibility_too_large() {
+        let builder = RunesBuilder::new()
+            .with_flags(1)
+            .with_rune("TEST")
+            .with_divisibility(39);
+        assert_eq!(
+            builder.validate(),
+            Err(vec![BuildError::DivisibilityTooLarge(39)])
+        );
+        assert!(
+            builder
+                .build()
+                .unwrap_err()
+                .to_string()
+                .contains("Divisibility 39")
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_all_violations() {
+        // premine > cap，且 etching 带 CAP 却没有 terms 位
+        let builder = RunesBuilder::new()
+            .with_flags(1)
+            .with_rune("TEST")
+            .with_premine(2_000)
+            .with_cap(1_000);
+        assert_eq!(
+            builder.validate(),
+            Err(vec![
+                BuildError::PremineExceedsCap {
+                    premine: 2_000,
+                    cap: 1_000
+                },
+                BuildError::TermsWithoutFlag,
+            ])
+        );
+
+        // 从解码结果还原的非法 SYMBOL（代理对码点）
+        let mut builder = RunesBuilder::new().with_flags(1);
+        builder.fields.push((SYMBOL, 0xD800));
+        assert_eq!(
+            builder.validate(),
+            Err(vec![BuildError::InvalidSymbol(0xD800)])
+        );
+    }
+
+    #[test]
+    fn test_validate_valid_etching() {
+        let builder = RunesBuilder::new()
+            .with_flags(3)
+            .with_rune("TEST")
+            .with_symbol('₹')
+            .with_divisibility(38)
+            .with_premine(1_000)
+            .with_cap(21_000);
+        assert_eq!(builder.validate(), Ok(()));
+        assert!(builder.build().is_ok());
+    }
 
     #[test]
     fn test_rune_name_conversion() {
@@ -296,19 +881,87 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_rune_name_minimum_length_by_height() {
+        let cases = [
+            (0, 13),
+            (839_999, 13),
+            (840_000, 12),
+            (857_499, 12),
+            (857_500, 11),
+            (875_000, 10),
+            (1_032_499, 2),
+            (1_032_500, 2),
+            // 区间内线性下降：最小值从 "AA" 降到 "Z" 时长度变为 1
+            (1_033_173, 1),
+            (1_049_999, 1),
+            (2_000_000, 1),
+        ];
+
+        for (height, expected) in cases {
+            assert_eq!(
+                rune_name_minimum_length(height),
+                expected,
+                "height {}",
+                height
+            );
+        }
+    }
+
+    #[test]
+    fn test_rune_name_is_reserved() {
+        // 13 个字母在激活前就可用
+        assert!(!rune_name_is_reserved("UNCOMMON•GOODS", 0));
+
+        // 840000：12 个字母的名称刚开始解锁，从 ZZZ... 往下
+        assert!(rune_name_is_reserved("AAAAAAAAAAAA", 840_000));
+        assert!(!rune_name_is_reserved("ZZZZZZZZZZZZ", 840_000));
+
+        assert!(rune_name_is_reserved("TEST", 840_000));
+        assert!(!rune_name_is_reserved("TEST", 1_049_999));
+
+        // 27 个 A 起为保留名称，任何高度都不能 etch
+        assert!(rune_name_is_reserved(&"A".repeat(27), 2_000_000));
+        assert!(!rune_name_is_reserved(&"Z".repeat(26), 2_000_000));
+
+        // 非字母
+        assert!(rune_name_is_reserved("TEST1", 2_000_000));
+    }
+
     #[test]
     fn test_varint_encoding() {
         let test_cases = vec![
             (0, vec![0x00]),
             (1, vec![0x01]),
-            (252, vec![0xfc]),
-            (253, vec![0xfd, 0xfd, 0x00]),
+            (127, vec![0x7f]),
+            (128, vec![0x80, 0x01]),
+            (252, vec![0xfc, 0x01]),
+            (253, vec![0xfd, 0x01]),
+            (0x1_0000_0000, vec![0x80, 0x80, 0x80, 0x80, 0x10]),
         ];
 
         for (value, expected) in test_cases {
             let result = encode_varint(value);
             assert_eq!(result, expected);
         }
+
+        // u128::MAX：18 个 0xff 加上最后的 0x03，共 19 字节
+        let mut max = vec![0xff; 18];
+        max.push(0x03);
+        assert_eq!(encode_varint(u128::MAX), max);
+
+        for value in [
+            0,
+            127,
+            128,
+            u64::MAX as u128,
+            u64::MAX as u128 + 1,
+            u128::MAX,
+        ] {
+            let mut decoder = crate::rune_decode::VarIntDecoder::new(encode_varint(value));
+            assert_eq!(decoder.decode_varint().unwrap(), value);
+            assert!(decoder.is_eof());
+        }
     }
 
     #[test]
@@ -336,4 +989,663 @@ mod tests {
             Err(e) => panic!("构建失败: {}", e),
         }
     }
+
+    #[test]
+    fn test_validate_edicts_overspend() {
+        let inputs = [
+            RuneBalance {
+                id: TEST_RUNE,
+                amount: 600,
+            },
+            RuneBalance {
+                id: TEST_RUNE,
+                amount: 400,
+            },
+        ];
+        let edicts = [
+            Edict {
+                id: TEST_RUNE,
+                amount: 700,
+                output: 0,
+            },
+            Edict {
+                id: TEST_RUNE,
+                amount: 300,
+                output: 1,
+            },
+        ];
+        assert!(validate_edicts(&edicts, &inputs, &tx_outputs(2)).is_ok());
+
+        let too_much = [Edict {
+            id: TEST_RUNE,
+            amount: 1_001,
+            output: 0,
+        }];
+        assert_eq!(
+            validate_edicts(&too_much, &inputs, &tx_outputs(2)),
+            Err(EdictError::Overspend {
+                id: TEST_RUNE,
+                requested: 1_001,
+                available: 1_000,
+            })
+        );
+
+        // 输入里没有的 rune 也算超额
+        let other = [Edict {
+            id: RuneId { block: 1, tx: 0 },
+            amount: 1,
+            output: 0,
+        }];
+        assert!(matches!(
+            validate_edicts(&other, &inputs, &tx_outputs(2)),
+            Err(EdictError::Overspend { available: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate_edicts_output_out_of_range() {
+        let inputs = [RuneBalance {
+            id: TEST_RUNE,
+            amount: 1_000,
+        }];
+
+        // 两个普通输出加 OP_RETURN：output == 3 表示平分，合法
+        let split = [Edict {
+            id: TEST_RUNE,
+            amount: 100,
+            output: 3,
+        }];
+        assert!(validate_edicts(&split, &inputs, &tx_outputs(2)).is_ok());
+
+        // 平分时每个非 OP_RETURN 输出都收到 amount：2 × 600 超过余额
+        let split_overspend = [Edict {
+            id: TEST_RUNE,
+            amount: 600,
+            output: 3,
+        }];
+        assert_eq!(
+            validate_edicts(&split_overspend, &inputs, &tx_outputs(2)),
+            Err(EdictError::Overspend {
+                id: TEST_RUNE,
+                requested: 1_200,
+                available: 1_000,
+            })
+        );
+        // amount × 输出个数溢出也按超额处理
+        let split_overflow = [Edict {
+            id: TEST_RUNE,
+            amount: u128::MAX / 2 + 1,
+            output: 3,
+        }];
+        assert!(matches!(
+            validate_edicts(&split_overflow, &inputs, &tx_outputs(2)),
+            Err(EdictError::Overspend { .. })
+        ));
+
+        let out_of_range = [Edict {
+            id: TEST_RUNE,
+            amount: 100,
+            output: 4,
+        }];
+        assert_eq!(
+            validate_edicts(&out_of_range, &inputs, &tx_outputs(2)),
+            Err(EdictError::OutputOutOfRange {
+                output: 4,
+                output_count: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_edicts_all_remaining() {
+        let inputs = [RuneBalance {
+            id: TEST_RUNE,
+            amount: 1_000,
+        }];
+        let edicts = [
+            Edict {
+                id: TEST_RUNE,
+                amount: 1_000,
+                output: 0,
+            },
+            Edict {
+                id: TEST_RUNE,
+                amount: u128::MAX,
+                output: 1,
+            },
+            Edict {
+                id: TEST_RUNE,
+                amount: 0,
+                output: 1,
+            },
+        ];
+        assert!(validate_edicts(&edicts, &inputs, &tx_outputs(2)).is_ok());
+    }
+
+    #[test]
+    fn test_cenotaph_is_detected() {
+        let script = RunesBuilder::cenotaph().build().unwrap();
+        assert!(script.is_op_return());
+        assert!(script.len() <= 83);
+
+        let runestone = RunesParser::parse_script_hex(&script.to_hex_string())
+            .unwrap()
+            .unwrap();
+        assert!(runestone.is_cenotaph());
+
+        let normal = example_test_token().unwrap();
+        let runestone = RunesParser::parse_script_hex(&normal.to_hex_string())
+            .unwrap()
+            .unwrap();
+        assert!(!runestone.is_cenotaph());
+    }
+
+    #[test]
+    fn test_edicts_round_trip() {
+        let rune_a = RuneId {
+            block: 840_000,
+            tx: 1,
+        };
+        let rune_b = RuneId {
+            block: 840_000,
+            tx: 7,
+        };
+        let rune_c = RuneId {
+            block: 840_100,
+            tx: 2,
+        };
+
+        let script = RunesBuilder::new()
+            .with_pointer(1)
+            .with_edict(rune_c, 5, 0)
+            .with_edict(rune_a, 1_000, 0)
+            .with_edict(rune_b, 0, 1)
+            .build()
+            .unwrap();
+
+        let decoded = RunesParser::parse_script_hex(&script.to_hex_string())
+            .unwrap()
+            .unwrap()
+            .decoded()
+            .unwrap();
+
+        assert_eq!(decoded.pointer, Some(1));
+        assert!(!decoded.cenotaph);
+        assert_eq!(
+            decoded.edicts,
+            vec![
+                Edict {
+                    id: rune_a,
+                    amount: 1_000,
+                    output: 0,
+                },
+                Edict {
+                    id: rune_b,
+                    amount: 0,
+                    output: 1,
+                },
+                Edict {
+                    id: rune_c,
+                    amount: 5,
+                    output: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_turbo_sets_flags_bit() {
+        let script = RunesBuilder::new()
+            .with_flags(1)
+            .with_spacers(0b101)
+            .with_symbol('T')
+            .with_turbo()
+            .build()
+            .unwrap();
+        let runestone = RunesParser::parse_script_hex(&script.to_hex_string())
+            .unwrap()
+            .unwrap();
+
+        // turbo 只体现在 FLAGS 中，没有单独的 tag 10 字段
+        assert_eq!(
+            runestone.fields.get(&FLAGS),
+            Some(&(FLAG_ETCHING | FLAG_TURBO))
+        );
+        assert!(!runestone.fields.contains_key(&crate::rune_decode::TURBO));
+
+        let flags = crate::rune_decode::Flags::from_bits(runestone.fields[&FLAGS]);
+        assert!(flags.etching && flags.turbo && !flags.terms);
+        assert_eq!(flags.unrecognized, 0);
+
+        let decoded = runestone.decoded().unwrap();
+        assert!(!decoded.cenotaph);
+        let etching = decoded.etching.unwrap();
+        assert!(etching.turbo);
+        assert_eq!(etching.spacers, Some(0b101));
+
+        // 没有 with_flags 时新增 FLAGS 字段
+        let script = RunesBuilder::new().with_turbo().build().unwrap();
+        let runestone = RunesParser::parse_script_hex(&script.to_hex_string())
+            .unwrap()
+            .unwrap();
+        assert_eq!(runestone.fields.get(&FLAGS), Some(&FLAG_TURBO));
+    }
+
+    #[test]
+    fn test_encoded_len_matches_build() {
+        let builders = [
+            RunesBuilder::new()
+                .with_flags(1)
+                .with_premine(1_000_000)
+                .with_divisibility(2)
+                .with_turbo(),
+            RunesBuilder::new().mint(TEST_RUNE).with_pointer(1),
+            (0..10).fold(RunesBuilder::new(), |builder, i| {
+                builder.with_edict(
+                    RuneId {
+                        block: 840_000 + i,
+                        tx: i as u32,
+                    },
+                    100_000 * i as u128,
+                    1,
+                )
+            }),
+        ];
+
+        for builder in builders {
+            let len = builder.encoded_len();
+            let script = builder.build().unwrap();
+            let payload: Vec<u8> = script
+                .instructions()
+                .skip(2)
+                .flat_map(|ins| ins.unwrap().push_bytes().unwrap().as_bytes().to_vec())
+                .collect();
+            assert_eq!(len, payload.len());
+        }
+    }
+
+    #[test]
+    fn test_large_runestone_round_trip_across_pushes() {
+        // 9 字节的 amount varint 让 520 / 1040 处的 push 边界落在 varint 中间
+        let mut builder = RunesBuilder::new()
+            .with_flags(1)
+            .with_rune("TEST")
+            .with_premine(1_000_000_000_000);
+        let mut expected = Vec::new();
+        for i in 0..100u32 {
+            let edict = Edict {
+                id: TEST_RUNE,
+                amount: u64::MAX as u128 - i as u128,
+                output: i % 2,
+            };
+            builder = builder.with_edict(edict.id, edict.amount, edict.output);
+            expected.push(edict);
+        }
+        let script = builder.build().unwrap();
+
+        // 拆成 3 个 push，每个不超过 520 字节
+        let pushes: Vec<Vec<u8>> = script
+            .instructions()
+            .skip(2)
+            .map(|ins| ins.unwrap().push_bytes().unwrap().as_bytes().to_vec())
+            .collect();
+        assert_eq!(pushes.len(), 3);
+        assert!(pushes.iter().all(|p| p.len() <= MAX_SCRIPT_ELEMENT_SIZE));
+
+        let data = pushes.concat();
+        let mut decoder = crate::rune_decode::VarIntDecoder::new(data);
+        let mut varint_ends = Vec::new();
+        while !decoder.is_eof() {
+            decoder.decode_varint().unwrap();
+            varint_ends.push(decoder.position());
+        }
+        assert!(!varint_ends.contains(&MAX_SCRIPT_ELEMENT_SIZE));
+        assert!(!varint_ends.contains(&(2 * MAX_SCRIPT_ELEMENT_SIZE)));
+
+        let runestone = RunesParser::parse_script_hex(&script.to_hex_string())
+            .unwrap()
+            .unwrap();
+        assert!(!runestone.is_cenotaph());
+        assert_eq!(runestone.edicts, expected);
+        let etching = runestone.etching().unwrap();
+        assert_eq!(etching.rune.as_deref(), Some("TEST"));
+        assert_eq!(etching.premine, Some(1_000_000_000_000));
+    }
+
+    #[test]
+    fn test_mint_round_trip() {
+        let rune_id = RuneId {
+            block: 840_000,
+            tx: 42,
+        };
+        let script = RunesBuilder::new().mint(rune_id).build().unwrap();
+
+        let runestone = RunesParser::parse_script_hex(&script.to_hex_string())
+            .unwrap()
+            .unwrap();
+        assert_eq!(runestone.mint, Some(rune_id));
+        assert_eq!(runestone.decoded().unwrap().mint, Some(rune_id));
+        assert!(!runestone.is_cenotaph());
+
+        // with_mint 与 mint 编码一致
+        assert_eq!(
+            RunesBuilder::new().with_mint(840_000, 42).build().unwrap(),
+            script
+        );
+        assert_eq!(
+            RunesBuilder::from_runestone(&runestone).build().unwrap(),
+            script
+        );
+    }
+
+    #[test]
+    fn test_mint_zero_rune_id_rejected() {
+        let err = RunesBuilder::new()
+            .mint(RuneId { block: 0, tx: 0 })
+            .build()
+            .unwrap_err();
+        assert!(err.to_string().contains("invalid mint rune id"));
+    }
+
+    #[test]
+    fn test_mint_with_single_value_is_cenotaph() {
+        // 只有 block 没有 tx
+        let script = RunesBuilder {
+            fields: vec![(3, 840_000)],
+            mint: None,
+            edicts: Vec::new(),
+            turbo: false,
+        }
+        .build()
+        .unwrap();
+
+        let runestone = RunesParser::parse_script_hex(&script.to_hex_string())
+            .unwrap()
+            .unwrap();
+        assert_eq!(runestone.mint, None);
+        assert!(runestone.is_cenotaph());
+    }
+
+    #[test]
+    fn test_edicts_parse_rebuild_is_identity() {
+        use bitcoin::secp256k1::rand::rngs::StdRng;
+        use bitcoin::secp256k1::rand::{Rng, SeedableRng};
+
+        // 随机生成 edicts（覆盖各个 VarInt 长度），parse → rebuild 必须得到同样的字节
+        let mut rng = StdRng::seed_from_u64(833);
+        for _ in 0..200 {
+            let mut builder = RunesBuilder::new();
+            for _ in 0..rng.gen_range(1..6) {
+                let block = match rng.gen_range(0..3) {
+                    0 => rng.gen_range(0..253),
+                    1 => rng.gen_range(840_000..840_010),
+                    _ => rng.gen_range(0..u32::MAX as u64),
+                };
+                let amount = match rng.gen_range(0..3) {
+                    0 => rng.gen_range(0..253),
+                    1 => rng.gen_range(0..u64::MAX as u128),
+                    _ => 0,
+                };
+                builder = builder.with_edict(
+                    RuneId {
+                        block,
+                        tx: rng.gen_range(0..70_000),
+                    },
+                    amount,
+                    rng.gen_range(0..4),
+                );
+            }
+            let script = builder.build().unwrap();
+
+            let runestone = RunesParser::parse_script_hex(&script.to_hex_string())
+                .unwrap()
+                .unwrap();
+            assert!(runestone.flaw.is_none());
+            let rebuilt = RunesBuilder::from_runestone(&runestone).build().unwrap();
+            assert_eq!(rebuilt, script);
+        }
+    }
+
+    fn transfer(edicts: Vec<Edict>, pointer: Option<u32>) -> DecodedRunestone {
+        DecodedRunestone {
+            edicts,
+            pointer,
+            flags: Default::default(),
+            mint: None,
+            etching: None,
+            cenotaph: false,
+        }
+    }
+
+    /// n 个普通输出，最后是 runestone 所在的 OP_RETURN
+    fn tx_outputs(n: usize) -> Vec<TxOut> {
+        let mut outputs = vec![
+            TxOut {
+                value: Amount::from_sat(546),
+                script_pubkey: ScriptBuf::new_p2tr_tweaked(
+                    bitcoin::key::TweakedPublicKey::dangerous_assume_tweaked(
+                        bitcoin::XOnlyPublicKey::from_slice(&[1; 32]).unwrap(),
+                    ),
+                ),
+            };
+            n
+        ];
+        outputs.push(TxOut {
+            value: Amount::ZERO,
+            script_pubkey: RunesBuilder::cenotaph().build().unwrap(),
+        });
+        outputs
+    }
+
+    /// 每个普通输出收到的余额，最后追加 OP_RETURN 的空结果
+    fn balances(amounts: &[u128]) -> Vec<Vec<RuneBalance>> {
+        amounts
+            .iter()
+            .map(|amount| match amount {
+                0 => vec![],
+                amount => vec![RuneBalance {
+                    id: TEST_RUNE,
+                    amount: *amount,
+                }],
+            })
+            .chain([vec![]])
+            .collect()
+    }
+
+    const INPUT: RuneBalance = RuneBalance {
+        id: TEST_RUNE,
+        amount: 1_000,
+    };
+
+    #[test]
+    fn test_apply_runestone_simple_split() {
+        let runestone = transfer(
+            vec![
+                Edict {
+                    id: TEST_RUNE,
+                    amount: 300,
+                    output: 1,
+                },
+                Edict {
+                    id: TEST_RUNE,
+                    amount: 200,
+                    output: 2,
+                },
+                // 超过剩余余额的 edict 只转出剩余部分
+                Edict {
+                    id: TEST_RUNE,
+                    amount: 10_000,
+                    output: 2,
+                },
+            ],
+            None,
+        );
+
+        assert_eq!(
+            apply_runestone(vec![INPUT], &runestone, &tx_outputs(3)),
+            balances(&[0, 300, 700])
+        );
+    }
+
+    #[test]
+    fn test_apply_runestone_all_remaining() {
+        // amount 0 且 output == 输出个数（含 OP_RETURN）：平分到普通输出，余数给前面的输出
+        let split_all = transfer(
+            vec![Edict {
+                id: TEST_RUNE,
+                amount: 0,
+                output: 4,
+            }],
+            None,
+        );
+        assert_eq!(
+            apply_runestone(vec![INPUT], &split_all, &tx_outputs(3)),
+            balances(&[334, 333, 333])
+        );
+
+        // 先转 100，再用 u128::MAX 把剩余全部转到 output 2
+        let remaining = transfer(
+            vec![
+                Edict {
+                    id: TEST_RUNE,
+                    amount: 100,
+                    output: 0,
+                },
+                Edict {
+                    id: TEST_RUNE,
+                    amount: u128::MAX,
+                    output: 2,
+                },
+            ],
+            None,
+        );
+        assert_eq!(
+            apply_runestone(vec![INPUT], &remaining, &tx_outputs(3)),
+            balances(&[100, 0, 900])
+        );
+    }
+
+    #[test]
+    fn test_apply_runestone_pointer_fallback() {
+        let edicts = vec![Edict {
+            id: TEST_RUNE,
+            amount: 400,
+            output: 0,
+        }];
+
+        // 未分配的 600 转到 pointer
+        assert_eq!(
+            apply_runestone(
+                vec![INPUT],
+                &transfer(edicts.clone(), Some(2)),
+                &tx_outputs(3)
+            ),
+            balances(&[400, 0, 600])
+        );
+        // 没有 pointer 时转到第一个输出
+        assert_eq!(
+            apply_runestone(vec![INPUT], &transfer(edicts.clone(), None), &tx_outputs(3)),
+            balances(&[1_000, 0, 0])
+        );
+        // pointer 指向 OP_RETURN：未分配的 600 被烧掉
+        assert_eq!(
+            apply_runestone(
+                vec![INPUT],
+                &transfer(edicts.clone(), Some(3)),
+                &tx_outputs(3)
+            ),
+            balances(&[400, 0, 0])
+        );
+        // pointer 越界、cenotaph：全部烧掉
+        assert_eq!(
+            apply_runestone(
+                vec![INPUT],
+                &transfer(edicts.clone(), Some(4)),
+                &tx_outputs(3)
+            ),
+            balances(&[0, 0, 0])
+        );
+        let mut cenotaph = transfer(edicts, None);
+        cenotaph.cenotaph = true;
+        assert_eq!(
+            apply_runestone(vec![INPUT], &cenotaph, &tx_outputs(3)),
+            balances(&[0, 0, 0])
+        );
+    }
+    #[test]
+    fn test_etch_and_distribute_in_one_tx() {
+        let script = RunesBuilder::new()
+            .with_flags(1)
+            .with_premine(1_000)
+            .with_edict(RuneId::ETCHED, 400, 1)
+            .with_edict(TEST_RUNE, 50, 2)
+            .build()
+            .unwrap();
+
+        let decoded = RunesParser::parse_script_hex(&script.to_hex_string())
+            .unwrap()
+            .unwrap()
+            .decoded()
+            .unwrap();
+        assert!(!decoded.cenotaph);
+        assert_eq!(decoded.etching.as_ref().unwrap().premine, Some(1_000));
+        // 0:0 排在最前，delta 编码为 (0, 0)，解码后保持 0:0
+        assert_eq!(
+            decoded.edicts,
+            vec![
+                Edict {
+                    id: RuneId::ETCHED,
+                    amount: 400,
+                    output: 1,
+                },
+                Edict {
+                    id: TEST_RUNE,
+                    amount: 50,
+                    output: 2,
+                },
+            ]
+        );
+
+        // premine 中 400 转到 output 1，其余随 pointer 默认规则到 output 0
+        let outputs = apply_runestone(
+            vec![RuneBalance {
+                id: TEST_RUNE,
+                amount: 50,
+            }],
+            &decoded,
+            &tx_outputs(3),
+        );
+        let etched = |amount| RuneBalance {
+            id: RuneId::ETCHED,
+            amount,
+        };
+        assert_eq!(outputs[0], vec![etched(600)]);
+        assert_eq!(outputs[1], vec![etched(400)]);
+        assert_eq!(
+            outputs[2],
+            vec![RuneBalance {
+                id: TEST_RUNE,
+                amount: 50,
+            }]
+        );
+
+        // 0:0 不从输入中扣；没有 etching 时不能引用 0:0
+        assert!(validate_edicts(&decoded.edicts, &[], &tx_outputs(3)).is_err());
+        assert!(
+            validate_edicts(
+                &decoded.edicts,
+                &[RuneBalance {
+                    id: TEST_RUNE,
+                    amount: 50,
+                }],
+                &tx_outputs(3)
+            )
+            .is_ok()
+        );
+        let err = RunesBuilder::new()
+            .with_edict(RuneId::ETCHED, 1, 0)
+            .build()
+            .unwrap_err();
+        assert!(err.to_string().contains("etching flag is not set"));
+    }
 }
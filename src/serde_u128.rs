@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// =====================================================
+/// u128 按十进制字符串序列化
+/// =====================================================
+///
+/// JSON 的 number 在大多数实现里是 f64，无法精确表示 u128（rune 数量、名称等），
+/// 用 `#[serde(with = "crate::serde_u128")]` 标注字段
+pub fn serialize<S: Serializer>(value: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(D::Error::custom)
+}
+
+/// Option<u128>：None 序列化为 null
+pub mod option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<u128>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.map(|v| v.to_string()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u128>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| s.parse().map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+/// HashMap<u128, u128>：key 和 value 都为字符串
+pub mod map {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        value: &HashMap<u128, u128>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(value.iter().map(|(k, v)| (k.to_string(), v.to_string())))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<u128, u128>, D::Error> {
+        HashMap::<String, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(k, v)| {
+                Ok((
+                    k.parse().map_err(D::Error::custom)?,
+                    v.parse().map_err(D::Error::custom)?,
+                ))
+            })
+            .collect()
+    }
+}
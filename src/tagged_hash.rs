@@ -0,0 +1,106 @@
+use bitcoin::hashes::{Hash, HashEngine, sha256, sha256t};
+
+/// =====================================================
+/// BIP340 tagged hash
+/// =====================================================
+///
+/// tagged_hash(tag, data) = SHA256(SHA256(tag) || SHA256(tag) || data)
+///
+/// 前 64 字节只和 tag 有关，正好是一个 SHA256 块。tag 用 sha256t::Tag 类型表示，
+/// 吸收完这 64 字节的 midstate 在编译期算好（sha256::Midstate::hash_tag），
+/// 每次调用只从常量 midstate 构造 engine，不加锁也不分配内存。
+/// Taproot 的 tag 直接用 bitcoin::taproot 里的 TapLeafTag / TapBranchTag / TapTweakTag
+pub fn tagged_hash<T: sha256t::Tag>(data: &[u8]) -> [u8; 32] {
+    let mut engine = T::engine();
+    engine.input(data);
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::ScriptBuf;
+    use bitcoin::consensus::encode;
+    use bitcoin::key::{Secp256k1, TapTweak, UntweakedPublicKey};
+    use bitcoin::taproot::{
+        LeafVersion, TapBranchTag, TapLeafHash, TapLeafTag, TapNodeHash, TapTweakHash, TapTweakTag,
+    };
+
+    #[test]
+    fn test_tagged_hash_bip341_vectors() {
+        // BIP341 wallet test vectors：scriptPubKey[1]
+        let script = ScriptBuf::from_hex(
+            "20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac",
+        )
+        .unwrap();
+        let mut leaf = vec![LeafVersion::TapScript.to_consensus()];
+        leaf.extend(encode::serialize(&script));
+        let leaf_hash = tagged_hash::<TapLeafTag>(&leaf);
+        assert_eq!(
+            hex::encode(leaf_hash),
+            "5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"
+        );
+        assert_eq!(
+            leaf_hash,
+            TapLeafHash::from_script(&script, LeafVersion::TapScript).to_byte_array()
+        );
+
+        // TapBranch：两个子节点按字节序排序后拼接
+        let other = [0x11u8; 32];
+        let (a, b) = if leaf_hash < other {
+            (leaf_hash, other)
+        } else {
+            (other, leaf_hash)
+        };
+        assert_eq!(
+            tagged_hash::<TapBranchTag>(&[a, b].concat()),
+            TapNodeHash::from_node_hashes(
+                TapNodeHash::from_byte_array(leaf_hash),
+                TapNodeHash::from_byte_array(other)
+            )
+            .to_byte_array()
+        );
+
+        // TapTweak：internal key || merkle root
+        let internal_key: UntweakedPublicKey =
+            "187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27"
+                .parse()
+                .unwrap();
+        let merkle_root = TapNodeHash::from_byte_array(leaf_hash);
+        let tweak = tagged_hash::<TapTweakTag>(&[internal_key.serialize(), leaf_hash].concat());
+        assert_eq!(
+            tweak,
+            TapTweakHash::from_key_and_tweak(internal_key, Some(merkle_root)).to_byte_array()
+        );
+        assert_eq!(
+            hex::encode(tweak),
+            "cbd8679ba636c1110ea247542cfbd964131a6be84f873f7f3b62a777528ed001"
+        );
+        let (output_key, _) = internal_key.tap_tweak(&Secp256k1::new(), Some(merkle_root));
+        assert_eq!(
+            output_key.to_string(),
+            "147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3"
+        );
+    }
+
+    #[test]
+    fn test_tagged_hash_matches_uncached() {
+        let uncached = |tag: &str, data: &[u8]| {
+            let tag_hash = sha256::Hash::hash(tag.as_bytes());
+            let mut engine = sha256::Hash::engine();
+            engine.input(tag_hash.as_ref());
+            engine.input(tag_hash.as_ref());
+            engine.input(data);
+            sha256::Hash::from_engine(engine).to_byte_array()
+        };
+
+        for data in [&b""[..], b"first", b"second"] {
+            assert_eq!(tagged_hash::<TapLeafTag>(data), uncached("TapLeaf", data));
+        }
+        // 不同 tag 得到不同的结果
+        assert_ne!(
+            tagged_hash::<TapLeafTag>(b"data"),
+            tagged_hash::<TapBranchTag>(b"data")
+        );
+    }
+}
@@ -1,15 +1,32 @@
-use bitcoin::key::{Keypair, Secp256k1, TweakedKeypair};
+use std::fmt;
+
+use bitcoin::address::{NetworkUnchecked, ParseError};
+use bitcoin::hashes::Hash;
+use bitcoin::key::Parity;
+use bitcoin::key::{Keypair, Secp256k1, TapTweak, TweakedKeypair};
 use bitcoin::script::Builder;
-use bitcoin::sighash::{Prevouts, SighashCache, TapSighashType};
-use bitcoin::taproot::{self, LeafVersion, TapLeaf, TaprootBuilder, TaprootSpendInfo};
+use bitcoin::secp256k1::rand::RngCore;
+use bitcoin::secp256k1::rand::seq::SliceRandom;
+use bitcoin::sighash::{Prevouts, SighashCache, TapSighash, TapSighashType};
+use bitcoin::taproot::{
+    self, ControlBlock, LeafVersion, TAPROOT_CONTROL_BASE_SIZE, TAPROOT_CONTROL_MAX_NODE_COUNT,
+    TAPROOT_CONTROL_NODE_SIZE, TAPROOT_LEAF_MASK, TapNodeHash, TaprootBuilder, TaprootSpendInfo,
+};
 use bitcoin::transaction::Version;
 use bitcoin::{
-    Address, Amount, Network, OutPoint, ScriptBuf, Sequence, TapLeafHash, Transaction, TxIn, TxOut,
-    Txid, Witness, hex,
+    Address, Amount, FeeRate, Network, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut,
+    Txid, Witness, XOnlyPublicKey, hex,
 };
 
-use crate::alchemy_client::TxOut as AlchemyTxOut;
-use crate::utils::{build_inscription_script, build_rune_op_return};
+use crate::alchemy_client::{PrevoutCache, TxOut as AlchemyTxOut};
+use crate::fees::{
+    SpendKind, dust_limit, ensure_not_dust, estimate_fee, is_dust, min_commit_value,
+};
+use crate::rune_decode::{RuneId, RunesParser, Terms, mint_is_open};
+use crate::runes_builder::{RunesBuilder, remap_runestone_outputs};
+use crate::utils::{
+    TEXT_PLAIN_UTF8, brc20_deploy_body, build_inscription_script, build_rune_op_return,
+};
 use crate::wallets::TaprootWallet;
 
 fn parse_taproot_schnorr_signature(
@@ -28,6 +45,43 @@ fn parse_taproot_schnorr_signature(
     }
 }
 
+/// =====================================================
+/// 检查 sighash 类型与交易输出是否匹配
+/// =====================================================
+///
+/// - ALL / DEFAULT：签名所有输出
+/// - NONE：不签名任何输出，任何人都可以改写输出
+/// - SINGLE：只签名与输入同序号的输出，该输出必须存在，
+///   否则签名无法计算（legacy 下就是著名的 SIGHASH_SINGLE bug）
+pub fn ensure_sighash_outputs(
+    tx: &Transaction,
+    input_index: usize,
+    sighash_type: TapSighashType,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if input_index >= tx.input.len() {
+        return Err(format!(
+            "input index {} out of range ({} inputs)",
+            input_index,
+            tx.input.len()
+        )
+        .into());
+    }
+
+    match sighash_type {
+        TapSighashType::Single | TapSighashType::SinglePlusAnyoneCanPay
+            if input_index >= tx.output.len() =>
+        {
+            Err(format!(
+                "SIGHASH_SINGLE on input {} requires an output at the same index ({} outputs)",
+                input_index,
+                tx.output.len()
+            )
+            .into())
+        }
+        _ => Ok(()),
+    }
+}
+
 fn p2tr_output_key_from_script_pubkey(
     spk: &ScriptBuf,
 ) -> Result<bitcoin::secp256k1::XOnlyPublicKey, Box<dyn std::error::Error>> {
@@ -43,6 +97,677 @@ fn p2tr_output_key_from_script_pubkey(
     )?)
 }
 
+/// 只用于估算交易大小的输出（金额不影响 vsize）
+pub fn size_only_output(script_pubkey: ScriptBuf) -> TxOut {
+    TxOut {
+        value: Amount::ZERO,
+        script_pubkey,
+    }
+}
+
+/// 构造找零输出：低于 dust 限制时不创建找零，这部分金额直接并入手续费
+pub fn change_output_or_fold(change_value: u64, script_pubkey: ScriptBuf) -> Option<TxOut> {
+    let value = Amount::from_sat(change_value);
+    if is_dust(value, &script_pubkey) {
+        println!("  ⚠️ Change {} sat is dust, folded into fee", change_value);
+        return None;
+    }
+    Some(TxOut {
+        value,
+        script_pubkey,
+    })
+}
+
+/// =====================================================
+/// 找零策略
+/// =====================================================
+///
+/// 无论哪种策略，低于 dust 的找零都会并入手续费
+#[derive(Debug, Clone)]
+pub enum ChangePolicy {
+    /// 找零到指定地址
+    ToAddress(Address),
+    /// 找零低于给定阈值时并入手续费，否则回到钱包地址
+    FoldIntoFee(Amount),
+    /// 找零到钱包派生的新找零地址（BIP86 change 链），钱包可以扫描并花费这些地址
+    ToNewDerivedAddress,
+}
+
+impl ChangePolicy {
+    /// 确定找零地址和并入手续费的阈值；ToNewDerivedAddress 每次调用都会派生新地址
+    fn resolve(
+        &self,
+        secp: &Secp256k1<bitcoin::secp256k1::All>,
+        wallet: &TaprootWallet,
+    ) -> Result<(Address, Amount), Box<dyn std::error::Error>> {
+        match self {
+            ChangePolicy::ToAddress(address) => {
+                ensure_address_network(address, wallet.network())?;
+                Ok((address.clone(), Amount::ZERO))
+            }
+            ChangePolicy::FoldIntoFee(threshold) => Ok((wallet.get_internal_address(), *threshold)),
+            ChangePolicy::ToNewDerivedAddress => {
+                Ok((wallet.next_change_address(secp)?, Amount::ZERO))
+            }
+        }
+    }
+}
+
+/// =====================================================
+/// 手续费由谁支付
+/// =====================================================
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeMode {
+    /// 交易按 fee_rate 自己支付手续费
+    SelfPaid,
+    /// CPFP：交易只按 min_relay 支付（仍高于 relay 下限，可以单独转发），
+    /// 找零输出作为 anchor，由之后花费它的子交易把整个包的费率补到 fee_rate
+    ChildPays { min_relay: FeeRate },
+}
+
+impl FeeMode {
+    /// 本交易实际使用的费率；min_relay 高于 fee_rate 时仍按 fee_rate
+    pub fn parent_fee_rate(&self, fee_rate: FeeRate) -> FeeRate {
+        match self {
+            FeeMode::SelfPaid => fee_rate,
+            FeeMode::ChildPays { min_relay } => fee_rate.min(*min_relay),
+        }
+    }
+}
+
+/// 按阈值构造找零输出：低于阈值（或 dust）时并入手续费
+fn change_output_above(
+    change_value: u64,
+    script_pubkey: ScriptBuf,
+    threshold: Amount,
+) -> Option<TxOut> {
+    if Amount::from_sat(change_value) < threshold {
+        println!(
+            "  ⚠️ Change {} sat is below {} sat, folded into fee",
+            change_value,
+            threshold.to_sat()
+        );
+        return None;
+    }
+    change_output_or_fold(change_value, script_pubkey)
+}
+
+/// 用给定的 RNG 打乱输出顺序，避免「支付在前、找零在后」这类可被链上分析利用的固定模式。
+/// 如果输出中有 runestone，同步改写其中的 edict output 和 POINTER
+fn shuffle_outputs(
+    outputs: &mut Vec<TxOut>,
+    rng: &mut dyn RngCore,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut order: Vec<usize> = (0..outputs.len()).collect();
+    order.shuffle(rng);
+
+    // new_index[旧序号] = 新序号
+    let mut new_index = vec![0; order.len()];
+    for (new, old) in order.iter().enumerate() {
+        new_index[*old] = new;
+    }
+
+    let mut shuffled: Vec<TxOut> = order.iter().map(|i| outputs[*i].clone()).collect();
+    for output in shuffled.iter_mut() {
+        if output.script_pubkey.is_op_return() {
+            output.script_pubkey = remap_runestone_outputs(&output.script_pubkey, &new_index)?;
+        }
+    }
+
+    *outputs = shuffled;
+    Ok(())
+}
+
+/// 花费前检查 UTXO 的确认数，0 确认的 UTXO 可能因 reorg 消失，导致后续交易链全部失效
+pub fn ensure_confirmations(
+    utxo: &AlchemyTxOut,
+    min_confirmations: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if utxo.confirmations < min_confirmations as i64 {
+        return Err(format!(
+            "UTXO {}:{} has {} confirmations, requires at least {}",
+            utxo.txid, utxo.vout, utxo.confirmations, min_confirmations
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// 检查地址属于钱包所在网络，避免把 testnet 的币转到 mainnet 地址（或反过来）
+pub fn ensure_address_network(
+    address: &Address,
+    network: Network,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !address.as_unchecked().is_valid_for_network(network) {
+        return Err(format!(
+            "address {} is not valid for network {}",
+            address,
+            network.to_core_arg()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// 解析用户输入或 RPC 返回的地址字符串，并校验属于指定网络
+///
+/// 所有字符串形式的地址都应经过这里，得到的 Address 可以直接当作已校验网络的地址使用
+pub fn parse_address(s: &str, network: Network) -> Result<Address, ParseError> {
+    s.trim()
+        .parse::<Address<NetworkUnchecked>>()?
+        .require_network(network)
+}
+
+/// coinbase 输出需要 100 个确认才能花费
+pub const COINBASE_MATURITY: u32 = 100;
+
+/// coinbase UTXO 未成熟时报错，并给出还差多少个确认（否则节点只会返回
+/// "bad-txns-premature-spend-of-coinbase"）
+pub fn ensure_coinbase_mature(utxo: &AlchemyTxOut) -> Result<(), Box<dyn std::error::Error>> {
+    if utxo.coinbase == Some(true) && utxo.confirmations < COINBASE_MATURITY as i64 {
+        return Err(format!(
+            "UTXO {}:{} is an immature coinbase: {} confirmations, needs {} more",
+            utxo.txid,
+            utxo.vout,
+            utxo.confirmations,
+            COINBASE_MATURITY as i64 - utxo.confirmations
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// =====================================================
+/// scriptPubKey 类型
+/// =====================================================
+///
+/// 钱包目前只会对 P2TR 做 key-path 签名；其它类型的 input 需要在签名前识别出来并报错，
+/// 而不是算出一个无效的签名
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptType {
+    P2tr,
+    P2wpkh,
+    P2wsh,
+    P2pkh,
+    Other,
+}
+
+impl fmt::Display for ScriptType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ScriptType::P2tr => "P2TR",
+            ScriptType::P2wpkh => "P2WPKH",
+            ScriptType::P2wsh => "P2WSH",
+            ScriptType::P2pkh => "P2PKH",
+            ScriptType::Other => "non-standard",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+pub fn classify_script(script: &ScriptBuf) -> ScriptType {
+    if script.is_p2tr() {
+        ScriptType::P2tr
+    } else if script.is_p2wpkh() {
+        ScriptType::P2wpkh
+    } else if script.is_p2wsh() {
+        ScriptType::P2wsh
+    } else if script.is_p2pkh() {
+        ScriptType::P2pkh
+    } else {
+        ScriptType::Other
+    }
+}
+
+/// 把 RPC 返回的 UTXO 转成签名用的 prevout，并确认它是 P2TR 输出、不是未成熟的 coinbase
+///
+/// sighash 会承诺 prevout 的 scriptPubKey，空脚本或非 taproot 脚本算出的签名没有意义，
+/// 在计算 sighash 之前直接报错
+pub fn taproot_prevout(utxo: &AlchemyTxOut) -> Result<TxOut, Box<dyn std::error::Error>> {
+    ensure_coinbase_mature(utxo)?;
+    let script_pubkey = utxo.script_pubkey.script.clone();
+    if script_pubkey.is_empty() {
+        return Err(format!("UTXO {}:{} has an empty scriptPubKey", utxo.txid, utxo.vout).into());
+    }
+    let script_type = classify_script(&script_pubkey);
+    if script_type != ScriptType::P2tr {
+        return Err(format!(
+            "UTXO {}:{} is not a taproot output ({}): unsupported input type, only P2TR key-path is implemented",
+            utxo.txid, utxo.vout, script_type
+        )
+        .into());
+    }
+
+    Ok(TxOut {
+        value: Amount::from_sat(utxo.value),
+        script_pubkey,
+    })
+}
+
+/// 对所有 input 做 key-path 签名（要求所有 prevout 都是 wallet 的 key-path 地址）
+fn sign_keypath_inputs(
+    secp: &Secp256k1<bitcoin::secp256k1::All>,
+    tx: &mut Transaction,
+    prevouts: &[TxOut],
+    taproot_wallet: &TaprootWallet,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // 按 prevout 的 scriptPubKey 选择 key，找零链上的 UTXO 也能花费
+    sign_keypath_inputs_with(tx, prevouts, |msg, prevout| {
+        taproot_wallet.sign_keypath_for(secp, msg, &prevout.script_pubkey)
+    })
+}
+
+/// 多 input 的 key-path 签名，prevout 从缓存中取（需先 PrevoutCache::fetch 所有 input）
+pub fn sign_keypath_inputs_cached(
+    secp: &Secp256k1<bitcoin::secp256k1::All>,
+    tx: &mut Transaction,
+    prevout_cache: &PrevoutCache,
+    taproot_wallet: &TaprootWallet,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let prevouts = prevout_cache.prevouts_for(tx)?;
+    sign_keypath_inputs(secp, tx, &prevouts, taproot_wallet)
+}
+
+/// 计算每个 input 的 key-path sighash 并用 sign 签名（传入该 input 的 prevout），写入 witness
+fn sign_keypath_inputs_with(
+    tx: &mut Transaction,
+    prevouts: &[TxOut],
+    sign: impl Fn(
+        &bitcoin::secp256k1::Message,
+        &TxOut,
+    ) -> Result<bitcoin::secp256k1::schnorr::Signature, Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let signatures = compute_keypath_sighashes(tx, prevouts, TapSighashType::Default)?
+        .into_iter()
+        .zip(prevouts)
+        .map(|(sighash, prevout)| sign(&bitcoin::secp256k1::Message::from(sighash), prevout))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (input, sig) in tx.input.iter_mut().zip(signatures) {
+        input.witness.push(sig.as_ref());
+    }
+
+    Ok(())
+}
+
+/// =====================================================
+/// 离线签名：计算所有 input 的 key-path sighash
+/// =====================================================
+///
+/// sighash 需要完整的 prevout（金额和 scriptPubKey），私钥可以留在离线设备上：
+/// 在线端算出 sighash 交给离线签名方，签名再由 finalize_keypath_tx 组装
+pub fn compute_keypath_sighashes(
+    tx: &Transaction,
+    prevouts: &[TxOut],
+    sighash_type: TapSighashType,
+) -> Result<Vec<TapSighash>, Box<dyn std::error::Error>> {
+    if prevouts.len() != tx.input.len() {
+        return Err(format!(
+            "prevouts length mismatch: prevouts={} tx.inputs={}",
+            prevouts.len(),
+            tx.input.len()
+        )
+        .into());
+    }
+
+    let mut sighash_cache = SighashCache::new(tx);
+    (0..tx.input.len())
+        .map(|input_index| {
+            Ok(sighash_cache.taproot_key_spend_signature_hash(
+                input_index,
+                &Prevouts::All(prevouts),
+                sighash_type,
+            )?)
+        })
+        .collect()
+}
+
+/// =====================================================
+/// 离线签名：把外部提供的签名组装进交易
+/// =====================================================
+///
+/// 签名在别处（离线设备）完成，这里按 input 顺序把每个 key-path 签名写成唯一的 witness 元素。
+/// 签名为 64 字节（SIGHASH_DEFAULT）或 65 字节（末尾带 sighash 类型，不能是 0x00）
+pub fn finalize_keypath_tx(
+    mut tx: Transaction,
+    signatures: Vec<Vec<u8>>,
+) -> Result<Transaction, Box<dyn std::error::Error>> {
+    if signatures.len() != tx.input.len() {
+        return Err(format!(
+            "signature count mismatch: signatures={} tx.inputs={}",
+            signatures.len(),
+            tx.input.len()
+        )
+        .into());
+    }
+
+    for (index, (input, sig)) in tx.input.iter_mut().zip(signatures).enumerate() {
+        parse_taproot_schnorr_signature(&sig).map_err(|e| format!("input {}: {}", index, e))?;
+        if sig.len() == 65 && sig[64] == TapSighashType::Default as u8 {
+            return Err(format!(
+                "input {}: SIGHASH_DEFAULT signature must be 64 bytes",
+                index
+            )
+            .into());
+        }
+        input.witness = Witness::from_slice(&[sig]);
+    }
+
+    Ok(tx)
+}
+
+/// 校验 POINTER 指向的输出：必须存在，且不能是 OP_RETURN（否则按规范成为 cenotaph）
+pub fn validate_pointer(pointer: u32, outputs: &[TxOut]) -> Result<(), Box<dyn std::error::Error>> {
+    let output = outputs.get(pointer as usize).ok_or(format!(
+        "Pointer {} out of range ({} outputs)",
+        pointer,
+        outputs.len()
+    ))?;
+    if output.script_pubkey.is_op_return() {
+        return Err(format!("Pointer {} points at the OP_RETURN output", pointer).into());
+    }
+    Ok(())
+}
+
+/// =====================================================
+/// Runestone 输出
+/// =====================================================
+///
+/// 一笔交易只能有一个 runestone（OP_RETURN OP_13）输出；再出现其它 OP_RETURN 输出时，
+/// 钱包和索引器可能认错 runestone，所以构造交易时一并拒绝
+#[derive(Debug, Clone)]
+pub struct RunestoneOutput(TxOut);
+
+impl RunestoneOutput {
+    /// 脚本必须能解析为 runestone
+    pub fn new(script_pubkey: ScriptBuf) -> Result<Self, Box<dyn std::error::Error>> {
+        RunesParser::parse_script_hex(&script_pubkey.to_hex_string())?
+            .ok_or("Runestone script is not a valid runestone")?;
+        Ok(RunestoneOutput(TxOut {
+            value: Amount::ZERO,
+            script_pubkey,
+        }))
+    }
+
+    pub fn tx_out(&self) -> &TxOut {
+        &self.0
+    }
+
+    /// 把 runestone 追加到输出列表末尾；已有 OP_RETURN 输出时报错
+    pub fn attach_to(self, outputs: &mut Vec<TxOut>) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(index) = outputs.iter().position(|o| o.script_pubkey.is_op_return()) {
+            return Err(format!(
+                "Output {} is already an OP_RETURN, cannot attach runestone",
+                index
+            )
+            .into());
+        }
+        outputs.push(self.0);
+        Ok(())
+    }
+}
+
+/// 输出中最多只能有一个 OP_RETURN
+fn ensure_single_op_return(outputs: &[TxOut]) -> Result<(), Box<dyn std::error::Error>> {
+    let count = outputs
+        .iter()
+        .filter(|o| o.script_pubkey.is_op_return())
+        .count();
+    if count > 1 {
+        return Err(format!("Transaction has {} OP_RETURN outputs, expected 1", count).into());
+    }
+    Ok(())
+}
+
+/// 解码输出中的 runestone，如果设置了 POINTER 就按最终的输出列表校验
+fn validate_runestone_pointer(outputs: &[TxOut]) -> Result<(), Box<dyn std::error::Error>> {
+    ensure_single_op_return(outputs)?;
+    let runestone = &outputs
+        .iter()
+        .find(|o| o.script_pubkey.is_op_return())
+        .ok_or("Missing runestone output")?
+        .script_pubkey;
+    let decoded = RunesParser::parse_script_hex(&runestone.to_hex_string())?
+        .ok_or("Runestone script is not a valid runestone")?
+        .decoded()?;
+    if let Some(pointer) = decoded.pointer {
+        validate_pointer(pointer, outputs)?;
+    }
+    Ok(())
+}
+
+/// premine 不会丢失、但分配方式可能不是调用方本意的情况
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PremineWarning {
+    /// 没有 edict 也没有 POINTER，premine 全部进入第一个非 OP_RETURN 输出
+    ImplicitDefaultOutput { premine: u128, output: usize },
+}
+
+impl fmt::Display for PremineWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PremineWarning::ImplicitDefaultOutput { premine, output } => write!(
+                f,
+                "premine {} has no edict or pointer, it goes to output {}",
+                premine, output
+            ),
+        }
+    }
+}
+
+/// 校验 etching 的 premine 最终落在真实输出上，而不是被烧掉：
+///   - edict（rune id 0:0）不能指向 OP_RETURN 输出
+///   - edict 分配剩下的部分进入 POINTER 指向的输出，没有 POINTER 时进入第一个非 OP_RETURN 输出；
+///     两者都没有时 premine 没有去处
+///
+/// 没有 edict 也没有 POINTER 时不报错（premine 按规范进入第一个非 OP_RETURN 输出），
+/// 返回 PremineWarning 由调用方决定如何提示
+pub fn validate_premine_allocation(
+    outputs: &[TxOut],
+) -> Result<Option<PremineWarning>, Box<dyn std::error::Error>> {
+    let runestone = &outputs
+        .iter()
+        .find(|o| o.script_pubkey.is_op_return())
+        .ok_or("Missing runestone output")?
+        .script_pubkey;
+    let decoded = RunesParser::parse_script_hex(&runestone.to_hex_string())?
+        .ok_or("Runestone script is not a valid runestone")?
+        .decoded()?;
+    let premine = decoded
+        .etching
+        .as_ref()
+        .and_then(|etching| etching.premine)
+        .unwrap_or(0);
+    if premine == 0 {
+        return Ok(None);
+    }
+    if decoded.cenotaph {
+        return Err(format!(
+            "premine {} would be burned: runestone is a cenotaph",
+            premine
+        )
+        .into());
+    }
+
+    for edict in decoded.edicts.iter().filter(|e| e.id == RuneId::ETCHED) {
+        if let Some(output) = outputs.get(edict.output as usize)
+            && output.script_pubkey.is_op_return()
+        {
+            return Err(format!(
+                "premine edict sends {} to the OP_RETURN output {}, it would be burned",
+                edict.amount, edict.output
+            )
+            .into());
+        }
+    }
+
+    let default_output = outputs.iter().position(|o| !o.script_pubkey.is_op_return());
+    match (decoded.pointer, default_output) {
+        (Some(_), _) => Ok(None),
+        (None, Some(output)) if decoded.edicts.is_empty() => {
+            Ok(Some(PremineWarning::ImplicitDefaultOutput {
+                premine,
+                output,
+            }))
+        }
+        (None, Some(_)) => Ok(None),
+        (None, None) => Err(format!(
+            "premine {} has no destination: no edict, pointer or non-OP_RETURN output",
+            premine
+        )
+        .into()),
+    }
+}
+
+// 标准性检查用到的上限（Bitcoin Core policy 默认值）
+pub const MAX_OP_RETURN_RELAY: usize = 83;
+pub const MAX_STANDARD_WITNESS_ITEM_SIZE: usize = 80;
+pub const MAX_STANDARD_P2WSH_SCRIPT_SIZE: usize = 3_600;
+pub const MAX_STANDARD_P2WSH_STACK_ITEMS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NonStandardReason {
+    /// version 不是 1 或 2
+    Version(i32),
+    /// prevouts 数量与 input 数量不一致，无法判断 witness 类型
+    PrevoutCountMismatch { inputs: usize, prevouts: usize },
+    /// 输出脚本不是标准类型
+    NonStandardOutput(usize),
+    /// OP_RETURN 脚本超过 83 字节
+    OpReturnTooLarge { output: usize, size: usize },
+    /// 超过一个 OP_RETURN 输出
+    MultipleOpReturn(usize),
+    /// witness 栈元素过大（P2WSH 脚本、tapscript 栈元素）或元素过多
+    OversizedWitness { input: usize, size: usize },
+    /// taproot witness 带了 annex
+    Annex(usize),
+}
+
+impl fmt::Display for NonStandardReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NonStandardReason::Version(version) => {
+                write!(f, "Transaction version {} is not standard", version)
+            }
+            NonStandardReason::PrevoutCountMismatch { inputs, prevouts } => write!(
+                f,
+                "Transaction has {} inputs but {} prevouts",
+                inputs, prevouts
+            ),
+            NonStandardReason::NonStandardOutput(output) => {
+                write!(f, "Output {} has a non-standard script", output)
+            }
+            NonStandardReason::OpReturnTooLarge { output, size } => write!(
+                f,
+                "OP_RETURN output {} is {} bytes, max {}",
+                output, size, MAX_OP_RETURN_RELAY
+            ),
+            NonStandardReason::MultipleOpReturn(count) => {
+                write!(f, "Transaction has {} OP_RETURN outputs, max 1", count)
+            }
+            NonStandardReason::OversizedWitness { input, size } => {
+                write!(
+                    f,
+                    "Input {} witness has an oversized element ({})",
+                    input, size
+                )
+            }
+            NonStandardReason::Annex(input) => write!(f, "Input {} witness has an annex", input),
+        }
+    }
+}
+
+impl std::error::Error for NonStandardReason {}
+
+fn is_standard_script(script: &ScriptBuf) -> bool {
+    script.is_p2pkh()
+        || script.is_p2sh()
+        || script.is_p2pk()
+        || script.is_witness_program()
+        || script.is_multisig()
+}
+
+/// =====================================================
+/// 标准性（standardness）检查
+/// =====================================================
+///
+/// 共识合法但不标准的交易节点不转发，Alchemy 会直接拒绝；广播前在本地检查常见的几条：
+///   - version 为 1 或 2
+///   - 输出是标准脚本类型，OP_RETURN 最多一个，且脚本不超过 83 字节（80 字节数据）
+///   - P2WSH / tapscript 的 witness 栈元素不超过 80 字节，不带 annex
+///
+/// 输出脚本类型由 prevouts 决定 witness 的检查规则；非 P2WSH / P2TR 的 input 不检查 witness
+pub fn is_standard_tx(tx: &Transaction, prevouts: &[TxOut]) -> Result<(), NonStandardReason> {
+    if !matches!(tx.version, Version::ONE | Version::TWO) {
+        return Err(NonStandardReason::Version(tx.version.0));
+    }
+    if tx.input.len() != prevouts.len() {
+        return Err(NonStandardReason::PrevoutCountMismatch {
+            inputs: tx.input.len(),
+            prevouts: prevouts.len(),
+        });
+    }
+
+    let mut op_returns = 0;
+    for (index, output) in tx.output.iter().enumerate() {
+        let script = &output.script_pubkey;
+        if script.is_op_return() {
+            op_returns += 1;
+            if script.len() > MAX_OP_RETURN_RELAY {
+                return Err(NonStandardReason::OpReturnTooLarge {
+                    output: index,
+                    size: script.len(),
+                });
+            }
+        } else if !is_standard_script(script) {
+            return Err(NonStandardReason::NonStandardOutput(index));
+        }
+    }
+    if op_returns > 1 {
+        return Err(NonStandardReason::MultipleOpReturn(op_returns));
+    }
+
+    for (index, (input, prevout)) in tx.input.iter().zip(prevouts).enumerate() {
+        let oversized = |size: usize| NonStandardReason::OversizedWitness { input: index, size };
+        let witness = &input.witness;
+
+        if prevout.script_pubkey.is_p2wsh() {
+            let Some(script) = witness.last() else {
+                continue;
+            };
+            if script.len() > MAX_STANDARD_P2WSH_SCRIPT_SIZE {
+                return Err(oversized(script.len()));
+            }
+            let stack = witness.len() - 1;
+            if stack > MAX_STANDARD_P2WSH_STACK_ITEMS {
+                return Err(oversized(stack));
+            }
+            if let Some(item) = witness
+                .iter()
+                .take(stack)
+                .find(|item| item.len() > MAX_STANDARD_WITNESS_ITEM_SIZE)
+            {
+                return Err(oversized(item.len()));
+            }
+        } else if prevout.script_pubkey.is_p2tr() {
+            if witness.taproot_annex().is_some() {
+                return Err(NonStandardReason::Annex(index));
+            }
+            // script-path：去掉 tapscript 和 control block 后的栈元素；key-path 只有一个签名
+            if witness.len() >= 2
+                && let Some(item) = witness
+                    .iter()
+                    .take(witness.len() - 2)
+                    .find(|item| item.len() > MAX_STANDARD_WITNESS_ITEM_SIZE)
+            {
+                return Err(oversized(item.len()));
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// 离线验证：检查某个 input 的 Taproot witness 签名是否能花费对应的 prevout。
 ///
 /// 注意：这里要求 `prevouts.len() == tx.input.len()`，因为 Taproot sighash 会承诺所有 prevouts。
@@ -100,9 +825,19 @@ pub fn create_first_tx(
     utxo: AlchemyTxOut,
     destination: &Address,
     tweaked_keypair: &TweakedKeypair,
+    fee_rate: FeeRate,
 ) -> Result<Transaction, Box<dyn std::error::Error>> {
     let commit_value: u64 = 10_000; // 10_000 sats = 0.0001 BTC
-    let fee: u64 = 200; // 100 sats = 0.000001 BTC
+    // 按 1 个 key-path input + [commit, change] 两个输出估算手续费
+    let fee = estimate_fee(
+        &[SpendKind::KeyPath],
+        &[
+            size_only_output(destination.script_pubkey()),
+            size_only_output(destination.script_pubkey()),
+        ],
+        fee_rate,
+    )?
+    .to_sat();
 
     if utxo.value < commit_value + fee {
         return Err("UTXO value not enough".into());
@@ -118,7 +853,7 @@ pub fn create_first_tx(
     // 1️⃣ Input
     let txin = TxIn {
         previous_output: OutPoint {
-            txid: utxo.txid.parse()?,
+            txid: utxo.txid,
             vout: utxo.vout,
         },
         script_sig: ScriptBuf::new(),
@@ -131,17 +866,19 @@ pub fn create_first_tx(
         value: Amount::from_sat(commit_value),
         script_pubkey: destination.script_pubkey(),
     };
+    ensure_not_dust(&commit_output)?;
 
-    let change_output = TxOut {
-        value: Amount::from_sat(change_value),
-        script_pubkey: destination.script_pubkey(),
-    };
+    let mut outputs = vec![commit_output];
+    outputs.extend(change_output_or_fold(
+        change_value,
+        destination.script_pubkey(),
+    ));
 
     let mut tx = Transaction {
         version: Version::TWO,
         lock_time: bitcoin::absolute::LockTime::ZERO,
         input: vec![txin],
-        output: vec![commit_output, change_output],
+        output: outputs,
     };
 
     // 3️⃣ Taproot key-path sighash
@@ -149,10 +886,7 @@ pub fn create_first_tx(
 
     let sighash = sighash_cache.taproot_key_spend_signature_hash(
         0,
-        &Prevouts::All(&[TxOut {
-            value: Amount::from_sat(utxo.value),
-            script_pubkey: ScriptBuf::from_hex(&utxo.script_pubkey.hex)?,
-        }]),
+        &Prevouts::All(&[taproot_prevout(&utxo)?]),
         TapSighashType::Default,
     )?;
 
@@ -168,145 +902,696 @@ pub fn create_first_tx(
     Ok(tx)
 }
 
-pub fn create_commit_tx(
+/// 批量转账：每个 recipient 一个输出，剩余找零到 change_address（低于 dust 时并入手续费），
+/// 所有 input 用 tweaked_keypair 做 key-path 签名
+pub fn create_multi_send_tx(
     secp: &Secp256k1<bitcoin::secp256k1::All>,
+    utxos: &[AlchemyTxOut],
+    recipients: &[(Address, Amount)],
+    change_address: &Address,
+    fee_rate: FeeRate,
+    tweaked_keypair: &TweakedKeypair,
+) -> Result<Transaction, Box<dyn std::error::Error>> {
+    if utxos.is_empty() {
+        return Err("No UTXOs to spend".into());
+    }
+    if recipients.is_empty() {
+        return Err("No recipients".into());
+    }
 
-    // 用来“出钱”的普通 UTXO（funding utxo）
-    funding_utxo: AlchemyTxOut,
+    let mut outputs = Vec::with_capacity(recipients.len() + 1);
+    for (address, value) in recipients {
+        let output = TxOut {
+            value: *value,
+            script_pubkey: address.script_pubkey(),
+        };
+        ensure_not_dust(&output)?;
+        outputs.push(output);
+    }
 
-    taproot_wallet: &TaprootWallet,
-) -> Result<(Transaction, TaprootSpendInfo), Box<dyn std::error::Error>> {
-    // ---------------- 参数 ----------------
-    let commit_value: u64 = 10_000;
-    let fee: u64 = 200; // 给足 fee，避免 mempool 拒绝
+    // 按带找零的输出估算手续费
+    let change_script = change_address.script_pubkey();
+    let mut size_outputs = outputs.clone();
+    size_outputs.push(size_only_output(change_script.clone()));
+    let fee = estimate_fee(
+        &vec![SpendKind::KeyPath; utxos.len()],
+        &size_outputs,
+        fee_rate,
+    )?
+    .to_sat();
+
+    let total_in: u64 = utxos.iter().map(|u| u.value).sum();
+    let total_out: u64 = outputs.iter().map(|o| o.value.to_sat()).sum();
+    if total_in < total_out + fee {
+        return Err(format!(
+            "UTXO value not enough: inputs {} sat < outputs {} sat + fee {} sat",
+            total_in, total_out, fee
+        )
+        .into());
+    }
 
-    if funding_utxo.value < commit_value + fee {
-        return Err("funding utxo not enough".into());
+    println!("  💰 Input Value: {} sat", total_in);
+    println!("  💰 Recipients: {} ({} sat)", recipients.len(), total_out);
+    println!("  💰 Fee: {} sat", fee);
+
+    outputs.extend(change_output_or_fold(
+        total_in - total_out - fee,
+        change_script,
+    ));
+
+    let mut inputs = Vec::with_capacity(utxos.len());
+    let mut prevouts = Vec::with_capacity(utxos.len());
+    for utxo in utxos {
+        inputs.push(TxIn {
+            previous_output: OutPoint {
+                txid: utxo.txid,
+                vout: utxo.vout,
+            },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::default(),
+        });
+        prevouts.push(taproot_prevout(utxo)?);
     }
 
-    let change_value = funding_utxo.value - commit_value - fee;
+    let mut tx = Transaction {
+        version: Version::TWO,
+        lock_time: bitcoin::absolute::LockTime::ZERO,
+        input: inputs,
+        output: outputs,
+    };
 
-    // ---------------- 1️⃣ 构造 Taproot script tree（核心） ----------------
-    let inscription_script = build_inscription_script(taproot_wallet.internal_xonly());
+    let keypair = tweaked_keypair.to_keypair();
+    sign_keypath_inputs_with(&mut tx, &prevouts, |msg, _| {
+        Ok(secp.sign_schnorr(msg, &keypair))
+    })?;
 
-    let taproot_spend_info: TaprootSpendInfo = TaprootBuilder::new()
-        .add_leaf(0, inscription_script.clone())?
-        .finalize(secp, taproot_wallet.internal_xonly())
-        .unwrap();
+    Ok(tx)
+}
 
-    // ---------------- 2️⃣ 构造 commit 地址（承诺脚本树） ----------------
-    // 地址 ≈ script_pubkey 的人类编码
-    let commit_address =
-        taproot_wallet.get_commit_address_with_script_tree(secp, &taproot_spend_info);
+/// 构造普通转账交易：向 destination 支付 amount，剩余找零回钱包地址
+pub fn create_payment_tx(
+    secp: &Secp256k1<bitcoin::secp256k1::All>,
+    utxo: AlchemyTxOut,
+    destination: &Address,
+    amount: Amount,
+    taproot_wallet: &TaprootWallet,
+    fee_rate: FeeRate,
+) -> Result<Transaction, Box<dyn std::error::Error>> {
+    ensure_address_network(destination, taproot_wallet.network())?;
+
+    let change_script = taproot_wallet.get_internal_address().script_pubkey();
+    let fee = estimate_fee(
+        &[SpendKind::KeyPath],
+        &[
+            size_only_output(destination.script_pubkey()),
+            size_only_output(change_script.clone()),
+        ],
+        fee_rate,
+    )?
+    .to_sat();
+
+    if utxo.value < amount.to_sat() + fee {
+        return Err("UTXO value not enough".into());
+    }
 
-    println!("  📍 Commit Address: {}", commit_address.to_string());
+    let change_value = utxo.value - amount.to_sat() - fee;
 
-    // ---------------- 3️⃣ 构造交易 input（花费 funding utxo） ----------------
-    let txin = TxIn {
-        previous_output: OutPoint {
-            txid: funding_utxo.txid.parse()?,
-            vout: funding_utxo.vout,
-        },
-        script_sig: ScriptBuf::new(),
-        sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
-        witness: Witness::default(),
-    };
+    println!("  💰 UTXO Value: {} sat", utxo.value);
+    println!("  💰 Amount: {} sat", amount.to_sat());
+    println!("  💰 Fee: {} sat", fee);
 
-    // ---------------- 4️⃣ 构造交易 outputs ----------------
-    // ① commit output：承诺 script tree 的 P2TR UTXO
-    let commit_output = TxOut {
-        value: Amount::from_sat(commit_value),
-        script_pubkey: commit_address.script_pubkey(),
+    let payment_output = TxOut {
+        value: amount,
+        script_pubkey: destination.script_pubkey(),
     };
+    ensure_not_dust(&payment_output)?;
 
-    // ② 找零（通常回到普通钱包地址，这里示例用同一个 internal key）
-    let change_address = taproot_wallet.get_internal_address();
+    let mut outputs = vec![payment_output];
+    outputs.extend(change_output_or_fold(change_value, change_script));
 
-    println!("  📍 Change Address: {}", change_address.to_string());
-
-    let change_output = TxOut {
-        value: Amount::from_sat(change_value),
-        script_pubkey: change_address.script_pubkey(),
-    };
+    let prevout = taproot_prevout(&utxo)?;
 
     let mut tx = Transaction {
         version: Version::TWO,
         lock_time: bitcoin::absolute::LockTime::ZERO,
-        input: vec![txin],
-        output: vec![commit_output, change_output],
+        input: vec![TxIn {
+            previous_output: OutPoint {
+                txid: utxo.txid,
+                vout: utxo.vout,
+            },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::default(),
+        }],
+        output: outputs,
     };
-    // 虽然这里用的是跟创建钱包时同样的 internal key 以及同样的规则，但是还是会生成一个新的地址
-    // 是可以被同一个私钥控制的，但是地址是不同的，有利于隐私保护
 
-    // ---------------- 5️⃣ key-path sighash（不是 script-path） ----------------
-    let mut sighash_cache = SighashCache::new(&mut tx);
+    sign_keypath_inputs(secp, &mut tx, &[prevout], taproot_wallet)?;
 
-    let sighash = sighash_cache.taproot_key_spend_signature_hash(
-        0,
-        &Prevouts::All(&[TxOut {
-            value: Amount::from_sat(funding_utxo.value),
-            script_pubkey: ScriptBuf::from_hex(&funding_utxo.script_pubkey.hex)?,
-        }]),
-        TapSighashType::Default,
-    )?;
+    Ok(tx)
+}
 
-    // ---------------- 6️⃣ Schnorr 签名（internal key） ----------------
-    let sig = taproot_wallet.sign_keypath(
+/// 清空钱包：花费所有 utxos，扣除手续费后全部转到 destination（没有找零输出）
+///
+/// 手续费按 utxos.len() 个 key-path input + 1 个输出的实际 vsize 计算，
+/// 输出金额恰好为 total - fee；总额不够付手续费或剩余低于 dust 时报错
+pub fn create_sweep_tx(
+    secp: &Secp256k1<bitcoin::secp256k1::All>,
+    utxos: &[AlchemyTxOut],
+    destination: &Address,
+    taproot_wallet: &TaprootWallet,
+    fee_rate: FeeRate,
+) -> Result<Transaction, Box<dyn std::error::Error>> {
+    ensure_address_network(destination, taproot_wallet.network())?;
+    if utxos.is_empty() {
+        return Err("nothing to sweep: no spendable UTXO".into());
+    }
+
+    let fee = estimate_fee(
+        &vec![SpendKind::KeyPath; utxos.len()],
+        &[size_only_output(destination.script_pubkey())],
+        fee_rate,
+    )?
+    .to_sat();
+    let total_in: u64 = utxos.iter().map(|u| u.value).sum();
+    if total_in <= fee {
+        return Err(format!(
+            "nothing to sweep: inputs {} sat <= fee {} sat",
+            total_in, fee
+        )
+        .into());
+    }
+
+    println!("  💰 Input Value: {} sat ({} UTXOs)", total_in, utxos.len());
+    println!("  💰 Fee: {} sat", fee);
+
+    let output = TxOut {
+        value: Amount::from_sat(total_in - fee),
+        script_pubkey: destination.script_pubkey(),
+    };
+    ensure_not_dust(&output)?;
+
+    let mut inputs = Vec::with_capacity(utxos.len());
+    let mut prevouts = Vec::with_capacity(utxos.len());
+    for utxo in utxos {
+        inputs.push(TxIn {
+            previous_output: OutPoint {
+                txid: utxo.txid,
+                vout: utxo.vout,
+            },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::default(),
+        });
+        prevouts.push(taproot_prevout(utxo)?);
+    }
+
+    let mut tx = Transaction {
+        version: Version::TWO,
+        lock_time: bitcoin::absolute::LockTime::ZERO,
+        input: inputs,
+        output: vec![output],
+    };
+
+    sign_keypath_inputs(secp, &mut tx, &prevouts, taproot_wallet)?;
+
+    Ok(tx)
+}
+
+/// =====================================================
+/// 带 script tree 的 taproot 输出走 key-path 花费
+/// =====================================================
+///
+/// output key = internal key + tagged_hash(internal key || merkle_root)·G，
+/// 所以签名前必须用同一个 merkle_root 做 tap_tweak，用 None tweak 出来的 key 签名是无效的。
+/// 典型场景：inscription commit 输出不想 reveal 了，直接用 key-path 把钱转走
+/// （不暴露 inscription 脚本）。
+///
+/// 整个 UTXO 扣掉手续费后转给 destination；merkle_root 与 UTXO 的地址对不上时报错
+pub fn create_keypath_spend_with_tree(
+    secp: &Secp256k1<bitcoin::secp256k1::All>,
+    utxo: AlchemyTxOut,
+    merkle_root: TapNodeHash,
+    internal_keypair: &Keypair,
+    destination: &Address,
+    fee_rate: FeeRate,
+) -> Result<Transaction, Box<dyn std::error::Error>> {
+    let prevout = taproot_prevout(&utxo)?;
+
+    let expected_script = ScriptBuf::new_p2tr(
         secp,
-        &bitcoin::secp256k1::Message::from_slice(sighash.as_ref())?,
+        internal_keypair.x_only_public_key().0,
+        Some(merkle_root),
     );
+    if prevout.script_pubkey != expected_script {
+        return Err(format!(
+            "UTXO {}:{} is not controlled by this internal key and merkle root",
+            utxo.txid, utxo.vout
+        )
+        .into());
+    }
 
-    tx.input[0].witness.push(sig.as_ref().to_vec());
+    let fee = estimate_fee(
+        &[SpendKind::KeyPath],
+        &[size_only_output(destination.script_pubkey())],
+        fee_rate,
+    )?;
+    let output = TxOut {
+        value: prevout
+            .value
+            .checked_sub(fee)
+            .ok_or("UTXO value not enough")?,
+        script_pubkey: destination.script_pubkey(),
+    };
+    ensure_not_dust(&output)?;
 
-    // ---------------- 返回 ----------------
-    // 要把 taproot_spend_info 返回，reveal tx 需要它拿 control_block
-    Ok((tx, taproot_spend_info))
+    let mut tx = Transaction {
+        version: Version::TWO,
+        lock_time: bitcoin::absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint {
+                txid: utxo.txid,
+                vout: utxo.vout,
+            },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::default(),
+        }],
+        output: vec![output],
+    };
+
+    let keypair = internal_keypair
+        .tap_tweak(secp, Some(merkle_root))
+        .to_keypair();
+    sign_keypath_inputs_with(&mut tx, &[prevout], |msg, _| {
+        Ok(secp.sign_schnorr(msg, &keypair))
+    })?;
+
+    Ok(tx)
 }
 
-pub fn create_brc20_transaction(
+/// inscription reveal 输出的默认金额（postage）
+pub const DEFAULT_POSTAGE: Amount = Amount::from_sat(10_000);
+
+/// =====================================================
+/// leaf version
+/// =====================================================
+///
+/// BIP341 的 leaf version 是 control block 首字节去掉最低位（output key parity）后的值，
+/// 所以只能是偶数；0xc0 是目前唯一有语义的 tapscript，其余偶数值留给未来的软分叉。
+/// 0x50 也不行：它和 annex 的前缀冲突
+pub fn leaf_version_from_byte(version: u8) -> Result<LeafVersion, Box<dyn std::error::Error>> {
+    if version & 1 != 0 {
+        return Err(format!(
+            "invalid leaf version 0x{:02x}: BIP341 leaf versions must be even",
+            version
+        )
+        .into());
+    }
+    LeafVersion::from_consensus(version)
+        .map_err(|e| format!("invalid leaf version 0x{:02x}: {}", version, e).into())
+}
+
+/// script tree 无法 finalize：叶子没有组成完整的二叉树（例如只在 depth 1 加了一个叶子）。
+/// 原样带回 builder，方便查看已经加入的叶子后补全
+#[derive(Debug)]
+pub struct IncompleteTreeError {
+    pub builder: TaprootBuilder,
+}
+
+impl fmt::Display for IncompleteTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "tree not finalizable: leaves do not form a complete binary tree"
+        )
+    }
+}
+
+impl std::error::Error for IncompleteTreeError {}
+
+/// finalize script tree，失败时返回带 builder 的 IncompleteTreeError 而不是 panic
+pub fn finalize_script_tree(
+    secp: &Secp256k1<bitcoin::secp256k1::All>,
+    builder: TaprootBuilder,
+    internal_key: XOnlyPublicKey,
+) -> Result<TaprootSpendInfo, IncompleteTreeError> {
+    builder
+        .finalize(secp, internal_key)
+        .map_err(|builder| IncompleteTreeError { builder })
+}
+
+/// 只有一个叶子的 script tree，叶子使用指定的 leaf version
+pub fn single_leaf_spend_info(
+    secp: &Secp256k1<bitcoin::secp256k1::All>,
+    internal_key: XOnlyPublicKey,
+    leaf_script: &ScriptBuf,
+    leaf_version: LeafVersion,
+) -> Result<TaprootSpendInfo, Box<dyn std::error::Error>> {
+    let builder = TaprootBuilder::new().add_leaf_with_ver(0, leaf_script.clone(), leaf_version)?;
+    Ok(finalize_script_tree(secp, builder, internal_key)?)
+}
+
+/// 叶子的 control block；首字节 = leaf version | output key parity
+pub fn leaf_control_block(
+    spend_info: &TaprootSpendInfo,
+    leaf_script: &ScriptBuf,
+    leaf_version: LeafVersion,
+) -> Result<ControlBlock, Box<dyn std::error::Error>> {
+    spend_info
+        .control_block(&(leaf_script.clone(), leaf_version))
+        .ok_or_else(|| "leaf script not found in script tree".into())
+}
+
+/// 解码后的 control block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedControlBlock {
+    pub leaf_version: LeafVersion,
+    /// output key 的 parity（首字节最低位）
+    pub parity: Parity,
+    pub internal_key: XOnlyPublicKey,
+    /// 从叶子到 root 的兄弟节点哈希
+    pub merkle_path: Vec<[u8; 32]>,
+}
+
+/// 解码 control block：[leaf version | parity](1) + internal key(32) + merkle path(32 * m)
+///
+/// 用于检查 reveal witness 是否对应预期的 output key，以及排查格式错误的 witness
+pub fn parse_control_block(bytes: &[u8]) -> Result<ParsedControlBlock, Box<dyn std::error::Error>> {
+    if bytes.len() < TAPROOT_CONTROL_BASE_SIZE {
+        return Err(format!(
+            "control block too short: {} bytes (min {})",
+            bytes.len(),
+            TAPROOT_CONTROL_BASE_SIZE
+        )
+        .into());
+    }
+    let path = &bytes[TAPROOT_CONTROL_BASE_SIZE..];
+    if !path.len().is_multiple_of(TAPROOT_CONTROL_NODE_SIZE) {
+        return Err(format!(
+            "truncated merkle path: {} bytes is not a multiple of {}",
+            path.len(),
+            TAPROOT_CONTROL_NODE_SIZE
+        )
+        .into());
+    }
+    if path.len() / TAPROOT_CONTROL_NODE_SIZE > TAPROOT_CONTROL_MAX_NODE_COUNT {
+        return Err(format!(
+            "merkle path too long: {} nodes (max {})",
+            path.len() / TAPROOT_CONTROL_NODE_SIZE,
+            TAPROOT_CONTROL_MAX_NODE_COUNT
+        )
+        .into());
+    }
+
+    Ok(ParsedControlBlock {
+        leaf_version: leaf_version_from_byte(bytes[0] & TAPROOT_LEAF_MASK)?,
+        parity: Parity::from_u8(bytes[0] & 1)?,
+        internal_key: XOnlyPublicKey::from_slice(&bytes[1..TAPROOT_CONTROL_BASE_SIZE])?,
+        merkle_path: path
+            .chunks_exact(TAPROOT_CONTROL_NODE_SIZE)
+            .map(|node| node.try_into().expect("chunk is 32 bytes"))
+            .collect(),
+    })
+}
+
+/// =====================================================
+/// 校验 reveal witness 是否承诺到被花费的输出
+/// =====================================================
+///
+/// witness = [..., 叶子脚本, control block]（末尾可带 annex）。用叶子脚本的 leaf hash
+/// 和 control block 里的 merkle path 算出 merkle root，tweak internal key，
+/// 检查结果是否等于 output_script（P2TR）里的 output key。
+/// 伪造或张冠李戴的 reveal（脚本被替换、control block 来自别的树）都会返回 false
+pub fn verify_reveal_commitment(witness: &Witness, output_script: &ScriptBuf) -> bool {
+    if !output_script.is_p2tr() {
+        return false;
+    }
+    let Ok(output_key) = XOnlyPublicKey::from_slice(&output_script.as_bytes()[2..34]) else {
+        return false;
+    };
+
+    // BIP341：最后一个元素以 0x50 开头时是 annex，不参与 script-path
+    let mut items: Vec<&[u8]> = witness.iter().collect();
+    if items.len() >= 2 && items.last().is_some_and(|last| last.first() == Some(&0x50)) {
+        items.pop();
+    }
+    let [.., script, control_block] = items[..] else {
+        return false;
+    };
+    let Ok(control_block) = ControlBlock::decode(control_block) else {
+        return false;
+    };
+
+    control_block.verify_taproot_commitment(
+        &Secp256k1::verification_only(),
+        output_key,
+        bitcoin::Script::from_bytes(script),
+    )
+}
+
+/// 只有一个 inscription 叶子的 script tree
+fn inscription_spend_info(
     secp: &Secp256k1<bitcoin::secp256k1::All>,
-    utxo: AlchemyTxOut,
     taproot_wallet: &TaprootWallet,
-) -> Result<Transaction, Box<dyn std::error::Error>> {
-    // ---------- 构造 commit value ----------
-    let commit_value: u64 = 9_800; // 9_800 sats = 0.000098 BTC
-    let fee: u64 = 200; // 100 sats = 0.000001 BTC
+    inscription_script: &ScriptBuf,
+) -> Result<TaprootSpendInfo, Box<dyn std::error::Error>> {
+    single_leaf_spend_info(
+        secp,
+        taproot_wallet.internal_xonly(),
+        inscription_script,
+        LeafVersion::TapScript,
+    )
+}
 
-    if utxo.value < commit_value + fee {
-        return Err("UTXO value not enough".into());
+/// reveal input 的花费方式：witness = [签名, inscription script, control block]
+fn inscription_reveal_spend(
+    taproot_spend_info: &TaprootSpendInfo,
+    inscription_script: &ScriptBuf,
+) -> Result<SpendKind, Box<dyn std::error::Error>> {
+    let control_block = leaf_control_block(
+        taproot_spend_info,
+        inscription_script,
+        LeafVersion::TapScript,
+    )?;
+    Ok(SpendKind::ScriptPath {
+        script_len: inscription_script.len(),
+        control_block_len: control_block.size(),
+    })
+}
+
+/// commit 输出至少需要的金额：按 fee_rate 估算的 reveal 手续费 + postage
+/// （reveal 输出回到钱包地址）
+pub fn min_inscription_commit_value(
+    secp: &Secp256k1<bitcoin::secp256k1::All>,
+    taproot_wallet: &TaprootWallet,
+    inscription_script: &ScriptBuf,
+    postage: Amount,
+    fee_rate: FeeRate,
+) -> Result<Amount, Box<dyn std::error::Error>> {
+    let spend_info = inscription_spend_info(secp, taproot_wallet, inscription_script)?;
+    min_commit_value(
+        inscription_reveal_spend(&spend_info, inscription_script)?,
+        &taproot_wallet.get_internal_address().script_pubkey(),
+        postage,
+        fee_rate,
+    )
+}
+
+/// 构造 BRC-20 deploy inscription 的 commit 交易
+#[allow(clippy::too_many_arguments)]
+pub fn create_commit_tx(
+    secp: &Secp256k1<bitcoin::secp256k1::All>,
+
+    // 用来“出钱”的普通 UTXO（funding utxo）
+    funding_utxo: AlchemyTxOut,
+
+    taproot_wallet: &TaprootWallet,
+    commit_value: Amount,
+    fee_rate: FeeRate,
+    fee_mode: FeeMode,
+    sighash_type: TapSighashType,
+    change_policy: &ChangePolicy,
+    shuffle_rng: Option<&mut dyn RngCore>,
+) -> Result<(Transaction, TaprootSpendInfo), Box<dyn std::error::Error>> {
+    let inscription_script = build_inscription_script(
+        taproot_wallet.internal_xonly(),
+        TEXT_PLAIN_UTF8,
+        &brc20_deploy_body(),
+    );
+
+    create_inscription_commit_tx(
+        secp,
+        funding_utxo,
+        taproot_wallet,
+        &inscription_script,
+        commit_value,
+        fee_rate,
+        fee_mode,
+        sighash_type,
+        change_policy,
+        shuffle_rng,
+    )
+}
+
+/// 构造任意 inscription 的 commit 交易：
+/// - output 0：承诺 inscription script tree 的 P2TR 输出（reveal 交易花费它）
+/// - output 1：找零
+///
+/// 传入 shuffle_rng 时输出顺序随机，reveal 前需按 script_pubkey 找到 commit 输出
+///
+/// commit_value 需要覆盖同一费率下的 reveal 手续费，且 reveal 输出不能是 dust，
+/// 否则直接报错，避免广播一个无法 reveal 的 commit
+///
+/// sighash_type 为 NONE / SINGLE 时，其他人可以在签名后继续追加输出
+#[allow(clippy::too_many_arguments)]
+pub fn create_inscription_commit_tx(
+    secp: &Secp256k1<bitcoin::secp256k1::All>,
+    funding_utxo: AlchemyTxOut,
+    taproot_wallet: &TaprootWallet,
+    inscription_script: &ScriptBuf,
+    commit_value: Amount,
+    fee_rate: FeeRate,
+    fee_mode: FeeMode,
+    sighash_type: TapSighashType,
+    change_policy: &ChangePolicy,
+    shuffle_rng: Option<&mut dyn RngCore>,
+) -> Result<(Transaction, TaprootSpendInfo), Box<dyn std::error::Error>> {
+    // ---------------- 1️⃣ 构造 Taproot script tree（核心） ----------------
+    let taproot_spend_info = inscription_spend_info(secp, taproot_wallet, inscription_script)?;
+
+    let reveal_output = taproot_wallet.get_internal_address().script_pubkey();
+    let min_value = min_commit_value(
+        inscription_reveal_spend(&taproot_spend_info, inscription_script)?,
+        &reveal_output,
+        dust_limit(&reveal_output),
+        fee_rate,
+    )?;
+    if commit_value < min_value {
+        return Err(format!(
+            "commit value {} sat cannot cover the reveal (minimum {} sat)",
+            commit_value.to_sat(),
+            min_value.to_sat()
+        )
+        .into());
     }
+    let commit_value = commit_value.to_sat();
 
-    let change_value = utxo.value - commit_value - fee; // 给自己的找零
+    // ---------------- 2️⃣ 构造 commit 地址（承诺脚本树） ----------------
+    // 地址 ≈ script_pubkey 的人类编码
+    let commit_address =
+        taproot_wallet.get_commit_address_with_script_tree(secp, &taproot_spend_info);
 
-    println!("  💰 UTXO Value: {} sat", utxo.value);
-    println!("  💰 Commit Value: {} sat", commit_value);
-    println!("  💰 Fee: {} sat", fee);
-    println!("  💰 Change Value: {} sat", change_value);
+    println!("  📍 Commit Address: {}", commit_address.to_string());
 
-    let input = TxIn {
+    // 按 1 个 key-path input + [commit, change] 两个输出估算手续费
+    // （ChildPays 时只付 relay 下限，reveal 仍按 fee_rate 估算）
+    let (change_address, fold_threshold) = change_policy.resolve(secp, taproot_wallet)?;
+    let fee = estimate_fee(
+        &[SpendKind::KeyPath],
+        &[
+            size_only_output(commit_address.script_pubkey()),
+            size_only_output(change_address.script_pubkey()),
+        ],
+        fee_mode.parent_fee_rate(fee_rate),
+    )?
+    .to_sat();
+
+    if funding_utxo.value < commit_value + fee {
+        return Err("funding utxo not enough".into());
+    }
+
+    let change_value = funding_utxo.value - commit_value - fee;
+
+    // ---------------- 3️⃣ 构造交易 input（花费 funding utxo） ----------------
+    let txin = TxIn {
         previous_output: OutPoint {
-            txid: utxo.txid.parse()?,
-            vout: utxo.vout,
+            txid: funding_utxo.txid,
+            vout: funding_utxo.vout,
         },
         script_sig: ScriptBuf::new(),
         sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
         witness: Witness::default(),
     };
 
-    let output = TxOut {
+    // ---------------- 4️⃣ 构造交易 outputs ----------------
+    // ① commit output：承诺 script tree 的 P2TR UTXO
+    let commit_output = TxOut {
         value: Amount::from_sat(commit_value),
-        script_pubkey: taproot_wallet.get_internal_address().script_pubkey(),
+        script_pubkey: commit_address.script_pubkey(),
     };
+    ensure_not_dust(&commit_output)?;
+
+    // ② 找零：地址和并入手续费的阈值由 change_policy 决定
+    println!("  📍 Change Address: {}", change_address.to_string());
+
+    let mut outputs = vec![commit_output];
+    outputs.extend(change_output_above(
+        change_value,
+        change_address.script_pubkey(),
+        fold_threshold,
+    ));
+    if let Some(rng) = shuffle_rng {
+        shuffle_outputs(&mut outputs, rng)?;
+    }
+    // ChildPays 必须留下找零输出，子交易靠花费它来补手续费
+    if let FeeMode::ChildPays { .. } = fee_mode {
+        let anchor = outputs
+            .iter()
+            .position(|o| o.script_pubkey == change_address.script_pubkey())
+            .ok_or("ChildPays needs a change output as the CPFP anchor, but change was folded")?;
+        println!("  ⚓ CPFP anchor: output {}", anchor);
+    }
 
     let mut tx = Transaction {
         version: Version::TWO,
         lock_time: bitcoin::absolute::LockTime::ZERO,
-        input: vec![input],
-        output: vec![output],
+        input: vec![txin],
+        output: outputs,
+    };
+    // 虽然这里用的是跟创建钱包时同样的 internal key 以及同样的规则，但是还是会生成一个新的地址
+    // 是可以被同一个私钥控制的，但是地址是不同的，有利于隐私保护
+
+    // ---------------- 5️⃣ key-path sighash（不是 script-path） ----------------
+    ensure_sighash_outputs(&tx, 0, sighash_type)?;
+    let mut sighash_cache = SighashCache::new(&mut tx);
+
+    let sighash = sighash_cache.taproot_key_spend_signature_hash(
+        0,
+        &Prevouts::All(&[taproot_prevout(&funding_utxo)?]),
+        sighash_type,
+    )?;
+
+    // ---------------- 6️⃣ Schnorr 签名（internal key） ----------------
+    // 非 DEFAULT 的 sighash 类型需要在签名后追加 1 字节 sighash flag
+    let sig = taproot::Signature {
+        signature: taproot_wallet.sign_keypath(
+            secp,
+            &bitcoin::secp256k1::Message::from_digest(sighash.to_byte_array()),
+        ),
+        sighash_type,
     };
 
+    tx.input[0].witness.push(sig.to_vec());
+
+    // ---------------- 返回 ----------------
+    // 要把 taproot_spend_info 返回，reveal tx 需要它拿 control_block
+    Ok((tx, taproot_spend_info))
+}
+
+pub fn create_brc20_transaction(
+    secp: &Secp256k1<bitcoin::secp256k1::All>,
+    utxo: AlchemyTxOut,
+    taproot_wallet: &TaprootWallet,
+    fee_rate: FeeRate,
+) -> Result<Transaction, Box<dyn std::error::Error>> {
     // ---------- 构造 brc20 data 和 inscription script----------
-    let inscription_script = build_inscription_script(taproot_wallet.internal_xonly());
+    let inscription_script = build_inscription_script(
+        taproot_wallet.internal_xonly(),
+        TEXT_PLAIN_UTF8,
+        &brc20_deploy_body(),
+    );
 
     println!(
         "inscription script hex: {}",
@@ -315,9 +1600,8 @@ pub fn create_brc20_transaction(
 
     // 构造 Taproot script tree
     let taproot_builder = TaprootBuilder::new().add_leaf(0, inscription_script.clone())?;
-    let taproot_info = taproot_builder
-        .finalize(&secp, taproot_wallet.internal_xonly())
-        .unwrap();
+    let taproot_info =
+        finalize_script_tree(secp, taproot_builder, taproot_wallet.internal_xonly())?;
 
     // 获取输出公钥（聚合后的，用于地址）
     // let output_pubkey = taproot_info.output_key().clone();
@@ -341,73 +1625,103 @@ pub fn create_brc20_transaction(
             inscription_script.clone(),
             bitcoin::taproot::LeafVersion::TapScript,
         ))
-        .unwrap();
-
-    let mut sighash_cache = SighashCache::new(&mut tx);
-
-    let prevout = TxOut {
-        value: Amount::from_sat(utxo.value),
-        script_pubkey: ScriptBuf::from_hex(&utxo.script_pubkey.hex)?,
-    };
-
-    let leaf_hash = TapLeafHash::from_script(&inscription_script, LeafVersion::TapScript);
-
-    let sighash = sighash_cache.taproot_script_spend_signature_hash(
-        0, // input index
-        // 签名 prevout 的 (value, scriptPubKey)
-        &Prevouts::All(&[prevout]),
-        leaf_hash,
-        TapSighashType::Default,
-    )?;
-
-    let sig = taproot_wallet.sign_internal(
-        secp,
-        &bitcoin::secp256k1::Message::from_digest_slice(sighash.as_ref())?,
-    );
+        .ok_or("inscription script not found in taproot spend info")?;
 
-    tx.input[0].witness.push(sig.as_ref().to_vec());
-    tx.input[0].witness.push(inscription_script.into_bytes());
-    tx.input[0].witness.push(control_block.serialize());
+    // script-path 只能花费锁定在这棵 script tree 上的输出：control block 证明的是
+    // internal key + merkle root → output key，prevout 的 witness program 必须就是这个 output key，
+    // 花费钱包地址（无 script tree）的 UTXO 会构造出无效交易
+    let tree_script = ScriptBuf::new_p2tr_tweaked(taproot_info.output_key());
+    if utxo.script_pubkey.script != tree_script {
+        return Err(format!(
+            "UTXO {}:{} is not locked to the inscription script tree (expected {})",
+            utxo.txid,
+            utxo.vout,
+            tree_script.to_hex_string()
+        )
+        .into());
+    }
 
-    Ok(tx)
-}
+    // ---------- 构造 commit value ----------
+    // script-path witness = [签名, inscription script, control block]，按它估算手续费
+    let output_script = taproot_wallet.get_internal_address().script_pubkey();
+    let fee = estimate_fee(
+        &[SpendKind::ScriptPath {
+            script_len: inscription_script.len(),
+            control_block_len: control_block.size(),
+        }],
+        &[size_only_output(output_script.clone())],
+        fee_rate,
+    )?
+    .to_sat();
 
-pub fn create_runes_tx(
-    secp: &Secp256k1<bitcoin::secp256k1::All>,
-    utxo: AlchemyTxOut,
-    taproot_wallet: &TaprootWallet,
-) -> Result<Transaction, Box<dyn std::error::Error>> {
-    let fee: u64 = 200;
+    let commit_value: u64 = 9_800; // 9_800 sats = 0.000098 BTC
 
-    if utxo.value < fee {
+    // 没有找零输出，commit_value 之外的部分都是手续费，不能低于估算值
+    if utxo
+        .value
+        .checked_sub(commit_value)
+        .is_none_or(|actual_fee| actual_fee < fee)
+    {
         return Err("UTXO value not enough".into());
     }
 
-    let change_value = utxo.value - fee; // 给自己的找零
+    println!("  💰 UTXO Value: {} sat", utxo.value);
+    println!("  💰 Commit Value: {} sat", commit_value);
+    println!("  💰 Fee: {} sat", utxo.value - commit_value);
 
-    // -------- Input --------
     let input = TxIn {
         previous_output: OutPoint {
-            txid: utxo.txid.parse()?,
+            txid: utxo.txid,
             vout: utxo.vout,
         },
         script_sig: ScriptBuf::new(),
         sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
-        witness: Default::default(),
+        witness: Witness::default(),
     };
 
-    // -------- Output 0: 找零 --------
-    let change_output = TxOut {
-        value: Amount::from_sat(change_value),
-        script_pubkey: taproot_wallet.get_internal_address().script_pubkey(),
+    let output = TxOut {
+        value: Amount::from_sat(commit_value),
+        script_pubkey: output_script,
     };
+    ensure_not_dust(&output)?;
 
-    // -------- Output 1: OP_RETURN (Rune) --------
+    let mut tx = Transaction {
+        version: Version::TWO,
+        lock_time: bitcoin::absolute::LockTime::ZERO,
+        input: vec![input],
+        output: vec![output],
+    };
+
+    // 签名 prevout 的 (value, scriptPubKey)
+    let prevout = taproot_prevout(&utxo)?;
+
+    // 叶子脚本里是 internal key（未 tweak），签名必须用 internal keypair 而不是 tweaked keypair
+    taproot_wallet.sign_script_path(
+        secp,
+        &mut tx,
+        0,
+        &[prevout],
+        &inscription_script,
+        &taproot_info,
+        LeafVersion::TapScript,
+        TapSighashType::Default,
+    )?;
+
+    Ok(tx)
+}
+
+pub fn create_runes_tx(
+    secp: &Secp256k1<bitcoin::secp256k1::All>,
+    utxo: AlchemyTxOut,
+    taproot_wallet: &TaprootWallet,
+    fee_rate: FeeRate,
+) -> Result<Transaction, Box<dyn std::error::Error>> {
+    // -------- Output 1: OP_RETURN (Rune) --------
     let rune_output = TxOut {
         value: Amount::from_sat(0),
         // script_pubkey: build_rune_op_return(),
         script_pubkey: Builder::new()
-            .push_slice(&[
+            .push_slice([
                 0x6a, 0x5d, 0x28, 0x02, 0x07, 0x04, 0xea, 0xda, 0xa9, 0xea, 0x92, 0xe0, 0xaa, 0xca,
                 0xaf, 0x85, 0x01, 0x05, 0xb0, 0x09, 0xc0, 0x10, 0x34, 0x00, 0x10, 0x80, 0x60, 0x80,
                 0x80, 0xb9, 0xf6, 0xcd, 0xbf, 0x5f, 0x08, 0xc0, 0xa0, 0x0a, 0x0a, 0x80, 0xc8, 0xaf,
@@ -416,6 +1730,39 @@ pub fn create_runes_tx(
             .into_script(),
     };
 
+    let change_script = taproot_wallet.get_internal_address().script_pubkey();
+    let fee = estimate_fee(
+        &[SpendKind::KeyPath],
+        &[size_only_output(change_script.clone()), rune_output.clone()],
+        fee_rate,
+    )?
+    .to_sat();
+
+    if utxo.value < fee {
+        return Err("UTXO value not enough".into());
+    }
+
+    let change_value = utxo.value - fee; // 给自己的找零
+
+    // -------- Input --------
+    let input = TxIn {
+        previous_output: OutPoint {
+            txid: utxo.txid,
+            vout: utxo.vout,
+        },
+        script_sig: ScriptBuf::new(),
+        sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+        witness: Default::default(),
+    };
+
+    // -------- Output 0: 找零 --------
+    // runes 交易只有这一个普通输出，不能并入手续费，低于 dust 直接报错
+    let change_output = TxOut {
+        value: Amount::from_sat(change_value),
+        script_pubkey: change_script,
+    };
+    ensure_not_dust(&change_output)?;
+
     let mut tx = Transaction {
         version: Version::TWO,
         lock_time: bitcoin::absolute::LockTime::ZERO,
@@ -436,10 +1783,7 @@ pub fn create_runes_tx(
 
     let sighash = sighash_cache.taproot_key_spend_signature_hash(
         0,
-        &Prevouts::All(&[TxOut {
-            value: Amount::from_sat(utxo.value),
-            script_pubkey: ScriptBuf::from_hex(&utxo.script_pubkey.hex)?,
-        }]),
+        &Prevouts::All(&[taproot_prevout(&utxo)?]),
         TapSighashType::Default,
     )?;
 
@@ -452,3 +1796,1827 @@ pub fn create_runes_tx(
 
     Ok(tx)
 }
+
+/// 构造 rune etching 交易：
+/// - output 0：recipient，未分配的 premine 默认落在第一个非 OP_RETURN 输出上，同时承接找零
+/// - output 1：runestone（OP_RETURN）
+pub fn create_etching_tx(
+    secp: &Secp256k1<bitcoin::secp256k1::All>,
+    utxo: AlchemyTxOut,
+    taproot_wallet: &TaprootWallet,
+    runestone: ScriptBuf,
+    recipient: &Address,
+    fee_rate: FeeRate,
+    shuffle_rng: Option<&mut dyn RngCore>,
+) -> Result<Transaction, Box<dyn std::error::Error>> {
+    ensure_address_network(recipient, taproot_wallet.network())?;
+
+    let runestone_output = RunestoneOutput::new(runestone)?;
+
+    let fee = estimate_fee(
+        &[SpendKind::KeyPath],
+        &[
+            size_only_output(recipient.script_pubkey()),
+            runestone_output.tx_out().clone(),
+        ],
+        fee_rate,
+    )?
+    .to_sat();
+
+    if utxo.value < fee {
+        return Err("UTXO value not enough".into());
+    }
+
+    let recipient_output = TxOut {
+        value: Amount::from_sat(utxo.value - fee),
+        script_pubkey: recipient.script_pubkey(),
+    };
+    ensure_not_dust(&recipient_output)?;
+
+    println!("  💰 UTXO Value: {} sat", utxo.value);
+    println!("  💰 Fee: {} sat", fee);
+
+    let prevout = taproot_prevout(&utxo)?;
+
+    let mut outputs = vec![recipient_output];
+    runestone_output.attach_to(&mut outputs)?;
+
+    let mut tx = Transaction {
+        version: Version::TWO,
+        lock_time: bitcoin::absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint {
+                txid: utxo.txid,
+                vout: utxo.vout,
+            },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::default(),
+        }],
+        output: outputs,
+    };
+    if let Some(rng) = shuffle_rng {
+        shuffle_outputs(&mut tx.output, rng)?;
+    }
+
+    validate_runestone_pointer(&tx.output)?;
+    if let Some(warning) = validate_premine_allocation(&tx.output)? {
+        println!("  ⚠️ {}", warning);
+    }
+
+    sign_keypath_inputs(secp, &mut tx, &[prevout], taproot_wallet)?;
+
+    Ok(tx)
+}
+
+/// mint 交易中 recipient 输出的金额（承载 mint 出来的 rune）
+const MINT_POSTAGE: Amount = Amount::from_sat(10_000);
+
+/// 构造 rune mint 交易：
+/// - output 0：recipient（MINT_POSTAGE），mint 出的 rune 默认落在第一个非 OP_RETURN 输出上
+/// - output 1：找零回钱包（dust 时并入手续费）
+/// - output 2：runestone（OP_RETURN，只包含 MINT）
+///
+/// mint_height（交易预计被打包的高度）不在 terms 的 mint 窗口内时直接报错，
+/// 这样的 mint 不会得到 rune，只是白付手续费
+#[allow(clippy::too_many_arguments)]
+pub fn create_mint_tx(
+    secp: &Secp256k1<bitcoin::secp256k1::All>,
+    utxo: AlchemyTxOut,
+    taproot_wallet: &TaprootWallet,
+    rune_id: RuneId,
+    terms: &Terms,
+    mint_height: u64,
+    recipient: &Address,
+    fee_rate: FeeRate,
+) -> Result<Transaction, Box<dyn std::error::Error>> {
+    ensure_address_network(recipient, taproot_wallet.network())?;
+    if !mint_is_open(terms, rune_id.block, mint_height) {
+        return Err(format!(
+            "mint for rune {} is not open at height {}",
+            rune_id, mint_height
+        )
+        .into());
+    }
+
+    let runestone_output = RunestoneOutput::new(RunesBuilder::new().mint(rune_id).build()?)?;
+    let change_script = taproot_wallet.get_internal_address().script_pubkey();
+
+    let fee = estimate_fee(
+        &[SpendKind::KeyPath],
+        &[
+            size_only_output(recipient.script_pubkey()),
+            size_only_output(change_script.clone()),
+            runestone_output.tx_out().clone(),
+        ],
+        fee_rate,
+    )?
+    .to_sat();
+
+    if utxo.value < MINT_POSTAGE.to_sat() + fee {
+        return Err("UTXO value not enough".into());
+    }
+    let change_value = utxo.value - MINT_POSTAGE.to_sat() - fee;
+
+    println!("  💰 UTXO Value: {} sat", utxo.value);
+    println!("  💰 Mint: {}", rune_id);
+    println!("  💰 Fee: {} sat", fee);
+
+    let mut outputs = vec![TxOut {
+        value: MINT_POSTAGE,
+        script_pubkey: recipient.script_pubkey(),
+    }];
+    outputs.extend(change_output_or_fold(change_value, change_script));
+    runestone_output.attach_to(&mut outputs)?;
+
+    let prevout = taproot_prevout(&utxo)?;
+
+    let mut tx = Transaction {
+        version: Version::TWO,
+        lock_time: bitcoin::absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint {
+                txid: utxo.txid,
+                vout: utxo.vout,
+            },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::default(),
+        }],
+        output: outputs,
+    };
+
+    sign_keypath_inputs(secp, &mut tx, &[prevout], taproot_wallet)?;
+
+    Ok(tx)
+}
+
+/// 构造 rune 转账交易：花费钱包中带 rune 的 UTXO，按 runestone 里的 edicts 分配
+///
+/// 输出顺序：recipients...，找零（低于 dust 时并入手续费），OP_RETURN（runestone）。
+/// runestone 的 POINTER / edict output 需按这个顺序填写，POINTER 越界或指向 OP_RETURN 时报错；
+/// 传入 shuffle_rng 时打乱后的顺序会同步写回 runestone
+pub fn create_transfer_tx(
+    secp: &Secp256k1<bitcoin::secp256k1::All>,
+    utxos: &[AlchemyTxOut],
+    taproot_wallet: &TaprootWallet,
+    runestone: ScriptBuf,
+    recipients: &[(Address, Amount)],
+    fee_rate: FeeRate,
+    shuffle_rng: Option<&mut dyn RngCore>,
+) -> Result<Transaction, Box<dyn std::error::Error>> {
+    if utxos.is_empty() {
+        return Err("No UTXOs to spend".into());
+    }
+
+    let runestone_output = RunestoneOutput::new(runestone)?;
+    let change_script = taproot_wallet.get_internal_address().script_pubkey();
+
+    let mut outputs = Vec::new();
+    for (address, value) in recipients {
+        ensure_address_network(address, taproot_wallet.network())?;
+        let output = TxOut {
+            value: *value,
+            script_pubkey: address.script_pubkey(),
+        };
+        ensure_not_dust(&output)?;
+        outputs.push(output);
+    }
+
+    let mut size_outputs = outputs.clone();
+    size_outputs.push(size_only_output(change_script.clone()));
+    size_outputs.push(runestone_output.tx_out().clone());
+    let fee = estimate_fee(
+        &vec![SpendKind::KeyPath; utxos.len()],
+        &size_outputs,
+        fee_rate,
+    )?
+    .to_sat();
+
+    let total_in: u64 = utxos.iter().map(|u| u.value).sum();
+    let total_out: u64 = outputs.iter().map(|o| o.value.to_sat()).sum();
+    if total_in < total_out + fee {
+        return Err("UTXO value not enough".into());
+    }
+
+    println!("  💰 Input Value: {} sat", total_in);
+    println!("  💰 Fee: {} sat", fee);
+
+    if let Some(change) = change_output_or_fold(total_in - total_out - fee, change_script) {
+        outputs.push(change);
+    }
+    runestone_output.attach_to(&mut outputs)?;
+    if let Some(rng) = shuffle_rng {
+        shuffle_outputs(&mut outputs, rng)?;
+    }
+
+    let mut inputs = Vec::with_capacity(utxos.len());
+    let mut prevouts = Vec::with_capacity(utxos.len());
+    for utxo in utxos {
+        inputs.push(TxIn {
+            previous_output: OutPoint {
+                txid: utxo.txid,
+                vout: utxo.vout,
+            },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::default(),
+        });
+        prevouts.push(taproot_prevout(utxo)?);
+    }
+
+    let mut tx = Transaction {
+        version: Version::TWO,
+        lock_time: bitcoin::absolute::LockTime::ZERO,
+        input: inputs,
+        output: outputs,
+    };
+
+    validate_runestone_pointer(&tx.output)?;
+
+    sign_keypath_inputs(secp, &mut tx, &prevouts, taproot_wallet)?;
+
+    Ok(tx)
+}
+
+/// 构造 inscription 的 reveal 交易：通过 script-path 花费 commit 输出，
+/// inscription 落在唯一的输出（钱包地址）上
+pub fn create_inscription_reveal_tx(
+    secp: &Secp256k1<bitcoin::secp256k1::All>,
+    commit_outpoint: OutPoint,
+    commit_output: TxOut,
+    taproot_wallet: &TaprootWallet,
+    inscription_script: &ScriptBuf,
+    taproot_spend_info: &TaprootSpendInfo,
+    fee_rate: FeeRate,
+) -> Result<Transaction, Box<dyn std::error::Error>> {
+    let output_script = taproot_wallet.get_internal_address().script_pubkey();
+    let fee = estimate_fee(
+        &[inscription_reveal_spend(
+            taproot_spend_info,
+            inscription_script,
+        )?],
+        &[size_only_output(output_script.clone())],
+        fee_rate,
+    )?;
+
+    let reveal_value = commit_output
+        .value
+        .checked_sub(fee)
+        .ok_or("commit output not enough for reveal fee")?;
+
+    let output = TxOut {
+        value: reveal_value,
+        script_pubkey: output_script,
+    };
+    ensure_not_dust(&output)?;
+
+    let mut tx = Transaction {
+        version: Version::TWO,
+        lock_time: bitcoin::absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: commit_outpoint,
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::default(),
+        }],
+        output: vec![output],
+    };
+
+    taproot_wallet.sign_script_path(
+        secp,
+        &mut tx,
+        0,
+        &[commit_output],
+        inscription_script,
+        taproot_spend_info,
+        LeafVersion::TapScript,
+        TapSighashType::Default,
+    )?;
+
+    Ok(tx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alchemy_client::TEST_TXID;
+    use crate::fees::estimate_reveal_fee;
+    use crate::wallets::{TEST_MNEMONIC, create_taproot_wallet_from_mnemonic};
+    use bitcoin::hashes::Hash;
+    use bitcoin::script::Instruction;
+    use bitcoin::secp256k1::rand::SeedableRng;
+    use bitcoin::secp256k1::rand::rngs::StdRng;
+    use bitcoin::taproot::TapLeafHash;
+    use std::collections::BTreeSet;
+
+    const XONLY_BOB: &str = "cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115";
+
+    fn etching_with_pointer(pointer: u32) -> Result<Transaction, Box<dyn std::error::Error>> {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let runestone = RunesBuilder::new()
+            .with_flags(1)
+            .with_rune("TEST")
+            .with_premine(1_000)
+            .with_pointer(pointer)
+            .build()?;
+
+        create_etching_tx(
+            &secp,
+            AlchemyTxOut::fixture(wallet.get_internal_address().script_pubkey(), 10_000, 0),
+            &wallet,
+            runestone,
+            &wallet.get_internal_address(),
+            FeeRate::from_sat_per_vb(2).unwrap(),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_etching_pointer_valid() {
+        let tx = etching_with_pointer(0).unwrap();
+        assert_eq!(tx.output.len(), 2);
+        assert!(tx.output[1].script_pubkey.is_op_return());
+    }
+
+    #[test]
+    fn test_etching_pointer_out_of_range() {
+        let err = etching_with_pointer(5).unwrap_err();
+        assert!(err.to_string().contains("out of range"));
+    }
+
+    #[test]
+    fn test_etching_pointer_at_op_return() {
+        let err = etching_with_pointer(1).unwrap_err();
+        assert!(err.to_string().contains("OP_RETURN"));
+    }
+
+    fn transfer_with_pointer(pointer: u32) -> Result<Transaction, Box<dyn std::error::Error>> {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let rune = RuneId {
+            block: 840_000,
+            tx: 1,
+        };
+        let runestone = RunesBuilder::new()
+            .with_pointer(pointer)
+            .with_edict(rune, 500, 0)
+            .build()?;
+
+        create_transfer_tx(
+            &secp,
+            &[AlchemyTxOut::fixture(
+                wallet.get_internal_address().script_pubkey(),
+                10_000,
+                0,
+            )],
+            &wallet,
+            runestone,
+            &[(wallet.get_internal_address(), Amount::from_sat(546))],
+            FeeRate::from_sat_per_vb(2).unwrap(),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_transfer_tx_layout() {
+        let tx = transfer_with_pointer(1).unwrap();
+
+        // recipient, change, OP_RETURN
+        assert_eq!(tx.output.len(), 3);
+        assert_eq!(tx.output[0].value, Amount::from_sat(546));
+        assert!(tx.output[2].script_pubkey.is_op_return());
+        assert_eq!(tx.input[0].witness.len(), 1);
+    }
+
+    #[test]
+    fn test_transfer_pointer_out_of_range() {
+        let err = transfer_with_pointer(3).unwrap_err();
+        assert!(err.to_string().contains("out of range"));
+    }
+
+    #[test]
+    fn test_transfer_pointer_at_op_return() {
+        let err = transfer_with_pointer(2).unwrap_err();
+        assert!(err.to_string().contains("OP_RETURN"));
+    }
+
+    #[test]
+    fn test_brc20_script_path_signature_verifies_against_leaf_key() {
+        let secp = Secp256k1::new();
+        let fee_rate = FeeRate::from_sat_per_vb(2).unwrap();
+        let mut output_parities = BTreeSet::new();
+
+        // 多个钱包，覆盖 output key y 为奇数 / 偶数两种情况
+        for seed in 0u8..8 {
+            let mnemonic = bip39::Mnemonic::from_entropy(&[seed; 16])
+                .unwrap()
+                .to_string();
+            let wallet =
+                create_taproot_wallet_from_mnemonic(&secp, &mnemonic, Network::Testnet).unwrap();
+            let inscription_script = build_inscription_script(
+                wallet.internal_xonly(),
+                TEXT_PLAIN_UTF8,
+                &brc20_deploy_body(),
+            );
+            let spend_info = single_leaf_spend_info(
+                &secp,
+                wallet.internal_xonly(),
+                &inscription_script,
+                LeafVersion::TapScript,
+            )
+            .unwrap();
+            output_parities.insert(spend_info.output_key_parity() == Parity::Odd);
+
+            let mut utxo =
+                AlchemyTxOut::fixture(wallet.get_internal_address().script_pubkey(), 100_000, 0);
+            utxo.script_pubkey.script = ScriptBuf::new_p2tr_tweaked(spend_info.output_key());
+            let prevout = taproot_prevout(&utxo).unwrap();
+            let tx = create_brc20_transaction(&secp, utxo, &wallet, fee_rate).unwrap();
+
+            let witness = &tx.input[0].witness;
+            assert_eq!(witness.len(), 3);
+            let sig = taproot::Signature::from_slice(witness.nth(0).unwrap()).unwrap();
+            let leaf_script = ScriptBuf::from_bytes(witness.nth(1).unwrap().to_vec());
+            let control_block = ControlBlock::decode(witness.nth(2).unwrap()).unwrap();
+            assert!(control_block.verify_taproot_commitment(
+                &secp,
+                spend_info.output_key().to_x_only_public_key(),
+                &leaf_script
+            ));
+
+            // 脚本里 OP_CHECKSIG 检查的 x-only key
+            let leaf_key = match leaf_script.instructions().next() {
+                Some(Ok(Instruction::PushBytes(bytes))) => {
+                    XOnlyPublicKey::from_slice(bytes.as_bytes()).unwrap()
+                }
+                other => panic!("unexpected instruction: {:?}", other),
+            };
+            assert_eq!(leaf_key, wallet.internal_xonly());
+
+            let sighash = SighashCache::new(&tx)
+                .taproot_script_spend_signature_hash(
+                    0,
+                    &Prevouts::All(&[prevout]),
+                    TapLeafHash::from_script(&leaf_script, LeafVersion::TapScript),
+                    TapSighashType::Default,
+                )
+                .unwrap();
+            let msg = bitcoin::secp256k1::Message::from_digest(sighash.to_byte_array());
+            assert!(secp.verify_schnorr(&sig.signature, &msg, &leaf_key).is_ok());
+            // 用 tweaked output key 验证不通过：签名不能用错 key
+            assert!(
+                secp.verify_schnorr(
+                    &sig.signature,
+                    &msg,
+                    &spend_info.output_key().to_x_only_public_key()
+                )
+                .is_err()
+            );
+        }
+        assert_eq!(output_parities.len(), 2);
+    }
+
+    #[test]
+    fn test_brc20_rejects_utxo_outside_script_tree() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        // 钱包地址上的 UTXO 没有 script tree，无法 script-path 花费
+        let err = create_brc20_transaction(
+            &secp,
+            AlchemyTxOut::fixture(wallet.get_internal_address().script_pubkey(), 100_000, 0),
+            &wallet,
+            FeeRate::from_sat_per_vb(2).unwrap(),
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("not locked to the inscription script tree")
+        );
+    }
+
+    #[test]
+    fn test_brc20_utxo_below_commit_value_is_error() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let inscription_script = build_inscription_script(
+            wallet.internal_xonly(),
+            TEXT_PLAIN_UTF8,
+            &brc20_deploy_body(),
+        );
+        let spend_info = single_leaf_spend_info(
+            &secp,
+            wallet.internal_xonly(),
+            &inscription_script,
+            LeafVersion::TapScript,
+        )
+        .unwrap();
+        let fee_rate = FeeRate::from_sat_per_vb(2).unwrap();
+        let build = |value: u64| {
+            let mut utxo =
+                AlchemyTxOut::fixture(wallet.get_internal_address().script_pubkey(), value, 0);
+            utxo.script_pubkey.script = ScriptBuf::new_p2tr_tweaked(spend_info.output_key());
+            create_brc20_transaction(&secp, utxo, &wallet, fee_rate)
+        };
+
+        // 低于 9_800 的 commit value 时报错而不是下溢
+        assert!(build(5_000).is_err());
+        // 够 commit value 但不够手续费
+        assert!(build(9_850).is_err());
+        let tx = build(100_000).unwrap();
+        assert_eq!(tx.output[0].value, Amount::from_sat(9_800));
+    }
+
+    #[test]
+    fn test_premine_without_destination_is_flagged() {
+        let etching = || {
+            RunesBuilder::new()
+                .with_flags(1)
+                .with_rune("TEST")
+                .with_premine(4_200_000)
+        };
+        let op_return = |builder: RunesBuilder| TxOut {
+            value: Amount::ZERO,
+            script_pubkey: builder.build().unwrap(),
+        };
+        let recipient = TxOut {
+            value: Amount::from_sat(10_000),
+            script_pubkey: ScriptBuf::new_p2tr(&Secp256k1::new(), XONLY_BOB.parse().unwrap(), None),
+        };
+
+        // 没有 edict / pointer，也没有非 OP_RETURN 输出：premine 无处可去
+        let err = validate_premine_allocation(&[op_return(etching())]).unwrap_err();
+        assert!(err.to_string().contains("no destination"));
+
+        // edict 把 premine 分给 OP_RETURN 输出：被烧掉
+        let outputs = [
+            recipient.clone(),
+            op_return(etching().with_edict(RuneId::ETCHED, 4_200_000, 1)),
+        ];
+        let err = validate_premine_allocation(&outputs).unwrap_err();
+        assert!(err.to_string().contains("burned"));
+
+        // 没有 edict / pointer：默认进入第一个非 OP_RETURN 输出，返回警告
+        assert_eq!(
+            validate_premine_allocation(&[op_return(etching()), recipient.clone()]).unwrap(),
+            Some(PremineWarning::ImplicitDefaultOutput {
+                premine: 4_200_000,
+                output: 1,
+            })
+        );
+        // 显式的 edict / pointer 不产生警告
+        let outputs = [
+            recipient.clone(),
+            op_return(etching().with_edict(RuneId::ETCHED, 4_200_000, 0)),
+        ];
+        assert_eq!(validate_premine_allocation(&outputs).unwrap(), None);
+        let outputs = [recipient, op_return(etching().with_pointer(0))];
+        assert_eq!(validate_premine_allocation(&outputs).unwrap(), None);
+    }
+
+    #[test]
+    fn test_immature_coinbase_rejected() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let build = |confirmations: i64| {
+            let mut utxo =
+                AlchemyTxOut::fixture(wallet.get_internal_address().script_pubkey(), 100_000, 0);
+            utxo.coinbase = Some(true);
+            utxo.confirmations = confirmations;
+            create_payment_tx(
+                &secp,
+                utxo,
+                &wallet.get_internal_address(),
+                Amount::from_sat(1_000),
+                &wallet,
+                FeeRate::from_sat_per_vb(2).unwrap(),
+            )
+        };
+
+        let err = build(50).unwrap_err();
+        assert!(err.to_string().contains("immature coinbase"));
+        assert!(err.to_string().contains("needs 50 more"));
+        assert!(build(100).is_ok());
+
+        // 普通 UTXO 不受限制
+        let mut utxo =
+            AlchemyTxOut::fixture(wallet.get_internal_address().script_pubkey(), 100_000, 0);
+        utxo.confirmations = 1;
+        assert!(ensure_coinbase_mature(&utxo).is_ok());
+    }
+
+    #[test]
+    fn test_empty_or_non_taproot_prevout_rejected() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let fee_rate = FeeRate::from_sat_per_vb(2).unwrap();
+
+        let mut utxo =
+            AlchemyTxOut::fixture(wallet.get_internal_address().script_pubkey(), 100_000, 0);
+        utxo.script_pubkey.script = ScriptBuf::new();
+        let err = create_payment_tx(
+            &secp,
+            utxo.clone(),
+            &wallet.get_internal_address(),
+            Amount::from_sat(1_000),
+            &wallet,
+            fee_rate,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("empty scriptPubKey"));
+
+        let err = create_commit_tx(
+            &secp,
+            utxo.clone(),
+            &wallet,
+            Amount::from_sat(20_000),
+            fee_rate,
+            FeeMode::SelfPaid,
+            TapSighashType::Default,
+            &ChangePolicy::FoldIntoFee(Amount::ZERO),
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("empty scriptPubKey"));
+
+        // P2WPKH 不是钱包的 taproot 输出
+        utxo.script_pubkey.script =
+            ScriptBuf::from_hex("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+        let err = create_payment_tx(
+            &secp,
+            utxo,
+            &wallet.get_internal_address(),
+            Amount::from_sat(1_000),
+            &wallet,
+            fee_rate,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("not a taproot output"));
+        assert!(err.to_string().contains("unsupported input type"));
+        assert!(err.to_string().contains("P2WPKH"));
+    }
+
+    #[test]
+    fn test_classify_script() {
+        let secp = Secp256k1::new();
+        let pubkey = bitcoin::PublicKey::new(
+            Keypair::new(&secp, &mut bitcoin::secp256k1::rand::thread_rng()).public_key(),
+        );
+        let compressed = bitcoin::CompressedPublicKey(pubkey.inner);
+        let (taproot, _) = random_keypath_owner(&secp);
+
+        let cases = [
+            (taproot.script_pubkey(), ScriptType::P2tr),
+            (
+                ScriptBuf::new_p2wpkh(&compressed.wpubkey_hash()),
+                ScriptType::P2wpkh,
+            ),
+            (
+                ScriptBuf::new_p2wsh(&ScriptBuf::new().wscript_hash()),
+                ScriptType::P2wsh,
+            ),
+            (
+                ScriptBuf::new_p2pkh(&pubkey.pubkey_hash()),
+                ScriptType::P2pkh,
+            ),
+            (
+                ScriptBuf::new_p2sh(&ScriptBuf::new().script_hash()),
+                ScriptType::Other,
+            ),
+            (ScriptBuf::new_op_return([1u8; 4]), ScriptType::Other),
+            (ScriptBuf::new(), ScriptType::Other),
+        ];
+        for (script, expected) in cases {
+            assert_eq!(classify_script(&script), expected, "{}", script);
+        }
+    }
+
+    #[test]
+    fn test_parse_address() {
+        let secp = Secp256k1::new();
+        let xonly = XONLY_BOB.parse().unwrap();
+        let testnet = Address::p2tr(&secp, xonly, None, Network::Testnet);
+        let mainnet = Address::p2tr(&secp, xonly, None, Network::Bitcoin);
+
+        let parsed = parse_address(&testnet.to_string(), Network::Testnet).unwrap();
+        assert_eq!(parsed, testnet);
+
+        // mainnet 地址不能当作 testnet 地址使用
+        assert!(matches!(
+            parse_address(&mainnet.to_string(), Network::Testnet),
+            Err(ParseError::NetworkValidation(_))
+        ));
+        assert!(parse_address("not an address", Network::Testnet).is_err());
+    }
+
+    #[test]
+    fn test_mainnet_address_rejected_by_testnet_builders() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let mainnet = Address::p2tr(&secp, XONLY_BOB.parse().unwrap(), None, Network::Bitcoin);
+
+        let err = create_payment_tx(
+            &secp,
+            AlchemyTxOut::fixture(wallet.get_internal_address().script_pubkey(), 10_000, 0),
+            &mainnet,
+            Amount::from_sat(1_000),
+            &wallet,
+            FeeRate::from_sat_per_vb(2).unwrap(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("not valid for network test"));
+
+        let runestone = RunesBuilder::new()
+            .with_flags(1)
+            .with_rune("TEST")
+            .build()
+            .unwrap();
+        let err = create_etching_tx(
+            &secp,
+            AlchemyTxOut::fixture(wallet.get_internal_address().script_pubkey(), 10_000, 0),
+            &wallet,
+            runestone.clone(),
+            &mainnet,
+            FeeRate::from_sat_per_vb(2).unwrap(),
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("not valid for network"));
+
+        let err = create_transfer_tx(
+            &secp,
+            &[AlchemyTxOut::fixture(
+                wallet.get_internal_address().script_pubkey(),
+                10_000,
+                0,
+            )],
+            &wallet,
+            runestone,
+            &[(mainnet, Amount::from_sat(1_000))],
+            FeeRate::from_sat_per_vb(2).unwrap(),
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("not valid for network"));
+    }
+
+    #[test]
+    fn test_runestone_attach_rejects_existing_op_return() {
+        let runestone = RunestoneOutput::new(
+            RunesBuilder::new()
+                .with_flags(1)
+                .with_rune("TEST")
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+
+        let mut outputs = vec![
+            TxOut {
+                value: Amount::from_sat(10_000),
+                script_pubkey: ScriptBuf::new_p2tr(
+                    &Secp256k1::new(),
+                    XONLY_BOB.parse().unwrap(),
+                    None,
+                ),
+            },
+            TxOut {
+                value: Amount::ZERO,
+                script_pubkey: ScriptBuf::new_op_return([0xAAu8; 4]),
+            },
+        ];
+        let err = runestone.clone().attach_to(&mut outputs).unwrap_err();
+        assert!(err.to_string().contains("Output 1 is already an OP_RETURN"));
+        assert_eq!(outputs.len(), 2);
+
+        outputs.pop();
+        runestone.attach_to(&mut outputs).unwrap();
+        assert_eq!(outputs.len(), 2);
+        assert!(ensure_single_op_return(&outputs).is_ok());
+    }
+
+    #[test]
+    fn test_duplicate_op_return_rejected() {
+        let runestone = RunesBuilder::new()
+            .with_flags(1)
+            .with_rune("TEST")
+            .build()
+            .unwrap();
+        let outputs = vec![
+            TxOut {
+                value: Amount::ZERO,
+                script_pubkey: runestone.clone(),
+            },
+            TxOut {
+                value: Amount::ZERO,
+                script_pubkey: runestone.clone(),
+            },
+        ];
+        let err = validate_runestone_pointer(&outputs).unwrap_err();
+        assert!(err.to_string().contains("2 OP_RETURN outputs"));
+
+        // 普通 OP_RETURN 不能当作 runestone
+        let err = RunestoneOutput::new(ScriptBuf::new_op_return([0xAAu8; 4])).unwrap_err();
+        assert!(err.to_string().contains("not a valid runestone"));
+    }
+
+    #[test]
+    fn test_commit_value_must_cover_reveal() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let inscription_script =
+            build_inscription_script(wallet.internal_xonly(), "text/plain", b"hello");
+        let fee_rate = FeeRate::from_sat_per_vb(10).unwrap();
+
+        let min_value = min_inscription_commit_value(
+            &secp,
+            &wallet,
+            &inscription_script,
+            Amount::ZERO,
+            fee_rate,
+        )
+        .unwrap();
+        let reveal_output = wallet.get_internal_address().script_pubkey();
+
+        // 只够付 reveal 手续费，reveal 输出会是 dust
+        let err = create_inscription_commit_tx(
+            &secp,
+            AlchemyTxOut::fixture(wallet.get_internal_address().script_pubkey(), 100_000, 0),
+            &wallet,
+            &inscription_script,
+            min_value,
+            fee_rate,
+            FeeMode::SelfPaid,
+            TapSighashType::Default,
+            &ChangePolicy::FoldIntoFee(Amount::ZERO),
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("cannot cover the reveal"));
+
+        // reveal 手续费 + postage：reveal 输出正好是 postage
+        let commit_value = min_inscription_commit_value(
+            &secp,
+            &wallet,
+            &inscription_script,
+            DEFAULT_POSTAGE,
+            fee_rate,
+        )
+        .unwrap();
+        assert_eq!(commit_value, min_value + DEFAULT_POSTAGE);
+
+        let (commit_tx, spend_info) = create_inscription_commit_tx(
+            &secp,
+            AlchemyTxOut::fixture(wallet.get_internal_address().script_pubkey(), 100_000, 0),
+            &wallet,
+            &inscription_script,
+            commit_value,
+            fee_rate,
+            FeeMode::SelfPaid,
+            TapSighashType::Default,
+            &ChangePolicy::FoldIntoFee(Amount::ZERO),
+            None,
+        )
+        .unwrap();
+        assert_eq!(commit_tx.output[0].value, commit_value);
+
+        let reveal_tx = create_inscription_reveal_tx(
+            &secp,
+            OutPoint {
+                txid: commit_tx.compute_txid(),
+                vout: 0,
+            },
+            commit_tx.output[0].clone(),
+            &wallet,
+            &inscription_script,
+            &spend_info,
+            fee_rate,
+        )
+        .unwrap();
+        assert_eq!(reveal_tx.output[0].value, DEFAULT_POSTAGE);
+        assert_eq!(reveal_tx.output[0].script_pubkey, reveal_output);
+    }
+
+    #[test]
+    fn test_estimate_reveal_fee_matches_built_reveal() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let fee_rate = FeeRate::from_sat_per_vb(3).unwrap();
+
+        // 200 字节的 BRC-20 文本 vs 100KB 的图片（拆成 520 字节的多个 push）
+        for (content_len, content_type) in [(200, TEXT_PLAIN_UTF8), (100_000, "image/png")] {
+            let body = vec![0xabu8; content_len];
+            let inscription_script =
+                build_inscription_script(wallet.internal_xonly(), content_type, &body);
+            let spend_info = inscription_spend_info(&secp, &wallet, &inscription_script).unwrap();
+            let commit_output = TxOut {
+                value: Amount::from_sat(1_000_000),
+                script_pubkey: wallet
+                    .get_commit_address_with_script_tree(&secp, &spend_info)
+                    .script_pubkey(),
+            };
+
+            let reveal_tx = create_inscription_reveal_tx(
+                &secp,
+                OutPoint {
+                    txid: TEST_TXID.parse().unwrap(),
+                    vout: 0,
+                },
+                commit_output,
+                &wallet,
+                &inscription_script,
+                &spend_info,
+                fee_rate,
+            )
+            .unwrap();
+
+            let estimate = estimate_reveal_fee(content_len, content_type, fee_rate);
+            assert_eq!(
+                estimate,
+                fee_rate.fee_vb(reveal_tx.vsize() as u64).unwrap(),
+                "content_len = {}",
+                content_len
+            );
+        }
+
+        assert!(
+            estimate_reveal_fee(100_000, "image/png", fee_rate)
+                > estimate_reveal_fee(200, TEXT_PLAIN_UTF8, fee_rate) * 100
+        );
+    }
+
+    #[test]
+    fn test_commit_tx_sighash_single_and_none() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let utxo = AlchemyTxOut::fixture(wallet.get_internal_address().script_pubkey(), 100_000, 0);
+        let prevout = TxOut {
+            value: Amount::from_sat(utxo.value),
+            script_pubkey: utxo.script_pubkey.script.clone(),
+        };
+
+        for sighash_type in [TapSighashType::Single, TapSighashType::None] {
+            let (mut tx, _) = create_commit_tx(
+                &secp,
+                utxo.clone(),
+                &wallet,
+                Amount::from_sat(20_000),
+                FeeRate::from_sat_per_vb(2).unwrap(),
+                FeeMode::SelfPaid,
+                sighash_type,
+                &ChangePolicy::FoldIntoFee(Amount::ZERO),
+                None,
+            )
+            .unwrap();
+
+            // 65 字节签名，最后一字节是 sighash flag
+            let sig = tx.input[0].witness.nth(0).unwrap();
+            assert_eq!(sig.len(), 65);
+            assert_eq!(sig[64], sighash_type as u8);
+            assert!(
+                verify_taproot_input_signature(&secp, &tx, 0, std::slice::from_ref(&prevout))
+                    .unwrap()
+            );
+
+            // 签名后追加输出不影响签名
+            tx.output.push(TxOut {
+                value: Amount::from_sat(1_000),
+                script_pubkey: prevout.script_pubkey.clone(),
+            });
+            assert!(
+                verify_taproot_input_signature(&secp, &tx, 0, std::slice::from_ref(&prevout))
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_commit_tx_change_policies() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let fee_rate = FeeRate::from_sat_per_vb(2).unwrap();
+        let commit = |policy: &ChangePolicy| {
+            create_commit_tx(
+                &secp,
+                AlchemyTxOut::fixture(wallet.get_internal_address().script_pubkey(), 100_000, 0),
+                &wallet,
+                Amount::from_sat(20_000),
+                fee_rate,
+                FeeMode::SelfPaid,
+                TapSighashType::Default,
+                policy,
+                None,
+            )
+            .map(|(tx, _)| tx)
+        };
+
+        // ToAddress：找零到指定地址；其他网络的地址直接报错
+        let bob = Address::p2tr(&secp, XONLY_BOB.parse().unwrap(), None, Network::Testnet);
+        let tx = commit(&ChangePolicy::ToAddress(bob.clone())).unwrap();
+        assert_eq!(tx.output[1].script_pubkey, bob.script_pubkey());
+        let mainnet = Address::p2tr(&secp, XONLY_BOB.parse().unwrap(), None, Network::Bitcoin);
+        assert!(commit(&ChangePolicy::ToAddress(mainnet)).is_err());
+
+        // FoldIntoFee：找零约 79_7xx sat，阈值决定是否保留
+        let tx = commit(&ChangePolicy::FoldIntoFee(Amount::from_sat(79_000))).unwrap();
+        assert_eq!(tx.output.len(), 2);
+        assert_eq!(
+            tx.output[1].script_pubkey,
+            wallet.get_internal_address().script_pubkey()
+        );
+        let change = tx.output[1].value;
+        let tx = commit(&ChangePolicy::FoldIntoFee(change + Amount::from_sat(1))).unwrap();
+        assert_eq!(tx.output.len(), 1);
+        let tx = commit(&ChangePolicy::FoldIntoFee(change)).unwrap();
+        assert_eq!(tx.output.len(), 2);
+
+        // ToNewDerivedAddress：每次派生新的找零地址，不回到收款地址
+        let first = commit(&ChangePolicy::ToNewDerivedAddress).unwrap();
+        let second = commit(&ChangePolicy::ToNewDerivedAddress).unwrap();
+        assert_eq!(
+            first.output[1].script_pubkey,
+            wallet.change_address(&secp, 0).unwrap().script_pubkey()
+        );
+        assert_ne!(
+            first.output[1].script_pubkey,
+            wallet.get_internal_address().script_pubkey()
+        );
+        assert_ne!(
+            first.output[1].script_pubkey,
+            second.output[1].script_pubkey
+        );
+
+        // 找零 UTXO 可以再花费：钱包按 scriptPubKey 找到找零链上的 key
+        let change_utxo = AlchemyTxOut {
+            txid: first.compute_txid(),
+            ..AlchemyTxOut::fixture(
+                first.output[1].script_pubkey.clone(),
+                first.output[1].value.to_sat(),
+                1,
+            )
+        };
+        let prevouts = [taproot_prevout(&change_utxo).unwrap()];
+        let spend = create_payment_tx(
+            &secp,
+            change_utxo,
+            &bob,
+            Amount::from_sat(10_000),
+            &wallet,
+            fee_rate,
+        )
+        .unwrap();
+        assert!(verify_taproot_input_signature(&secp, &spend, 0, &prevouts).unwrap());
+    }
+
+    #[test]
+    fn test_sighash_single_requires_matching_output() {
+        let txin = TxIn {
+            previous_output: OutPoint::null(),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::new(),
+        };
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![txin.clone(), txin],
+            output: vec![TxOut {
+                value: Amount::from_sat(1_000),
+                script_pubkey: ScriptBuf::new(),
+            }],
+        };
+
+        assert!(ensure_sighash_outputs(&tx, 0, TapSighashType::Single).is_ok());
+        // input 1 没有对应的 output 1
+        let err = ensure_sighash_outputs(&tx, 1, TapSighashType::Single).unwrap_err();
+        assert!(err.to_string().contains("SIGHASH_SINGLE"));
+        assert!(ensure_sighash_outputs(&tx, 1, TapSighashType::SinglePlusAnyoneCanPay).is_err());
+        // NONE / ALL 不关心输出序号
+        assert!(ensure_sighash_outputs(&tx, 1, TapSighashType::None).is_ok());
+        assert!(ensure_sighash_outputs(&tx, 1, TapSighashType::All).is_ok());
+        assert!(ensure_sighash_outputs(&tx, 2, TapSighashType::All).is_err());
+    }
+
+    #[test]
+    fn test_mint_tx_layout() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let bob = Address::p2tr(&secp, XONLY_BOB.parse().unwrap(), None, Network::Testnet);
+        let rune_id = RuneId {
+            block: 2_500_000,
+            tx: 7,
+        };
+
+        let tx = create_mint_tx(
+            &secp,
+            AlchemyTxOut::fixture(wallet.get_internal_address().script_pubkey(), 50_000, 0),
+            &wallet,
+            rune_id,
+            &Terms::default(),
+            2_500_001,
+            &bob,
+            FeeRate::from_sat_per_vb(2).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(tx.output.len(), 3);
+        assert_eq!(tx.output[0].script_pubkey, bob.script_pubkey());
+        assert_eq!(tx.output[0].value, MINT_POSTAGE);
+        assert_eq!(
+            tx.output[1].script_pubkey,
+            wallet.get_internal_address().script_pubkey()
+        );
+
+        let decoded = RunesParser::parse_script_hex(&tx.output[2].script_pubkey.to_hex_string())
+            .unwrap()
+            .unwrap()
+            .decoded()
+            .unwrap();
+        assert_eq!(decoded.mint, Some(rune_id));
+        assert_eq!(decoded.pointer, None);
+
+        // 相对 etching 高度的窗口 [+10, +100) 之外不构造交易
+        let terms = Terms {
+            offset: (Some(10), Some(100)),
+            ..Default::default()
+        };
+        for mint_height in [2_500_005, 2_500_100] {
+            let err = create_mint_tx(
+                &secp,
+                AlchemyTxOut::fixture(wallet.get_internal_address().script_pubkey(), 50_000, 0),
+                &wallet,
+                rune_id,
+                &terms,
+                mint_height,
+                &bob,
+                FeeRate::from_sat_per_vb(2).unwrap(),
+            )
+            .unwrap_err();
+            assert!(err.to_string().contains("is not open"));
+        }
+    }
+
+    #[test]
+    fn test_shuffle_outputs_keeps_edicts_consistent() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let other =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Regtest).unwrap();
+        let alice = Address::p2tr(&secp, other.internal_xonly(), None, Network::Testnet);
+        let bob = Address::p2tr(&secp, XONLY_BOB.parse().unwrap(), None, Network::Testnet);
+
+        let rune_a = crate::rune_decode::RuneId {
+            block: 840_000,
+            tx: 1,
+        };
+        let rune_b = crate::rune_decode::RuneId {
+            block: 840_001,
+            tx: 3,
+        };
+        // 原始顺序：0 = alice，1 = bob，2 = 找零，3 = OP_RETURN
+        let runestone = RunesBuilder::new()
+            .with_pointer(2)
+            .with_edict(rune_a, 100, 0)
+            .with_edict(rune_b, 200, 1)
+            .build()
+            .unwrap();
+
+        let change_script = wallet.get_internal_address().script_pubkey();
+        let output = |value: u64, script_pubkey: ScriptBuf| TxOut {
+            value: Amount::from_sat(value),
+            script_pubkey,
+        };
+        let mut outputs = vec![
+            output(546, alice.script_pubkey()),
+            output(546, bob.script_pubkey()),
+            output(10_000, change_script.clone()),
+            output(0, runestone),
+        ];
+
+        let mut rng = StdRng::seed_from_u64(7);
+        shuffle_outputs(&mut outputs, &mut rng).unwrap();
+
+        let scripts: Vec<ScriptBuf> = outputs.iter().map(|o| o.script_pubkey.clone()).collect();
+        assert_ne!(scripts[0], alice.script_pubkey(), "order should change");
+
+        let runestone = scripts.iter().find(|s| s.is_op_return()).unwrap();
+        let decoded = RunesParser::parse_script_hex(&runestone.to_hex_string())
+            .unwrap()
+            .unwrap()
+            .decoded()
+            .unwrap();
+
+        let target = |id| {
+            let edict = decoded.edicts.iter().find(|e| e.id == id).unwrap();
+            &scripts[edict.output as usize]
+        };
+        assert_eq!(target(rune_a), &alice.script_pubkey());
+        assert_eq!(target(rune_b), &bob.script_pubkey());
+        assert_eq!(&scripts[decoded.pointer.unwrap() as usize], &change_script);
+    }
+
+    #[test]
+    fn test_zero_conf_utxo_rejected() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let mut utxo =
+            AlchemyTxOut::fixture(wallet.get_internal_address().script_pubkey(), 10_000, 0);
+
+        utxo.confirmations = 0;
+        assert!(ensure_confirmations(&utxo, 0).is_ok());
+        let err = ensure_confirmations(&utxo, 1).unwrap_err();
+        assert!(err.to_string().contains("has 0 confirmations"));
+
+        utxo.confirmations = 1;
+        assert!(ensure_confirmations(&utxo, 1).is_ok());
+    }
+
+    /// 随机 key 的 key-path 地址和 tweaked keypair
+    fn random_keypath_owner(
+        secp: &Secp256k1<bitcoin::secp256k1::All>,
+    ) -> (Address, TweakedKeypair) {
+        let keypair = Keypair::new(secp, &mut bitcoin::secp256k1::rand::thread_rng());
+        let address = Address::p2tr(secp, keypair.x_only_public_key().0, None, Network::Testnet);
+        (address, keypair.tap_tweak(secp, None))
+    }
+
+    #[test]
+    fn test_multi_send_three_recipients() {
+        let secp = Secp256k1::new();
+        let (owner, tweaked) = random_keypath_owner(&secp);
+        let recipients: Vec<(Address, Amount)> = (1..=3)
+            .map(|i| (random_keypath_owner(&secp).0, Amount::from_sat(i * 1_000)))
+            .collect();
+        let utxos = [
+            AlchemyTxOut::fixture(owner.script_pubkey(), 5_000, 0),
+            AlchemyTxOut::fixture(owner.script_pubkey(), 5_000, 1),
+        ];
+        let fee_rate = FeeRate::from_sat_per_vb(2).unwrap();
+
+        let tx =
+            create_multi_send_tx(&secp, &utxos, &recipients, &owner, fee_rate, &tweaked).unwrap();
+
+        // 3 个 recipient + 找零
+        assert_eq!(tx.output.len(), 4);
+        for (output, (address, value)) in tx.output.iter().zip(&recipients) {
+            assert_eq!(output.script_pubkey, address.script_pubkey());
+            assert_eq!(output.value, *value);
+        }
+        assert_eq!(tx.output[3].script_pubkey, owner.script_pubkey());
+
+        let prevouts: Vec<TxOut> = utxos
+            .iter()
+            .map(|u| TxOut {
+                value: Amount::from_sat(u.value),
+                script_pubkey: owner.script_pubkey(),
+            })
+            .collect();
+        for i in 0..tx.input.len() {
+            assert!(verify_taproot_input_signature(&secp, &tx, i, &prevouts).unwrap());
+        }
+
+        let fee = 10_000 - tx.output.iter().map(|o| o.value.to_sat()).sum::<u64>();
+        assert_eq!(fee, fee_rate.fee_vb(tx.vsize() as u64).unwrap().to_sat());
+    }
+
+    #[test]
+    fn test_multi_send_folds_dust_change() {
+        let secp = Secp256k1::new();
+        let (owner, tweaked) = random_keypath_owner(&secp);
+        let recipients: Vec<(Address, Amount)> = (1..=3)
+            .map(|i| (random_keypath_owner(&secp).0, Amount::from_sat(i * 1_000)))
+            .collect();
+        let fee_rate = FeeRate::from_sat_per_vb(2).unwrap();
+
+        // 6_000 给 recipients，480 手续费（240 vB），剩 200 sat 低于 P2TR dust（330）
+        let utxos = [AlchemyTxOut::fixture(owner.script_pubkey(), 6_680, 0)];
+        let tx =
+            create_multi_send_tx(&secp, &utxos, &recipients, &owner, fee_rate, &tweaked).unwrap();
+
+        assert_eq!(tx.output.len(), 3);
+        assert_eq!(
+            tx.output.iter().map(|o| o.value.to_sat()).sum::<u64>(),
+            6_000
+        );
+
+        // 不够支付时报错
+        let utxos = [AlchemyTxOut::fixture(owner.script_pubkey(), 6_200, 0)];
+        assert!(
+            create_multi_send_tx(&secp, &utxos, &recipients, &owner, fee_rate, &tweaked).is_err()
+        );
+    }
+
+    #[test]
+    fn test_non_default_leaf_version() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let leaf_script = Builder::new()
+            .push_opcode(bitcoin::opcodes::OP_TRUE)
+            .into_script();
+
+        // 奇数、和 annex 冲突的 0x50 都不是合法的 leaf version
+        assert!(
+            leaf_version_from_byte(0xc1)
+                .unwrap_err()
+                .to_string()
+                .contains("must be even")
+        );
+        assert!(leaf_version_from_byte(0x50).is_err());
+        assert_eq!(
+            leaf_version_from_byte(0xc0).unwrap(),
+            LeafVersion::TapScript
+        );
+
+        let future = leaf_version_from_byte(0xc2).unwrap();
+        let default_info = single_leaf_spend_info(
+            &secp,
+            wallet.internal_xonly(),
+            &leaf_script,
+            LeafVersion::TapScript,
+        )
+        .unwrap();
+        let future_info =
+            single_leaf_spend_info(&secp, wallet.internal_xonly(), &leaf_script, future).unwrap();
+
+        // leaf hash 依赖 leaf version，所以 merkle root / output key 也不同
+        assert_ne!(
+            TapLeafHash::from_script(&leaf_script, future),
+            TapLeafHash::from_script(&leaf_script, LeafVersion::TapScript)
+        );
+        assert_ne!(default_info.output_key(), future_info.output_key());
+
+        let control_block = leaf_control_block(&future_info, &leaf_script, future).unwrap();
+        let encoded = control_block.serialize();
+        assert_eq!(encoded[0] & 0xfe, 0xc2);
+        assert_eq!(encoded[0] & 1, future_info.output_key_parity().to_u8(),);
+        assert!(control_block.verify_taproot_commitment(
+            &secp,
+            future_info.output_key().to_x_only_public_key(),
+            &leaf_script
+        ));
+
+        // 用默认 leaf version 在这棵树里找不到该叶子
+        assert!(leaf_control_block(&future_info, &leaf_script, LeafVersion::TapScript).is_err());
+    }
+
+    #[test]
+    fn test_parse_control_block() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let leaf = |n: i64| Builder::new().push_int(n).into_script();
+
+        // 三个叶子：深度 1、2、2，leaf(2) 的 merkle path 有两个节点
+        let spend_info = TaprootBuilder::new()
+            .add_leaf(1, leaf(1))
+            .unwrap()
+            .add_leaf(2, leaf(2))
+            .unwrap()
+            .add_leaf(2, leaf(3))
+            .unwrap()
+            .finalize(&secp, wallet.internal_xonly())
+            .unwrap();
+        let control_block =
+            leaf_control_block(&spend_info, &leaf(2), LeafVersion::TapScript).unwrap();
+        let bytes = control_block.serialize();
+
+        let parsed = parse_control_block(&bytes).unwrap();
+        assert_eq!(parsed.leaf_version, LeafVersion::TapScript);
+        assert_eq!(parsed.parity, spend_info.output_key_parity());
+        assert_eq!(parsed.internal_key, wallet.internal_xonly());
+        assert_eq!(parsed.merkle_path.len(), 2);
+        let expected_path: Vec<[u8; 32]> = control_block
+            .merkle_branch
+            .iter()
+            .map(|node| node.to_byte_array())
+            .collect();
+        assert_eq!(parsed.merkle_path, expected_path);
+
+        // 截断的 merkle path、缺少 internal key
+        assert!(
+            parse_control_block(&bytes[..bytes.len() - 1])
+                .unwrap_err()
+                .to_string()
+                .contains("truncated")
+        );
+        assert!(parse_control_block(&bytes[..32]).is_err());
+    }
+
+    #[test]
+    fn test_verify_reveal_commitment() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let inscription_script =
+            build_inscription_script(wallet.internal_xonly(), TEXT_PLAIN_UTF8, b"hello");
+        let spend_info = inscription_spend_info(&secp, &wallet, &inscription_script).unwrap();
+        let commit_output = TxOut {
+            value: Amount::from_sat(50_000),
+            script_pubkey: wallet
+                .get_commit_address_with_script_tree(&secp, &spend_info)
+                .script_pubkey(),
+        };
+
+        let reveal_tx = create_inscription_reveal_tx(
+            &secp,
+            OutPoint {
+                txid: TEST_TXID.parse().unwrap(),
+                vout: 0,
+            },
+            commit_output.clone(),
+            &wallet,
+            &inscription_script,
+            &spend_info,
+            FeeRate::from_sat_per_vb(2).unwrap(),
+        )
+        .unwrap();
+        let witness = &reveal_tx.input[0].witness;
+        assert!(verify_reveal_commitment(
+            witness,
+            &commit_output.script_pubkey
+        ));
+
+        // 把脚本换成另一个 inscription：leaf hash 变了，承诺不成立
+        let forged_script =
+            build_inscription_script(wallet.internal_xonly(), TEXT_PLAIN_UTF8, b"forged");
+        let forged = Witness::from_slice(&[
+            witness.nth(0).unwrap(),
+            forged_script.as_bytes(),
+            witness.nth(2).unwrap(),
+        ]);
+        assert!(!verify_reveal_commitment(
+            &forged,
+            &commit_output.script_pubkey
+        ));
+
+        // 花费的不是这个输出（key-path 钱包地址）
+        assert!(!verify_reveal_commitment(
+            witness,
+            &wallet.get_internal_address().script_pubkey()
+        ));
+    }
+
+    fn standard_tx(outputs: Vec<TxOut>) -> (Transaction, Vec<TxOut>) {
+        let (address, _) = random_keypath_owner(&Secp256k1::new());
+        let prevout = TxOut {
+            value: Amount::from_sat(10_000),
+            script_pubkey: address.script_pubkey(),
+        };
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness: Witness::from_slice(&[[0u8; 64]]),
+            }],
+            output: outputs,
+        };
+        (tx, vec![prevout])
+    }
+
+    fn op_return(data_len: usize) -> TxOut {
+        TxOut {
+            value: Amount::ZERO,
+            script_pubkey: ScriptBuf::new_op_return(
+                <&bitcoin::script::PushBytes>::try_from(vec![0u8; data_len].as_slice()).unwrap(),
+            ),
+        }
+    }
+
+    #[test]
+    fn test_is_standard_tx_op_return_size() {
+        let (tx, prevouts) = standard_tx(vec![op_return(80)]);
+        assert_eq!(is_standard_tx(&tx, &prevouts), Ok(()));
+
+        let (tx, prevouts) = standard_tx(vec![op_return(81)]);
+        assert_eq!(
+            is_standard_tx(&tx, &prevouts),
+            Err(NonStandardReason::OpReturnTooLarge {
+                output: 0,
+                size: 84
+            })
+        );
+    }
+
+    #[test]
+    fn test_is_standard_tx_rejects_two_op_returns() {
+        let (tx, prevouts) = standard_tx(vec![op_return(10), op_return(10)]);
+        assert_eq!(
+            is_standard_tx(&tx, &prevouts),
+            Err(NonStandardReason::MultipleOpReturn(2))
+        );
+
+        // version 3 和非标准输出脚本
+        let (mut tx, prevouts) = standard_tx(vec![op_return(10)]);
+        tx.version = Version(3);
+        assert_eq!(
+            is_standard_tx(&tx, &prevouts),
+            Err(NonStandardReason::Version(3))
+        );
+        let (tx, prevouts) = standard_tx(vec![TxOut {
+            value: Amount::from_sat(1_000),
+            script_pubkey: Builder::new().push_int(1).into_script(),
+        }]);
+        assert_eq!(
+            is_standard_tx(&tx, &prevouts),
+            Err(NonStandardReason::NonStandardOutput(0))
+        );
+    }
+
+    /// owner 的两个 UTXO 转给 owner 自己的未签名交易及其 prevouts
+    fn unsigned_keypath_tx(owner: &Address) -> (Transaction, Vec<TxOut>) {
+        let prevouts = vec![
+            TxOut {
+                value: Amount::from_sat(5_000),
+                script_pubkey: owner.script_pubkey(),
+            };
+            2
+        ];
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: (0..2)
+                .map(|vout| TxIn {
+                    previous_output: OutPoint {
+                        txid: TEST_TXID.parse().unwrap(),
+                        vout,
+                    },
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output: vec![TxOut {
+                value: Amount::from_sat(9_000),
+                script_pubkey: owner.script_pubkey(),
+            }],
+        };
+        (tx, prevouts)
+    }
+
+    #[test]
+    fn test_finalize_keypath_tx_with_external_signatures() {
+        let secp = Secp256k1::new();
+        let (owner, tweaked) = random_keypath_owner(&secp);
+        let (tx, prevouts) = unsigned_keypath_tx(&owner);
+
+        // 「离线签名方」：对每个 input 的 sighash 签名，第二个 input 用 65 字节的 SIGHASH_ALL
+        let mut cache = SighashCache::new(&tx);
+        let signatures: Vec<Vec<u8>> = [TapSighashType::Default, TapSighashType::All]
+            .into_iter()
+            .enumerate()
+            .map(|(i, sighash_type)| {
+                let sighash = cache
+                    .taproot_key_spend_signature_hash(i, &Prevouts::All(&prevouts), sighash_type)
+                    .unwrap();
+                let msg = bitcoin::secp256k1::Message::from_digest(sighash.to_byte_array());
+                taproot::Signature {
+                    signature: secp.sign_schnorr(&msg, &tweaked.to_keypair()),
+                    sighash_type,
+                }
+                .to_vec()
+            })
+            .collect();
+        assert_eq!(signatures[0].len(), 64);
+        assert_eq!(signatures[1].len(), 65);
+
+        // 数量不一致、长度错误
+        assert!(finalize_keypath_tx(tx.clone(), signatures[..1].to_vec()).is_err());
+        assert!(
+            finalize_keypath_tx(tx.clone(), vec![vec![0u8; 63], signatures[1].clone()]).is_err()
+        );
+        let mut explicit_default = signatures[0].clone();
+        explicit_default.push(0x00);
+        assert!(
+            finalize_keypath_tx(tx.clone(), vec![explicit_default, signatures[1].clone()]).is_err()
+        );
+
+        let signed = finalize_keypath_tx(tx, signatures).unwrap();
+        for i in 0..signed.input.len() {
+            assert_eq!(signed.input[i].witness.len(), 1);
+            assert!(verify_taproot_input_signature(&secp, &signed, i, &prevouts).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_compute_keypath_sighashes_matches_commit_tx() {
+        let secp = Secp256k1::new();
+        let wallet = create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet)
+            .unwrap()
+            .with_aux_rand(false);
+        let utxo = AlchemyTxOut::fixture(wallet.get_internal_address().script_pubkey(), 100_000, 0);
+        let prevout = taproot_prevout(&utxo).unwrap();
+
+        for sighash_type in [TapSighashType::Default, TapSighashType::Single] {
+            let (tx, _) = create_commit_tx(
+                &secp,
+                utxo.clone(),
+                &wallet,
+                Amount::from_sat(20_000),
+                FeeRate::from_sat_per_vb(2).unwrap(),
+                FeeMode::SelfPaid,
+                sighash_type,
+                &ChangePolicy::FoldIntoFee(Amount::ZERO),
+                None,
+            )
+            .unwrap();
+
+            // 去掉签名后重新计算 sighash，用同一个 key 确定性签名，应得到相同的 witness
+            let mut unsigned = tx.clone();
+            unsigned.input[0].witness = Witness::new();
+            let sighashes =
+                compute_keypath_sighashes(&unsigned, std::slice::from_ref(&prevout), sighash_type)
+                    .unwrap();
+            assert_eq!(sighashes.len(), 1);
+
+            let msg = bitcoin::secp256k1::Message::from(sighashes[0]);
+            let sig = taproot::Signature {
+                signature: wallet.sign_keypath(&secp, &msg),
+                sighash_type,
+            };
+            let signed = finalize_keypath_tx(unsigned, vec![sig.to_vec()]).unwrap();
+            assert_eq!(signed, tx);
+        }
+
+        // prevouts 数量不一致
+        let (tx, prevouts) = unsigned_keypath_tx(&wallet.get_internal_address());
+        assert!(compute_keypath_sighashes(&tx, &prevouts[..1], TapSighashType::Default).is_err());
+    }
+
+    #[test]
+    fn test_keypath_spend_with_script_tree() {
+        let secp = Secp256k1::new();
+        let internal = Keypair::from_seckey_slice(&secp, &[7u8; 32]).unwrap();
+        let leaf = build_inscription_script(internal.x_only_public_key().0, "text/plain", b"hi");
+        let spend_info = single_leaf_spend_info(
+            &secp,
+            internal.x_only_public_key().0,
+            &leaf,
+            LeafVersion::TapScript,
+        )
+        .unwrap();
+        let merkle_root = spend_info.merkle_root().unwrap();
+
+        // 用 merkle_root tweak 出来的 key 就是地址里的 output key；None tweak 的不是
+        let address = Address::p2tr(
+            &secp,
+            internal.x_only_public_key().0,
+            Some(merkle_root),
+            Network::Testnet,
+        );
+        let tweaked = internal.tap_tweak(&secp, Some(merkle_root));
+        let (output_key, _) = tweaked.to_keypair().x_only_public_key();
+        assert_eq!(output_key, spend_info.output_key().to_x_only_public_key());
+        assert_eq!(
+            address.script_pubkey().as_bytes()[2..],
+            output_key.serialize()
+        );
+        let untweaked = internal
+            .tap_tweak(&secp, None)
+            .to_keypair()
+            .x_only_public_key()
+            .0;
+        assert_ne!(untweaked, output_key);
+
+        let (destination, _) = random_keypath_owner(&secp);
+        let fee_rate = FeeRate::from_sat_per_vb(2).unwrap();
+        let utxo = AlchemyTxOut::fixture(address.script_pubkey(), 10_000, 0);
+        let tx = create_keypath_spend_with_tree(
+            &secp,
+            utxo.clone(),
+            merkle_root,
+            &internal,
+            &destination,
+            fee_rate,
+        )
+        .unwrap();
+        let prevout = taproot_prevout(&utxo).unwrap();
+        assert!(
+            verify_taproot_input_signature(&secp, &tx, 0, std::slice::from_ref(&prevout)).unwrap()
+        );
+        assert_eq!(tx.output[0].script_pubkey, destination.script_pubkey());
+
+        // merkle_root 与地址不符
+        let other_root = TapNodeHash::from_byte_array([1u8; 32]);
+        assert!(
+            create_keypath_spend_with_tree(
+                &secp,
+                utxo,
+                other_root,
+                &internal,
+                &destination,
+                fee_rate
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_incomplete_script_tree_is_error() {
+        let secp = Secp256k1::new();
+        let internal = Keypair::from_seckey_slice(&secp, &[7u8; 32])
+            .unwrap()
+            .x_only_public_key()
+            .0;
+        let leaf = |body: &[u8]| build_inscription_script(internal, "text/plain", body);
+
+        // 两个 depth 0 的叶子：第二个叶子直接报错，不会 panic
+        let builder = TaprootBuilder::new().add_leaf(0, leaf(b"a")).unwrap();
+        assert!(builder.add_leaf(0, leaf(b"b")).is_err());
+
+        // 只有一个 depth 1 的叶子：finalize 报错并带回 builder，补上另一个叶子后可以 finalize
+        let builder = TaprootBuilder::new().add_leaf(1, leaf(b"a")).unwrap();
+        let err = finalize_script_tree(&secp, builder, internal).unwrap_err();
+        assert!(err.to_string().contains("tree not finalizable"));
+        let builder = err.builder.add_leaf(1, leaf(b"b")).unwrap();
+        assert!(finalize_script_tree(&secp, builder, internal).is_ok());
+    }
+
+    #[test]
+    fn test_create_sweep_tx_nothing_to_sweep() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let destination = wallet.get_internal_address();
+        let fee_rate = FeeRate::from_sat_per_vb(10).unwrap();
+
+        let utxos = [
+            AlchemyTxOut::fixture(wallet.get_internal_address().script_pubkey(), 300, 0),
+            AlchemyTxOut::fixture(wallet.get_internal_address().script_pubkey(), 300, 0),
+        ];
+        let err = create_sweep_tx(&secp, &utxos, &destination, &wallet, fee_rate).unwrap_err();
+        assert!(err.to_string().contains("nothing to sweep"));
+
+        let err = create_sweep_tx(&secp, &[], &destination, &wallet, fee_rate).unwrap_err();
+        assert!(err.to_string().contains("nothing to sweep"));
+    }
+
+    #[test]
+    fn test_child_pays_commit_underpays_parent() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let utxo = AlchemyTxOut::fixture(wallet.get_internal_address().script_pubkey(), 100_000, 0);
+        let fee_rate = FeeRate::from_sat_per_vb(20).unwrap();
+        let min_relay = FeeRate::from_sat_per_vb(1).unwrap();
+        let commit = |fee_mode: FeeMode, policy: &ChangePolicy| {
+            create_commit_tx(
+                &secp,
+                utxo.clone(),
+                &wallet,
+                Amount::from_sat(20_000),
+                fee_rate,
+                fee_mode,
+                TapSighashType::Default,
+                policy,
+                None,
+            )
+            .map(|(tx, _)| tx)
+        };
+        let fee_of = |tx: &Transaction| {
+            Amount::from_sat(utxo.value) - tx.output.iter().map(|o| o.value).sum::<Amount>()
+        };
+        let policy = ChangePolicy::FoldIntoFee(Amount::ZERO);
+
+        let self_paid = commit(FeeMode::SelfPaid, &policy).unwrap();
+        let child_pays = commit(FeeMode::ChildPays { min_relay }, &policy).unwrap();
+
+        assert!(fee_of(&child_pays) < fee_of(&self_paid));
+        // 仍不低于 relay 下限
+        assert!(fee_of(&child_pays) >= min_relay.fee_vb(child_pays.vsize() as u64).unwrap());
+        // 找零输出保留下来作为 anchor
+        let change_script = wallet.get_internal_address().script_pubkey();
+        assert!(
+            child_pays
+                .output
+                .iter()
+                .any(|o| o.script_pubkey == change_script)
+        );
+
+        // 找零被并入手续费时没有 anchor，报错
+        let err = commit(
+            FeeMode::ChildPays { min_relay },
+            &ChangePolicy::FoldIntoFee(Amount::from_sat(1_000_000)),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("CPFP anchor"));
+    }
+}
@@ -0,0 +1,452 @@
+use bitcoin::key::Secp256k1;
+use bitcoin::transaction::Version;
+use bitcoin::{
+    Address, Amount, FeeRate, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness,
+};
+
+use crate::alchemy_client::{AlchemyClient, PrevoutCache};
+use crate::fees::{SpendKind, ensure_not_dust, estimate_fee, select_utxos_bnb_for_outputs};
+use crate::transactions::{
+    change_output_or_fold, ensure_address_network, sign_keypath_inputs_cached, size_only_output,
+};
+use crate::wallets::TaprootWallet;
+
+/// =====================================================
+/// 交易模板：用输入 / 输出描述组合出交易
+/// =====================================================
+///
+/// 常见的交易形状（支付 + 找零、inscription commit + 找零、runestone + 找零……）
+/// 都可以用一组 OutputSpec 描述，不必每种场景写一个 create_*_tx。
+///
+/// 所有 input 都按钱包的 key-path 花费签名；输出按加入的顺序排列
+#[derive(Debug, Clone)]
+pub enum OutputSpec {
+    /// 向固定地址支付固定金额
+    Pay { address: Address, amount: Amount },
+    /// 找零：收到扣除支付和手续费后剩余的金额，低于 dust 时并入手续费；最多一个
+    Change(Address),
+    /// runestone（或其他 OP_RETURN）输出，金额为 0
+    Runestone(ScriptBuf),
+}
+
+/// 交易的 input 来源
+#[derive(Debug, Clone)]
+pub enum InputSpec {
+    /// 花费指定的 outpoint
+    Outpoints(Vec<OutPoint>),
+    /// 扫描钱包地址的 UTXO，按模板的全部输出用 BnB 选出足够支付的 input
+    AutoSelect,
+}
+
+#[derive(Debug, Clone)]
+pub struct TxTemplate {
+    inputs: InputSpec,
+    outputs: Vec<OutputSpec>,
+}
+
+impl TxTemplate {
+    pub fn new(inputs: InputSpec) -> Self {
+        TxTemplate {
+            inputs,
+            outputs: Vec::new(),
+        }
+    }
+
+    pub fn with_output(mut self, output: OutputSpec) -> Self {
+        self.outputs.push(output);
+        self
+    }
+
+    /// 所有 Pay 输出的金额之和
+    fn pay_total(&self) -> Result<Amount, Box<dyn std::error::Error>> {
+        self.outputs
+            .iter()
+            .filter_map(|output| match output {
+                OutputSpec::Pay { amount, .. } => Some(*amount),
+                _ => None,
+            })
+            .try_fold(Amount::ZERO, |total, amount| total.checked_add(amount))
+            .ok_or("payment total overflow".into())
+    }
+
+    /// 找零输出的脚本（模板里没有 Change 时为 None）
+    fn change_script(&self) -> Option<ScriptBuf> {
+        self.outputs.iter().find_map(|output| match output {
+            OutputSpec::Change(address) => Some(address.script_pubkey()),
+            _ => None,
+        })
+    }
+
+    /// 除找零外的输出，金额为实际支付的金额，用于选币和估算手续费
+    fn fixed_outputs(&self) -> Vec<TxOut> {
+        self.outputs
+            .iter()
+            .filter_map(|output| match output {
+                OutputSpec::Pay { address, amount } => Some(TxOut {
+                    value: *amount,
+                    script_pubkey: address.script_pubkey(),
+                }),
+                OutputSpec::Runestone(script) => Some(TxOut {
+                    value: Amount::ZERO,
+                    script_pubkey: script.clone(),
+                }),
+                OutputSpec::Change(_) => None,
+            })
+            .collect()
+    }
+
+    /// 确定要花费的 outpoint
+    async fn resolve_inputs(
+        &self,
+        wallet: &TaprootWallet,
+        client: &AlchemyClient,
+        fee_rate: FeeRate,
+    ) -> Result<Vec<OutPoint>, Box<dyn std::error::Error>> {
+        match &self.inputs {
+            InputSpec::Outpoints(outpoints) => Ok(outpoints.clone()),
+            InputSpec::AutoSelect => {
+                let address = wallet.get_internal_address().to_string();
+                let utxos = client.scan_utxos_for_address(&address).await?;
+                let selected = select_utxos_bnb_for_outputs(
+                    &utxos,
+                    &self.fixed_outputs(),
+                    self.change_script().as_ref(),
+                    fee_rate,
+                )
+                .ok_or("not enough UTXOs to fund the template")?;
+                selected
+                    .iter()
+                    .map(|utxo| {
+                        Ok(OutPoint {
+                            txid: utxo.txid.parse()?,
+                            vout: utxo.vout as u32,
+                        })
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// 选币、估算手续费、构造输出并用钱包签名
+    pub async fn build(
+        &self,
+        wallet: &TaprootWallet,
+        client: &AlchemyClient,
+        fee_rate: FeeRate,
+    ) -> Result<Transaction, Box<dyn std::error::Error>> {
+        let change_count = self
+            .outputs
+            .iter()
+            .filter(|output| matches!(output, OutputSpec::Change(_)))
+            .count();
+        if change_count > 1 {
+            return Err("transaction template has more than one change output".into());
+        }
+        for output in &self.outputs {
+            if let OutputSpec::Pay { address, .. } | OutputSpec::Change(address) = output {
+                ensure_address_network(address, wallet.network())?;
+            }
+        }
+
+        let outpoints = self.resolve_inputs(wallet, client, fee_rate).await?;
+        if outpoints.is_empty() {
+            return Err("transaction template has no inputs".into());
+        }
+        let mut prevout_cache = PrevoutCache::new();
+        prevout_cache.fetch(client, &outpoints).await?;
+        let total_in: Amount = outpoints
+            .iter()
+            .map(|op| prevout_cache.get(op).map(|prevout| prevout.value))
+            .sum::<Option<Amount>>()
+            .ok_or("missing prevout")?;
+
+        // 先按包含找零输出的交易确定手续费，再从剩余中扣出找零
+        let available = total_in
+            .checked_sub(self.pay_total()?)
+            .ok_or("inputs not enough to cover outputs and fee")?;
+        let inputs = vec![SpendKind::KeyPath; outpoints.len()];
+        let mut size_outputs = self.fixed_outputs();
+        let change_script = self.change_script();
+        size_outputs.extend(change_script.clone().map(size_only_output));
+        let fee = estimate_fee(&inputs, &size_outputs, fee_rate)?;
+        let change_value = match available.checked_sub(fee) {
+            Some(change_value) => change_value,
+            // 放不下找零输出（BnB 选出了不需要找零的组合）：不带找零时手续费够付就去掉找零，
+            // 剩余全部作为手续费
+            None if change_script.is_some() => {
+                size_outputs.pop();
+                if available < estimate_fee(&inputs, &size_outputs, fee_rate)? {
+                    return Err("inputs not enough to cover outputs and fee".into());
+                }
+                Amount::ZERO
+            }
+            None => return Err("inputs not enough to cover outputs and fee".into()),
+        };
+
+        let mut outputs = Vec::with_capacity(self.outputs.len());
+        for output in &self.outputs {
+            match output {
+                OutputSpec::Pay { address, amount } => {
+                    let output = TxOut {
+                        value: *amount,
+                        script_pubkey: address.script_pubkey(),
+                    };
+                    ensure_not_dust(&output)?;
+                    outputs.push(output);
+                }
+                OutputSpec::Change(address) => outputs.extend(change_output_or_fold(
+                    change_value.to_sat(),
+                    address.script_pubkey(),
+                )),
+                OutputSpec::Runestone(script) => outputs.push(TxOut {
+                    value: Amount::ZERO,
+                    script_pubkey: script.clone(),
+                }),
+            }
+        }
+
+        let mut tx = Transaction {
+            version: Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: outpoints
+                .iter()
+                .map(|outpoint| TxIn {
+                    previous_output: *outpoint,
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    witness: Witness::default(),
+                })
+                .collect(),
+            output: outputs,
+        };
+
+        sign_keypath_inputs_cached(&Secp256k1::new(), &mut tx, &prevout_cache, wallet)?;
+
+        Ok(tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alchemy_client::mock_rpc::{client_for, mock_batch, mock_result};
+    use crate::alchemy_client::{TEST_TXID, TxOut as AlchemyTxOut};
+    use crate::rune_decode::RuneId;
+    use crate::runes_builder::RunesBuilder;
+    use crate::transactions::{ChangePolicy, FeeMode, create_commit_tx};
+    use crate::wallets::{TEST_MNEMONIC, create_taproot_wallet_from_mnemonic};
+    use bitcoin::Network;
+    use bitcoin::sighash::TapSighashType;
+    use serde_json::json;
+    use wiremock::MockServer;
+
+    #[tokio::test]
+    async fn test_template_matches_create_commit_tx() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let wallet_script = wallet.get_internal_address().script_pubkey();
+        let fee_rate = FeeRate::from_sat_per_vb(3).unwrap();
+        let commit_value = Amount::from_sat(20_000);
+
+        let funding = AlchemyTxOut {
+            bestblock: "00".to_string(),
+            confirmations: 6,
+            ..AlchemyTxOut::fixture(wallet_script.clone(), 100_000, 1)
+        };
+        let (commit_tx, spend_info) = create_commit_tx(
+            &secp,
+            funding.clone(),
+            &wallet,
+            commit_value,
+            fee_rate,
+            FeeMode::SelfPaid,
+            TapSighashType::Default,
+            &ChangePolicy::FoldIntoFee(Amount::ZERO),
+            None,
+        )
+        .unwrap();
+
+        let server = MockServer::start().await;
+        let script_hex = wallet_script.to_hex_string();
+        mock_batch(&server, move |_, _| {
+            json!({
+                "bestblock": "00",
+                "confirmations": 6,
+                "value": 0.001,
+                "scriptPubKey": { "asm": "", "hex": script_hex },
+                "coinbase": false
+            })
+        })
+        .await;
+
+        let template = TxTemplate::new(InputSpec::Outpoints(vec![OutPoint {
+            txid: funding.txid,
+            vout: funding.vout,
+        }]))
+        .with_output(OutputSpec::Pay {
+            address: wallet.get_commit_address_with_script_tree(&secp, &spend_info),
+            amount: commit_value,
+        })
+        .with_output(OutputSpec::Change(wallet.get_internal_address()));
+        let tx = template
+            .build(&wallet, &client_for(&server), fee_rate)
+            .await
+            .unwrap();
+
+        assert_eq!(tx.output, commit_tx.output);
+        assert_eq!(
+            tx.input[0].previous_output,
+            commit_tx.input[0].previous_output
+        );
+        assert_eq!(tx.input[0].witness.len(), 1);
+    }
+
+    fn test_runestone() -> ScriptBuf {
+        RunesBuilder::new()
+            .with_edict(
+                RuneId {
+                    block: 840_000,
+                    tx: 1,
+                },
+                100,
+                0,
+            )
+            .build()
+            .unwrap()
+    }
+
+    /// 挂载 gettxout（batch），所有 outpoint 都按 value_of(vout) 返回钱包地址上的 UTXO
+    async fn mock_wallet_tx_outs(
+        server: &MockServer,
+        wallet: &TaprootWallet,
+        value_of: impl Fn(u64) -> u64 + Send + Sync + 'static,
+    ) {
+        let script_hex = wallet
+            .get_internal_address()
+            .script_pubkey()
+            .to_hex_string();
+        mock_batch(server, move |_, params| {
+            let sats = value_of(params[1].as_u64().unwrap());
+            json!({
+                "bestblock": "00",
+                "confirmations": 6,
+                "value": Amount::from_sat(sats).to_btc(),
+                "scriptPubKey": { "asm": "", "hex": script_hex },
+                "coinbase": false
+            })
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_template_with_runestone_output() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let fee_rate = FeeRate::from_sat_per_vb(2).unwrap();
+        let pay = TxOut {
+            value: Amount::from_sat(20_000),
+            script_pubkey: wallet.get_internal_address().script_pubkey(),
+        };
+
+        let server = MockServer::start().await;
+        mock_wallet_tx_outs(&server, &wallet, |_| 100_000).await;
+
+        let tx = TxTemplate::new(InputSpec::Outpoints(vec![OutPoint {
+            txid: TEST_TXID.parse().unwrap(),
+            vout: 0,
+        }]))
+        .with_output(OutputSpec::Pay {
+            address: wallet.get_internal_address(),
+            amount: pay.value,
+        })
+        .with_output(OutputSpec::Runestone(test_runestone()))
+        .with_output(OutputSpec::Change(wallet.get_internal_address()))
+        .build(&wallet, &client_for(&server), fee_rate)
+        .await
+        .unwrap();
+
+        assert_eq!(tx.output.len(), 3);
+        assert_eq!(tx.output[0], pay);
+        assert_eq!(
+            tx.output[1],
+            TxOut {
+                value: Amount::ZERO,
+                script_pubkey: test_runestone(),
+            }
+        );
+        // 手续费按包含 runestone 的全部输出估算
+        let fee = estimate_fee(&[SpendKind::KeyPath], &tx.output, fee_rate).unwrap();
+        let total_out: Amount = tx.output.iter().map(|o| o.value).sum();
+        assert_eq!(Amount::from_sat(100_000) - total_out, fee);
+    }
+
+    #[tokio::test]
+    async fn test_auto_select_changeless_with_runestone() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let fee_rate = FeeRate::from_sat_per_vb(2).unwrap();
+        let pay = TxOut {
+            value: Amount::from_sat(20_000),
+            script_pubkey: wallet.get_internal_address().script_pubkey(),
+        };
+        let runestone = TxOut {
+            value: Amount::ZERO,
+            script_pubkey: test_runestone(),
+        };
+
+        // vout 0 刚好够 [pay, runestone] 加手续费，多出的 100 sat 不够做找零；
+        // vout 1 太大，选它会产生找零
+        let changeless_fee = estimate_fee(
+            &[SpendKind::KeyPath],
+            &[pay.clone(), runestone.clone()],
+            fee_rate,
+        )
+        .unwrap();
+        let exact = (pay.value + changeless_fee).to_sat() + 100;
+        let value_of = move |vout: u64| if vout == 0 { exact } else { 1_000_000 };
+
+        let server = MockServer::start().await;
+        // 单个调用的 mock 先挂载，batch 请求才会落到 mock_wallet_tx_outs
+        mock_result(
+            &server,
+            "scantxoutset",
+            json!({
+                "success": true,
+                "height": 200,
+                "unspents": (0..2)
+                    .map(|vout| json!({
+                        "txid": TEST_TXID,
+                        "vout": vout,
+                        "amount": Amount::from_sat(value_of(vout)).to_btc(),
+                        "height": 150
+                    }))
+                    .collect::<Vec<_>>()
+            }),
+        )
+        .await;
+        mock_wallet_tx_outs(&server, &wallet, value_of).await;
+
+        let tx = TxTemplate::new(InputSpec::AutoSelect)
+            .with_output(OutputSpec::Pay {
+                address: wallet.get_internal_address(),
+                amount: pay.value,
+            })
+            .with_output(OutputSpec::Runestone(test_runestone()))
+            .with_output(OutputSpec::Change(wallet.get_internal_address()))
+            .build(&wallet, &client_for(&server), fee_rate)
+            .await
+            .unwrap();
+
+        assert_eq!(tx.input.len(), 1);
+        assert_eq!(tx.input[0].previous_output.vout, 0);
+        // 没有找零输出，多出的部分并入手续费
+        assert_eq!(tx.output, vec![pay.clone(), runestone]);
+        assert_eq!(
+            Amount::from_sat(exact) - pay.value,
+            changeless_fee + Amount::from_sat(100)
+        );
+    }
+}
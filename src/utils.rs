@@ -1,46 +1,405 @@
-use bitcoin::opcodes::OP_FALSE;
+use std::collections::BTreeSet;
+
+use bitcoin::blockdata::constants::MAX_SCRIPT_ELEMENT_SIZE;
 use bitcoin::opcodes::all::{OP_CHECKSIG, OP_ENDIF, OP_IF, OP_PUSHNUM_13, OP_RETURN};
-use bitcoin::script::{Builder, PushBytesBuf};
-use bitcoin::{ScriptBuf, XOnlyPublicKey};
-use serde::Serialize;
-use serde_json::json;
+use bitcoin::opcodes::{OP_FALSE, Opcode};
+use bitcoin::script::{Builder, Instruction, PushBytesBuf};
+use bitcoin::{Amount, OutPoint, ScriptBuf, Transaction, Txid, Witness, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
 
+use crate::rune_decode::{RunesParser, Runestone};
 use crate::runes_builder::RunesBuilder;
+use crate::transactions::MAX_OP_RETURN_RELAY;
+
+/// inscription envelope 中单个 push 的最大字节数（tapscript 的 MAX_SCRIPT_ELEMENT_SIZE）
+pub const MAX_INSCRIPTION_CHUNK_SIZE: usize = 520;
+
+/// BRC-20 inscription 使用的 content-type
+pub const TEXT_PLAIN_UTF8: &str = "text/plain;charset=utf-8";
 
-pub fn build_inscription_script(xonly_pubkey: XOnlyPublicKey) -> ScriptBuf {
-    let brc20_data = serde_json::to_string_pretty(&json!({
+/// 示例用的 BRC-20 deploy 数据
+pub fn brc20_deploy_body() -> Vec<u8> {
+    serde_json::to_string_pretty(&json!({
         "p": "brc-20",
         "op": "deploy",
         "tick": "ordi",
         "max": "21000000",
         "lim": "1000"
     }))
-    .expect("Failed to format JSON");
+    .expect("Failed to format JSON")
+    .into_bytes()
+}
+
+/// ord 协议的 envelope 标识
+pub const ORD_PROTOCOL_ID: &[u8] = b"ord";
 
-    // let json_bytes = brc20_json.as_bytes();
-    let mut json_pb = PushBytesBuf::new();
-    json_pb
-        .extend_from_slice(brc20_data.as_bytes())
-        .expect("Failed to push slice");
+/// 构造 inscription 的 tapscript：
+/// <pubkey> OP_CHECKSIG OP_FALSE OP_IF "ord" 1 <content_type> 0 <body chunks...> OP_ENDIF
+///
+/// body 超过 520 字节时拆成多个 push
+pub fn build_inscription_script(
+    xonly_pubkey: XOnlyPublicKey,
+    content_type: &str,
+    body: &[u8],
+) -> ScriptBuf {
+    build_inscription_script_with_protocol(xonly_pubkey, ORD_PROTOCOL_ID, content_type, body)
+}
 
+/// 同 build_inscription_script，但 envelope 使用自定义的协议标识代替 "ord"
+/// （沿用 ord envelope 格式的其他元协议）
+pub fn build_inscription_script_with_protocol(
+    xonly_pubkey: XOnlyPublicKey,
+    protocol: &[u8],
+    content_type: &str,
+    body: &[u8],
+) -> ScriptBuf {
     let mut pk_pb = PushBytesBuf::new();
     pk_pb
         .extend_from_slice(&xonly_pubkey.serialize())
         .expect("Failed to push pubkey");
 
     // push_slice 要求实现 PushBytes 特征（不能超过 2^32 字节）
-    Builder::new()
-        .push_slice(pk_pb)
-        .push_opcode(OP_CHECKSIG)
+    let builder = Builder::new().push_slice(pk_pb).push_opcode(OP_CHECKSIG);
+
+    push_envelope(builder, protocol, content_type, body, None, None).into_script()
+}
+
+/// 构造带 metadata 的 inscription tapscript：metadata 先编码为 CBOR，
+/// 超过 520 字节时拆成多个 `5 <chunk>` 字段
+pub fn build_inscription_script_with_metadata(
+    xonly_pubkey: XOnlyPublicKey,
+    content_type: &str,
+    body: &[u8],
+    metadata: &Value,
+) -> Result<ScriptBuf, Box<dyn std::error::Error>> {
+    let mut cbor = Vec::new();
+    ciborium::into_writer(metadata, &mut cbor)?;
+
+    let builder = Builder::new()
+        .push_slice(xonly_pubkey.serialize())
+        .push_opcode(OP_CHECKSIG);
+
+    Ok(push_envelope(
+        builder,
+        ORD_PROTOCOL_ID,
+        content_type,
+        body,
+        None,
+        Some(&cbor),
+    )
+    .into_script())
+}
+
+/// 追加一个 envelope：
+/// OP_FALSE OP_IF <protocol> 1 <content_type> [2 <pointer>] [5 <metadata chunks>...] 0 <body chunks...> OP_ENDIF
+fn push_envelope(
+    builder: Builder,
+    protocol: &[u8],
+    content_type: &str,
+    body: &[u8],
+    pointer: Option<u64>,
+    metadata: Option<&[u8]>,
+) -> Builder {
+    let mut content_type_pb = PushBytesBuf::new();
+    content_type_pb
+        .extend_from_slice(content_type.as_bytes())
+        .expect("Failed to push content type");
+
+    let mut builder = builder
         .push_opcode(OP_FALSE)
         .push_opcode(OP_IF)
-        .push_slice(b"ord")
-        .push_slice(&[1u8]) // ord version
-        .push_slice(b"text/plain;charset=utf-8")
-        .push_slice(&[0u8]) // separator
-        .push_slice(json_pb)
-        .push_opcode(OP_ENDIF)
-        .into_script()
+        .push_slice(PushBytesBuf::try_from(protocol.to_vec()).expect("Failed to push protocol"))
+        .push_slice([1u8]) // content-type tag
+        .push_slice(content_type_pb);
+
+    if let Some(pointer) = pointer {
+        builder = builder
+            .push_slice(INSCRIPTION_TAG_POINTER)
+            .push_slice(encode_pointer(pointer));
+    }
+
+    for chunk in metadata
+        .unwrap_or_default()
+        .chunks(MAX_INSCRIPTION_CHUNK_SIZE)
+    {
+        let mut chunk_pb = PushBytesBuf::new();
+        chunk_pb
+            .extend_from_slice(chunk)
+            .expect("Failed to push metadata");
+        builder = builder
+            .push_slice(INSCRIPTION_TAG_METADATA)
+            .push_slice(chunk_pb);
+    }
+
+    builder = builder.push_opcode(OP_FALSE); // body 分隔符（空 push）
+
+    for chunk in body.chunks(MAX_INSCRIPTION_CHUNK_SIZE) {
+        let mut chunk_pb = PushBytesBuf::new();
+        chunk_pb
+            .extend_from_slice(chunk)
+            .expect("Failed to push slice");
+        builder = builder.push_slice(chunk_pb);
+    }
+
+    builder.push_opcode(OP_ENDIF)
+}
+
+/// pointer 字段：小端序的 sat 偏移量，去掉末尾的 0 字节
+fn encode_pointer(pointer: u64) -> PushBytesBuf {
+    let bytes = pointer.to_le_bytes();
+    let len = bytes.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+    PushBytesBuf::try_from(bytes[..len].to_vec()).expect("pointer fits in a push")
+}
+
+/// =====================================================
+/// 批量 inscription：一个 reveal 交易里铭刻多个 inscription
+/// =====================================================
+///
+/// 所有 envelope 依次放在同一个 tapscript 里，commit 开销被多个 inscription 分摊。
+/// reveal 交易的第 i 个输出金额为 postage，第 i 个 inscription 的 pointer
+/// 指向第 i 个输出的第一个 sat，即偏移量 i * postage；第一个 inscription
+/// 默认落在偏移量 0，不需要 pointer
+pub fn build_batch_inscription(
+    xonly_pubkey: XOnlyPublicKey,
+    items: &[(String, Vec<u8>)],
+    postage: Amount,
+) -> Result<ScriptBuf, Box<dyn std::error::Error>> {
+    if items.is_empty() {
+        return Err("batch inscription needs at least one item".into());
+    }
+
+    let mut builder = Builder::new()
+        .push_slice(xonly_pubkey.serialize())
+        .push_opcode(OP_CHECKSIG);
+
+    for (index, (content_type, body)) in items.iter().enumerate() {
+        let pointer = match index {
+            0 => None,
+            _ => Some(
+                postage
+                    .to_sat()
+                    .checked_mul(index as u64)
+                    .ok_or("inscription pointer overflow")?,
+            ),
+        };
+        builder = push_envelope(builder, ORD_PROTOCOL_ID, content_type, body, pointer, None);
+    }
+
+    Ok(builder.into_script())
+}
+
+/// 从 envelope 中解析出的 inscription
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inscription {
+    /// envelope 的协议标识，如 "ord"
+    pub protocol: Vec<u8>,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+    /// metadata 字段（tag 5）CBOR 解码后的结果；没有 metadata 或 CBOR 无法解码时为 None
+    pub metadata: Option<Value>,
+    /// metadata 字段的原始字节（多个 tag 5 按顺序拼接），CBOR 解码失败时可以从这里取
+    pub metadata_bytes: Option<Vec<u8>>,
+}
+
+/// inscription envelope 里 content-type 字段的 tag
+const INSCRIPTION_TAG_CONTENT_TYPE: &[u8] = &[1];
+
+/// inscription envelope 里 pointer 字段的 tag
+const INSCRIPTION_TAG_POINTER: [u8; 1] = [2];
+
+/// inscription envelope 里 metadata 字段的 tag，值为 CBOR，可以拆成多个字段
+const INSCRIPTION_TAG_METADATA: [u8; 1] = [5];
+
+/// 解析 reveal 脚本中的 inscription envelope：
+/// OP_FALSE OP_IF "ord" <tag> <value> ... <body 分隔符> <body chunks...> OP_ENDIF
+///
+/// - 脚本里没有 envelope（或 envelope 没有 OP_ENDIF 结尾）时返回 None
+/// - body 可能被拆成多个 push，这里会按顺序拼接
+pub fn parse_inscription(script: &ScriptBuf) -> Option<Inscription> {
+    parse_inscription_with_protocols(script, &[ORD_PROTOCOL_ID])
+}
+
+/// 同 parse_inscription，但识别 protocols 中任意一个协议标识的 envelope
+pub fn parse_inscription_with_protocols(
+    script: &ScriptBuf,
+    protocols: &[&[u8]],
+) -> Option<Inscription> {
+    let instructions: Vec<Instruction> = script.instructions().collect::<Result<_, _>>().ok()?;
+
+    // 找到 envelope 的起点：OP_FALSE OP_IF <protocol>
+    let start = instructions.windows(3).position(|w| {
+        matches!(w[0], Instruction::PushBytes(pb) if pb.is_empty())
+            && w[1] == Instruction::Op(OP_IF)
+            && matches!(w[2], Instruction::PushBytes(pb) if protocols.contains(&pb.as_bytes()))
+    })?;
+    let Instruction::PushBytes(protocol) = instructions[start + 2] else {
+        return None;
+    };
+
+    let mut content_type = None;
+    let mut metadata_bytes: Option<Vec<u8>> = None;
+    let mut body = Vec::new();
+    let mut in_body = false;
+    let mut iter = instructions[start + 3..].iter();
+
+    while let Some(instruction) = iter.next() {
+        match instruction {
+            Instruction::Op(op) if *op == OP_ENDIF => {
+                let metadata = metadata_bytes
+                    .as_deref()
+                    .and_then(|bytes| ciborium::from_reader(bytes).ok());
+                return Some(Inscription {
+                    protocol: protocol.as_bytes().to_vec(),
+                    content_type,
+                    body,
+                    metadata,
+                    metadata_bytes,
+                });
+            }
+            Instruction::PushBytes(pb) if in_body => body.extend_from_slice(pb.as_bytes()),
+            // body 分隔符：ord 规范是空 push，兼容旧版本脚本里的 [0]
+            Instruction::PushBytes(tag) if tag.is_empty() || tag.as_bytes() == [0u8] => {
+                in_body = true;
+            }
+            Instruction::PushBytes(tag) => {
+                let Some(Instruction::PushBytes(value)) = iter.next() else {
+                    return None;
+                };
+                if tag.as_bytes() == INSCRIPTION_TAG_CONTENT_TYPE {
+                    content_type = Some(String::from_utf8_lossy(value.as_bytes()).into_owned());
+                } else if tag.as_bytes() == INSCRIPTION_TAG_METADATA {
+                    metadata_bytes
+                        .get_or_insert_with(Vec::new)
+                        .extend_from_slice(value.as_bytes());
+                }
+            }
+            Instruction::Op(_) => return None,
+        }
+    }
+
+    None
+}
+
+/// 从 reveal 交易 input 的 witness（[sig, script, control_block]）中解析 inscription
+pub fn parse_inscription_from_witness(witness: &Witness) -> Option<Inscription> {
+    parse_inscription(&witness.taproot_leaf_script()?.script.to_owned())
+}
+
+/// =====================================================
+/// 交易解析：一次性查看 inputs / outputs 以及其中的 runestone、inscription
+/// =====================================================
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxSummary {
+    pub txid: Txid,
+    pub vsize: usize,
+    pub inputs: Vec<InputSummary>,
+    pub outputs: Vec<OutputSummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputSummary {
+    pub previous_output: OutPoint,
+    /// script-path witness 中 inscription 的 content-type（没有 content-type 时为空字符串）
+    pub inscription_content_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputSummary {
+    pub value: Amount,
+    pub script_pubkey: ScriptBuf,
+    pub runestone: Option<Runestone>,
+    pub inscription_content_type: Option<String>,
+}
+
+impl TxSummary {
+    /// 本交易创建的、带有 inscription 或 rune 的输出序号
+    ///
+    /// - inscription：reveal 时落在第一个输出上
+    /// - rune：runestone 中 edict 指向的输出，以及接收未分配 rune 的 POINTER
+    ///   （未设置时为第一个非 OP_RETURN 输出）；cenotaph 会烧掉所有 rune
+    ///
+    /// 只根据本交易判断，没有 runestone 时从输入继承的 rune 无法识别
+    pub fn asset_outputs(&self) -> BTreeSet<u32> {
+        let mut outputs = BTreeSet::new();
+
+        if self
+            .inputs
+            .iter()
+            .any(|input| input.inscription_content_type.is_some())
+            && !self.outputs.is_empty()
+        {
+            outputs.insert(0);
+        }
+
+        let non_op_return: Vec<u32> = self
+            .outputs
+            .iter()
+            .enumerate()
+            .filter(|(_, o)| !o.script_pubkey.is_op_return())
+            .map(|(i, _)| i as u32)
+            .collect();
+
+        let runestone = self
+            .outputs
+            .iter()
+            .find_map(|o| o.runestone.as_ref())
+            // POINTER 超出 u32 的 runestone 无法确定去向，按 cenotaph 处理
+            .and_then(|r| r.decoded().ok());
+        if let Some(runestone) = runestone.filter(|r| !r.cenotaph) {
+            for edict in &runestone.edicts {
+                if edict.output as usize == self.outputs.len() {
+                    outputs.extend(&non_op_return);
+                } else {
+                    outputs.insert(edict.output);
+                }
+            }
+            outputs.extend(runestone.pointer.or(non_op_return.first().copied()));
+        }
+
+        outputs
+    }
+}
+
+fn inscription_content_type(inscription: Inscription) -> String {
+    inscription.content_type.unwrap_or_default()
+}
+
+/// 反序列化原始交易 hex，逐个 input / output 尝试解析 inscription 和 runestone；
+/// 无法解析的非标准脚本直接跳过，不报错
+pub fn inspect_transaction(hex: &str) -> Result<TxSummary, Box<dyn std::error::Error>> {
+    let tx: Transaction = bitcoin::consensus::encode::deserialize_hex(hex.trim())?;
+
+    let inputs = tx
+        .input
+        .iter()
+        .map(|input| InputSummary {
+            previous_output: input.previous_output,
+            inscription_content_type: parse_inscription_from_witness(&input.witness)
+                .map(inscription_content_type),
+        })
+        .collect();
+
+    let outputs = tx
+        .output
+        .iter()
+        .map(|output| OutputSummary {
+            value: output.value,
+            script_pubkey: output.script_pubkey.clone(),
+            runestone: RunesParser::parse_script_hex(&output.script_pubkey.to_hex_string())
+                .ok()
+                .flatten(),
+            inscription_content_type: parse_inscription(&output.script_pubkey)
+                .map(inscription_content_type),
+        })
+        .collect();
+
+    Ok(TxSummary {
+        txid: tx.compute_txid(),
+        vsize: tx.vsize(),
+        inputs,
+        outputs,
+    })
 }
 
 /// =====================================================
@@ -101,3 +460,517 @@ pub fn build_rune_op_return() -> ScriptBuf {
 
     script
 }
+
+/// =====================================================
+/// 任意数据的 OP_RETURN 输出脚本
+/// =====================================================
+///
+/// OP_RETURN 后跟一个 push（时间戳、协议标记等），push 操作码按数据长度选择。
+/// 脚本超过 MAX_OP_RETURN_RELAY（83 字节，即 80 字节数据）时报错，
+/// 除非 allow_nonstandard（共识有效，但大多数节点不转发）；单个 push 仍不能超过 520 字节
+pub fn build_op_return(
+    data: &[u8],
+    allow_nonstandard: bool,
+) -> Result<ScriptBuf, Box<dyn std::error::Error>> {
+    if data.len() > MAX_SCRIPT_ELEMENT_SIZE {
+        return Err(format!(
+            "OP_RETURN data is {} bytes, exceeds the {} byte push limit",
+            data.len(),
+            MAX_SCRIPT_ELEMENT_SIZE
+        )
+        .into());
+    }
+    let mut pb = PushBytesBuf::new();
+    pb.extend_from_slice(data)?;
+    let script = Builder::new()
+        .push_opcode(OP_RETURN)
+        .push_slice(pb)
+        .into_script();
+    if script.len() > MAX_OP_RETURN_RELAY && !allow_nonstandard {
+        return Err(format!(
+            "OP_RETURN data is {} bytes, script is {} bytes, exceeds the standard limit of {}",
+            data.len(),
+            script.len(),
+            MAX_OP_RETURN_RELAY
+        )
+        .into());
+    }
+    Ok(script)
+}
+
+/// =====================================================
+/// OP_RETURN 元协议脚本构建器
+/// =====================================================
+///
+/// OP_RETURN [marker 操作码] <prefix || payload chunks...>
+///
+/// - marker：协议标记操作码，如 Runes 的 OP_PUSHNUM_13；没有时直接跟数据
+/// - prefix：拼在 payload 前面的文本标记，如 Omni 的 "omni"
+///
+/// 数据超过 520 字节时拆成多个 push，解码方按顺序拼接
+#[derive(Debug, Clone, Default)]
+pub struct DataCarrierBuilder {
+    marker: Option<Opcode>,
+    prefix: Vec<u8>,
+    payload: Vec<u8>,
+}
+
+impl DataCarrierBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_marker(mut self, marker: Opcode) -> Self {
+        self.marker = Some(marker);
+        self
+    }
+
+    pub fn with_prefix(mut self, prefix: &[u8]) -> Self {
+        self.prefix = prefix.to_vec();
+        self
+    }
+
+    pub fn with_payload(mut self, payload: &[u8]) -> Self {
+        self.payload = payload.to_vec();
+        self
+    }
+
+    pub fn build(&self) -> Result<ScriptBuf, Box<dyn std::error::Error>> {
+        let mut builder = Builder::new().push_opcode(OP_RETURN);
+        if let Some(marker) = self.marker {
+            builder = builder.push_opcode(marker);
+        }
+
+        let data: Vec<u8> = self.prefix.iter().chain(&self.payload).copied().collect();
+        for chunk in data.chunks(MAX_SCRIPT_ELEMENT_SIZE) {
+            builder = builder.push_slice(PushBytesBuf::try_from(chunk.to_vec())?);
+        }
+
+        Ok(builder.into_script())
+    }
+}
+
+/// =====================================================
+/// 解析 scriptPubKey.asm
+/// =====================================================
+///
+/// gettxout 返回的 asm 是 Bitcoin Core 的格式：OP_0 / OP_1..OP_16 写成 "0".."16"，
+/// 不超过 4 字节的 push 写成十进制数，其余 push 写成 hex；
+/// 同时兼容 rust-bitcoin 的 to_asm_string 格式（"OP_PUSHNUM_13"、"OP_PUSHBYTES_n <hex>"）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptToken {
+    Opcode(Opcode),
+    /// 以 hex 写出的数据 push
+    Data(Vec<u8>),
+    /// Core 以十进制写出的短 push（不超过 4 字节）
+    Number(i64),
+}
+
+/// 按空白切分 asm，把每个 token 归类为 opcode、数据 push 或数字
+pub fn parse_asm(asm: &str) -> Result<Vec<ScriptToken>, Box<dyn std::error::Error>> {
+    let mut tokens = Vec::new();
+    let mut words = asm.split_whitespace();
+
+    while let Some(word) = words.next() {
+        if let Some(opcode) = opcode_from_name(word) {
+            // rust-bitcoin 格式：push opcode 后面紧跟 hex 数据
+            if (0x01..=0x4e).contains(&opcode.to_u8()) {
+                let data = words
+                    .next()
+                    .ok_or(format!("{} is missing its data", word))?;
+                tokens.push(ScriptToken::Data(hex::decode(data)?));
+            } else {
+                tokens.push(ScriptToken::Opcode(opcode));
+            }
+        } else if word.starts_with("OP_") {
+            return Err(format!("unknown opcode in asm: {}", word).into());
+        } else if let Some(number) = parse_asm_number(word) {
+            tokens.push(match number {
+                -1 => ScriptToken::Opcode(bitcoin::opcodes::all::OP_PUSHNUM_NEG1),
+                0 => ScriptToken::Opcode(bitcoin::opcodes::OP_0),
+                1..=16 => ScriptToken::Opcode(Opcode::from(0x50 + number as u8)),
+                _ => ScriptToken::Number(number),
+            });
+        } else {
+            let data =
+                hex::decode(word).map_err(|e| format!("invalid asm token {}: {}", word, e))?;
+            tokens.push(ScriptToken::Data(data));
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// 按名字查找 opcode，包括 Core 和 rust-bitcoin 写法不同的别名
+fn opcode_from_name(name: &str) -> Option<Opcode> {
+    use bitcoin::opcodes::all::{OP_CLTV, OP_CSV, OP_PUSHNUM_1, OP_PUSHNUM_NEG1};
+
+    match name {
+        "OP_0" | "OP_FALSE" => return Some(bitcoin::opcodes::OP_0),
+        "OP_TRUE" => return Some(OP_PUSHNUM_1),
+        "OP_1NEGATE" => return Some(OP_PUSHNUM_NEG1),
+        "OP_CHECKLOCKTIMEVERIFY" | "OP_NOP2" => return Some(OP_CLTV),
+        "OP_CHECKSEQUENCEVERIFY" | "OP_NOP3" => return Some(OP_CSV),
+        _ => {}
+    }
+    // OP_1..OP_16
+    if let Some(n) = name.strip_prefix("OP_").and_then(|n| n.parse::<u8>().ok())
+        && (1..=16).contains(&n)
+    {
+        return Some(Opcode::from(0x50 + n));
+    }
+    (0..=u8::MAX)
+        .map(Opcode::from)
+        .find(|opcode| opcode.to_string() == name)
+}
+
+/// Core 写出的十进制数：规范写法（无前导 0）且在 4 字节 script number 范围内。
+/// 10 位数字与 5 字节的 hex 无法区分，按 hex 处理
+fn parse_asm_number(word: &str) -> Option<i64> {
+    let number: i64 = word.parse().ok()?;
+    let digits = word.trim_start_matches('-').len();
+    (number.to_string() == word && digits < 10).then_some(number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::key::{Keypair, Secp256k1};
+
+    fn test_xonly() -> XOnlyPublicKey {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_seckey_slice(&secp, &[1u8; 32]).unwrap();
+        keypair.x_only_public_key().0
+    }
+
+    #[test]
+    fn test_parse_inscription_round_trip() {
+        let script = build_inscription_script(test_xonly(), TEXT_PLAIN_UTF8, &brc20_deploy_body());
+
+        let inscription = parse_inscription(&script).expect("envelope not found");
+        assert_eq!(inscription.content_type.as_deref(), Some(TEXT_PLAIN_UTF8));
+
+        let json: serde_json::Value = serde_json::from_slice(&inscription.body).unwrap();
+        assert_eq!(json["p"], "brc-20");
+        assert_eq!(json["tick"], "ordi");
+    }
+
+    #[test]
+    fn test_inscription_custom_protocol() {
+        let script =
+            build_inscription_script_with_protocol(test_xonly(), b"xyz", "text/plain", b"hi");
+
+        // 默认只识别 "ord"
+        assert_eq!(parse_inscription(&script), None);
+
+        let inscription =
+            parse_inscription_with_protocols(&script, &[ORD_PROTOCOL_ID, b"xyz"]).unwrap();
+        assert_eq!(inscription.protocol, b"xyz");
+        assert_eq!(inscription.body, b"hi");
+
+        let ord = build_inscription_script(test_xonly(), "text/plain", b"hi");
+        assert_eq!(parse_inscription(&ord).unwrap().protocol, ORD_PROTOCOL_ID);
+        assert_eq!(parse_inscription_with_protocols(&ord, &[b"xyz"]), None);
+    }
+
+    #[test]
+    fn test_build_batch_inscription() {
+        let items = vec![
+            (TEXT_PLAIN_UTF8.to_string(), b"first".to_vec()),
+            ("image/png".to_string(), vec![0xab; 1_000]),
+            (TEXT_PLAIN_UTF8.to_string(), b"third".to_vec()),
+        ];
+        let script =
+            build_batch_inscription(test_xonly(), &items, Amount::from_sat(10_000)).unwrap();
+
+        let instructions: Vec<Instruction> = script.instructions().map(|i| i.unwrap()).collect();
+        let count = |op| {
+            instructions
+                .iter()
+                .filter(|i| **i == Instruction::Op(op))
+                .count()
+        };
+        assert_eq!(count(OP_IF), 3);
+        assert_eq!(count(OP_ENDIF), 3);
+
+        // pointer：第一个没有，之后依次为 10_000、20_000（小端序，去掉末尾 0）
+        let pointers: Vec<&[u8]> = instructions
+            .windows(2)
+            .filter_map(|w| match w {
+                [Instruction::PushBytes(tag), Instruction::PushBytes(value)]
+                    if tag.as_bytes() == INSCRIPTION_TAG_POINTER =>
+                {
+                    Some(value.as_bytes())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            pointers,
+            vec![&10_000u64.to_le_bytes()[..2], &20_000u64.to_le_bytes()[..2]]
+        );
+
+        // 第一个 envelope 仍然可以被解析
+        let first = parse_inscription(&script).unwrap();
+        assert_eq!(first.body, b"first");
+
+        assert!(build_batch_inscription(test_xonly(), &[], Amount::from_sat(10_000)).is_err());
+    }
+
+    #[test]
+    fn test_parse_inscription_multi_push_body() {
+        let script = Builder::new()
+            .push_opcode(OP_FALSE)
+            .push_opcode(OP_IF)
+            .push_slice(b"ord")
+            .push_slice([1u8])
+            .push_slice(b"text/plain")
+            .push_slice(&[] as &[u8; 0])
+            .push_slice(b"hello ")
+            .push_slice(b"world")
+            .push_opcode(OP_ENDIF)
+            .into_script();
+
+        let inscription = parse_inscription(&script).unwrap();
+        assert_eq!(inscription.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(inscription.body, b"hello world");
+    }
+
+    #[test]
+    fn test_inscription_metadata_cbor_round_trip() {
+        let metadata = json!({ "title": "provenance", "edition": 1, "tags": ["a", "b"] });
+        let script =
+            build_inscription_script_with_metadata(test_xonly(), "text/plain", b"hi", &metadata)
+                .unwrap();
+
+        let inscription = parse_inscription(&script).unwrap();
+        assert_eq!(inscription.body, b"hi");
+        assert_eq!(inscription.metadata, Some(metadata));
+
+        // 没有 metadata 字段
+        let plain = build_inscription_script(test_xonly(), "text/plain", b"hi");
+        let inscription = parse_inscription(&plain).unwrap();
+        assert_eq!(inscription.metadata, None);
+        assert_eq!(inscription.metadata_bytes, None);
+    }
+
+    #[test]
+    fn test_inscription_malformed_metadata_keeps_raw_bytes() {
+        // 0x82 表示长度为 2 的数组，但后面只有一个元素
+        let script = Builder::new()
+            .push_opcode(OP_FALSE)
+            .push_opcode(OP_IF)
+            .push_slice(b"ord")
+            .push_slice(INSCRIPTION_TAG_METADATA)
+            .push_slice([0x82, 0x01])
+            .push_slice(&[] as &[u8; 0])
+            .push_slice(b"body")
+            .push_opcode(OP_ENDIF)
+            .into_script();
+
+        let inscription = parse_inscription(&script).unwrap();
+        assert_eq!(inscription.metadata, None);
+        assert_eq!(inscription.metadata_bytes, Some(vec![0x82, 0x01]));
+        assert_eq!(inscription.body, b"body");
+    }
+
+    #[test]
+    fn test_build_inscription_chunks_large_body() {
+        let body = vec![0xabu8; 1200];
+        let script = build_inscription_script(test_xonly(), "image/png", &body);
+
+        // 1200 字节拆成 520 + 520 + 160 三个 push
+        let pushes: Vec<usize> = script
+            .instructions()
+            .filter_map(|ins| match ins.unwrap() {
+                Instruction::PushBytes(pb) if pb.len() > 100 => Some(pb.len()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(pushes, vec![520, 520, 160]);
+
+        let inscription = parse_inscription(&script).unwrap();
+        assert_eq!(inscription.content_type.as_deref(), Some("image/png"));
+        assert_eq!(inscription.body, body);
+    }
+
+    #[test]
+    fn test_data_carrier_builder() {
+        use bitcoin::opcodes::all::OP_PUSHNUM_15;
+
+        // OP_RETURN OP_15 PUSH3 010203
+        let script = DataCarrierBuilder::new()
+            .with_marker(OP_PUSHNUM_15)
+            .with_payload(&[1, 2, 3])
+            .build()
+            .unwrap();
+        assert_eq!(script.as_bytes(), [0x6a, 0x5f, 0x03, 0x01, 0x02, 0x03]);
+
+        // 文本前缀与 payload 在同一个 push 里：OP_RETURN PUSH6 "omni" 0000
+        let script = DataCarrierBuilder::new()
+            .with_prefix(b"omni")
+            .with_payload(&[0, 0])
+            .build()
+            .unwrap();
+        assert_eq!(script.to_hex_string(), "6a066f6d6e690000");
+
+        // 超过 520 字节拆成多个 push
+        let script = DataCarrierBuilder::new()
+            .with_marker(OP_PUSHNUM_13)
+            .with_payload(&[0xab; 600])
+            .build()
+            .unwrap();
+        let pushes: Vec<usize> = script
+            .instructions()
+            .filter_map(|ins| match ins.unwrap() {
+                Instruction::PushBytes(pb) => Some(pb.len()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(pushes, vec![520, 80]);
+    }
+
+    #[test]
+    fn test_parse_inscription_without_envelope() {
+        let script = Builder::new()
+            .push_slice(test_xonly().serialize())
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+
+        assert_eq!(parse_inscription(&script), None);
+    }
+
+    #[test]
+    fn test_inspect_transaction_with_runestone() {
+        use bitcoin::transaction::Version;
+        use bitcoin::{Sequence, TxIn, TxOut, absolute};
+
+        let runestone = RunesBuilder::new()
+            .with_flags(1)
+            .with_rune("TEST")
+            .with_premine(1_000)
+            .build()
+            .unwrap();
+        // 截断的 OP_PUSHDATA1：不是合法的 runestone，应被跳过
+        let broken = ScriptBuf::from_bytes(vec![0x6a, 0x5d, 0x4c]);
+
+        let mut witness = Witness::new();
+        witness.push([1u8; 64]);
+        witness.push(build_inscription_script(test_xonly(), "text/plain", b"hi").as_bytes());
+        witness.push([0xc0u8; 33]);
+
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness,
+            }],
+            output: vec![
+                TxOut {
+                    value: Amount::from_sat(10_000),
+                    script_pubkey: ScriptBuf::new_p2tr(
+                        &bitcoin::key::Secp256k1::new(),
+                        test_xonly(),
+                        None,
+                    ),
+                },
+                TxOut {
+                    value: Amount::ZERO,
+                    script_pubkey: runestone,
+                },
+                TxOut {
+                    value: Amount::ZERO,
+                    script_pubkey: broken,
+                },
+            ],
+        };
+
+        let summary = inspect_transaction(&bitcoin::consensus::encode::serialize_hex(&tx)).unwrap();
+
+        assert_eq!(summary.txid, tx.compute_txid());
+        assert_eq!(
+            summary.inputs[0].inscription_content_type.as_deref(),
+            Some("text/plain")
+        );
+        assert_eq!(summary.outputs.len(), 3);
+        assert!(summary.outputs[0].runestone.is_none());
+
+        let decoded = summary.outputs[1].runestone.as_ref().unwrap();
+        assert!(decoded.flags().etching);
+        assert_eq!(decoded.fields.get(&7), Some(&1_000));
+
+        assert!(summary.outputs[2].runestone.is_none());
+    }
+
+    #[test]
+    fn test_inspect_transaction_invalid_hex() {
+        assert!(inspect_transaction("zz").is_err());
+        assert!(inspect_transaction("0200").is_err());
+    }
+    #[test]
+    fn test_build_op_return() {
+        use bitcoin::opcodes::all::{OP_PUSHBYTES_75, OP_PUSHDATA1, OP_PUSHDATA2};
+
+        // 80 字节：OP_RETURN OP_PUSHDATA1 0x50 <data>，共 83 字节
+        let script = build_op_return(&[0xab; 80], false).unwrap();
+        assert!(script.is_op_return());
+        assert_eq!(script.len(), 83);
+        assert_eq!(script.as_bytes()[1], OP_PUSHDATA1.to_u8());
+        assert_eq!(script.as_bytes()[2], 80);
+
+        // 75 字节以内直接用 OP_PUSHBYTES_N
+        let script = build_op_return(&[0xab; 75], false).unwrap();
+        assert_eq!(script.as_bytes()[1], OP_PUSHBYTES_75.to_u8());
+        assert_eq!(script.len(), 77);
+
+        // 81 字节：默认报错，allow_nonstandard 时允许
+        let err = build_op_return(&[0xab; 81], false).unwrap_err();
+        assert!(err.to_string().contains("81 bytes"));
+        let script = build_op_return(&[0xab; 300], true).unwrap();
+        assert_eq!(script.as_bytes()[1], OP_PUSHDATA2.to_u8());
+        assert_eq!(script.len(), 1 + 3 + 300);
+
+        // 单个 push 超过 520 字节
+        assert!(build_op_return(&[0xab; 521], true).is_err());
+    }
+
+    #[test]
+    fn test_parse_asm_runestone() {
+        let script = RunesBuilder::new()
+            .with_flags(1)
+            .with_rune("ASMTEST")
+            .with_premine(1_000)
+            .build()
+            .unwrap();
+        let payload = match script.instructions().nth(2) {
+            Some(Ok(Instruction::PushBytes(bytes))) => bytes.as_bytes().to_vec(),
+            other => panic!("unexpected instruction: {:?}", other),
+        };
+        let expected = vec![
+            ScriptToken::Opcode(OP_RETURN),
+            ScriptToken::Opcode(OP_PUSHNUM_13),
+            ScriptToken::Data(payload.clone()),
+        ];
+
+        // Bitcoin Core（gettxout）的写法
+        let core_asm = format!("OP_RETURN 13 {}", hex::encode(&payload));
+        assert_eq!(parse_asm(&core_asm).unwrap(), expected);
+        // rust-bitcoin 的写法
+        assert_eq!(parse_asm(&script.to_asm_string()).unwrap(), expected);
+
+        assert_eq!(
+            parse_asm("0 -1 1234 OP_CHECKSIG").unwrap(),
+            vec![
+                ScriptToken::Opcode(bitcoin::opcodes::OP_0),
+                ScriptToken::Opcode(bitcoin::opcodes::all::OP_PUSHNUM_NEG1),
+                ScriptToken::Number(1234),
+                ScriptToken::Opcode(OP_CHECKSIG),
+            ]
+        );
+        assert!(parse_asm("OP_NOTANOPCODE").is_err());
+        assert!(parse_asm("OP_RETURN [error]").is_err());
+    }
+}
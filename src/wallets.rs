@@ -1,12 +1,18 @@
 use bip39::{Language, Mnemonic};
 use bitcoin::{
-    Address, Network, PrivateKey, XOnlyPublicKey,
-    bip32::{DerivationPath, Xpriv},
-    key::{Keypair, Secp256k1, TapTweak, TweakedKeypair},
-    taproot::TaprootSpendInfo,
+    Address, Amount, Network, PrivateKey, ScriptBuf, Transaction, TxOut, Witness, XOnlyPublicKey,
+    bip32::{ChildNumber, DerivationPath, Xpriv, Xpub},
+    key::{Keypair, Parity, Secp256k1, TapTweak, TweakedKeypair},
+    sighash::{Prevouts, SighashCache, TapSighashType},
+    taproot::{self, LeafVersion, TapLeafHash, TaprootSpendInfo},
 };
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+use zeroize::Zeroizing;
 
-use crate::env_config::ENV_CONFIGS;
+use crate::amount::sat_to_btc_string;
+use crate::bip322;
+use crate::env_config::{ENV_CONFIGS, load_mnemonic};
 
 pub struct TaprootWallet {
     /// Taproot internal key（root identity）
@@ -21,31 +27,244 @@ pub struct TaprootWallet {
     /// 默认 key-path 地址（无 script tree）
     /// 用于接受转账等
     internal_address: Address,
+
+    /// 地址所属网络（决定 HRP：bc / tb / bcrt）
+    network: Network,
+
+    /// schnorr 签名是否使用 BIP340 的 aux randomness（默认开启，抗侧信道）；
+    /// 关闭后同一 key + 同一 sighash 得到完全相同的签名，便于 golden-file 测试
+    aux_rand: bool,
+
+    /// BIP86 找零链（.../1）的扩展私钥，用于派生找零地址
+    change_xprv: Xpriv,
+
+    /// 下一个找零地址的序号
+    next_change_index: AtomicU32,
     // Tweaked key-path 地址（有 script tree）
     // tweaked_address: Address,
 }
 
 // https://rust-bitcoin.org/book/tx_taproot.html
 
+/// 不使用 aux randomness 的 BIP340 签名：nonce 只由私钥和消息决定，结果可复现
+pub fn sign_schnorr_deterministic(
+    secp: &Secp256k1<bitcoin::secp256k1::All>,
+    msg: &bitcoin::secp256k1::Message,
+    keypair: &Keypair,
+) -> bitcoin::secp256k1::schnorr::Signature {
+    secp.sign_schnorr_no_aux_rand(msg, keypair)
+}
+
+/// BIP341 推荐的 NUMS（nothing-up-my-sleeve）点 H 的 x 坐标：
+/// 没有人知道它的私钥，用作 internal key 时 key-path 不可花费
+pub const NUMS_INTERNAL_KEY: &str =
+    "50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0";
+
+/// key-path 钱包的 internal key 不能是 NUMS 点，否则发到该地址的币只能走 script-path
+pub fn ensure_not_nums(xonly: &XOnlyPublicKey) -> Result<(), Box<dyn std::error::Error>> {
+    if *xonly == NUMS_INTERNAL_KEY.parse::<XOnlyPublicKey>()? {
+        return Err("internal key is the BIP341 NUMS point; key path would be unspendable".into());
+    }
+    Ok(())
+}
+
+/// 无 script tree 的 key-path P2TR 地址，拒绝 NUMS internal key
+pub fn key_path_address(
+    secp: &Secp256k1<bitcoin::secp256k1::All>,
+    xonly: XOnlyPublicKey,
+    network: Network,
+) -> Result<Address, Box<dyn std::error::Error>> {
+    ensure_not_nums(&xonly)?;
+    Ok(Address::p2tr(secp, xonly, None, network))
+}
+
 /// 创建 Taproot 钱包
 /// 创建 Taproot 钱包（BIP86, testnet: m/86'/1'/0'/0/0）
 pub fn create_taproot_wallet(
     secp: &Secp256k1<bitcoin::secp256k1::All>,
 ) -> Result<TaprootWallet, Box<dyn std::error::Error>> {
-    // 1️⃣ 解析 mnemonic（bip39 v2 正确方式）
-    let mnemonic = Mnemonic::parse_in_normalized(Language::English, &ENV_CONFIGS.mnemonic)?;
+    let mnemonic = load_mnemonic()?;
+    create_taproot_wallet_from_mnemonic(secp, &mnemonic, ENV_CONFIGS.network)
+}
+
+/// BIP86 派生路径：主网 coin type 为 0，testnet / signet / regtest 都为 1
+fn bip86_derivation_path(network: Network) -> &'static str {
+    match network {
+        Network::Bitcoin => "m/86'/0'/0'/0/0",
+        _ => "m/86'/1'/0'/0/0",
+    }
+}
+
+/// BIP86 找零链：同一账户下 change = 1
+fn bip86_change_path(network: Network) -> &'static str {
+    match network {
+        Network::Bitcoin => "m/86'/0'/0'/1",
+        _ => "m/86'/1'/0'/1",
+    }
+}
+
+/// 找零链的 gap limit：扫描和签名时，在已派生的序号之后再多看这么多个地址
+/// （钱包重启后派生序号从 0 开始，之前用过的找零地址仍能找到）
+pub const CHANGE_GAP_LIMIT: u32 = 20;
+
+/// BIP39 支持的助记词长度：熵 128 / 160 / 192 / 224 / 256 位
+pub const MNEMONIC_WORD_COUNTS: [usize; 5] = [12, 15, 18, 21, 24];
+
+/// 生成新的英文助记词，word_count 只能是 12 / 15 / 18 / 21 / 24；
+/// 返回的 Zeroizing<String> drop 时自动清零
+pub fn generate_mnemonic(
+    word_count: usize,
+) -> Result<Zeroizing<String>, Box<dyn std::error::Error>> {
+    if !MNEMONIC_WORD_COUNTS.contains(&word_count) {
+        return Err(format!(
+            "invalid mnemonic word count {}, expected one of {:?}",
+            word_count, MNEMONIC_WORD_COUNTS
+        )
+        .into());
+    }
+    Ok(Zeroizing::new(
+        Mnemonic::generate_in(Language::English, word_count)?.to_string(),
+    ))
+}
+
+/// =====================================================
+/// 助记词校验
+/// =====================================================
+///
+/// Mnemonic::parse 只返回笼统的错误；这里逐个在词表中查找单词，
+/// 指出是哪个词拼错了（并给出最接近的词），还是所有词都对但校验和不匹配
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MnemonicError {
+    /// 单词数不是 12 / 15 / 18 / 21 / 24
+    BadWordCount(usize),
+    /// 第 position 个词（从 1 开始）不在词表中
+    UnknownWord {
+        language: Language,
+        position: usize,
+        word: String,
+        suggestion: Option<&'static str>,
+    },
+    /// 所有词都在词表中，但校验和不匹配（通常是某个词写成了另一个合法的词）
+    InvalidChecksum,
+}
+
+impl fmt::Display for MnemonicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MnemonicError::BadWordCount(count) => write!(
+                f,
+                "mnemonic has {} words, expected one of {:?}",
+                count, MNEMONIC_WORD_COUNTS
+            ),
+            MnemonicError::UnknownWord {
+                language,
+                position,
+                word,
+                suggestion,
+            } => {
+                write!(
+                    f,
+                    "word {} '{}' is not in the {:?} wordlist",
+                    position, word, language
+                )?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, "; did you mean '{}'?", suggestion)?;
+                }
+                Ok(())
+            }
+            MnemonicError::InvalidChecksum => {
+                write!(
+                    f,
+                    "mnemonic checksum is invalid; check the word order and spelling"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for MnemonicError {}
+
+/// 编辑距离（Levenshtein），用于给拼错的词找建议
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitute.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// 词表中编辑距离最近（不超过 2）的词；距离相同时取公共前缀更长的
+fn closest_word(language: Language, word: &str) -> Option<&'static str> {
+    let common_prefix = |candidate: &str| {
+        word.chars()
+            .zip(candidate.chars())
+            .take_while(|(a, b)| a == b)
+            .count()
+    };
+    language
+        .word_list()
+        .iter()
+        .map(|candidate| (edit_distance(word, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, candidate)| {
+            (*distance, std::cmp::Reverse(common_prefix(candidate)))
+        })
+        .map(|(_, candidate)| candidate)
+}
+
+pub fn validate_mnemonic(phrase: &str, language: Language) -> Result<(), MnemonicError> {
+    let words: Vec<&str> = phrase.split_whitespace().collect();
+    if !MNEMONIC_WORD_COUNTS.contains(&words.len()) {
+        return Err(MnemonicError::BadWordCount(words.len()));
+    }
+
+    for (index, word) in words.iter().enumerate() {
+        if language.find_word(word).is_none() {
+            return Err(MnemonicError::UnknownWord {
+                language,
+                position: index + 1,
+                word: word.to_string(),
+                suggestion: closest_word(language, word),
+            });
+        }
+    }
+
+    Mnemonic::parse_in_normalized(language, &words.join(" "))
+        .map(|_| ())
+        .map_err(|_| MnemonicError::InvalidChecksum)
+}
+
+/// 从给定的助记词创建 Taproot 钱包（不读取环境变量，便于测试）
+///
+/// 助记词可以是任意 BIP39 合法长度（12 / 15 / 18 / 21 / 24 个词）
+pub fn create_taproot_wallet_from_mnemonic(
+    secp: &Secp256k1<bitcoin::secp256k1::All>,
+    mnemonic: &str,
+    network: Network,
+) -> Result<TaprootWallet, Box<dyn std::error::Error>> {
+    // 1️⃣ 解析 mnemonic（bip39 v2 正确方式），先校验以便给出具体的错误
+    validate_mnemonic(mnemonic, Language::English)?;
+    let mnemonic = Mnemonic::parse_in_normalized(Language::English, mnemonic)?;
 
     // 2️⃣ mnemonic -> seed bytes (64 bytes)
     // passphrase 为空字符串
-    let seed = mnemonic.to_seed_normalized("");
+    let seed = Zeroizing::new(mnemonic.to_seed_normalized(""));
 
-    // 3️⃣ seed -> master xprv (bitcoin::bip32)
-    let master_xprv = Xpriv::new_master(Network::Testnet, &seed)?;
+    // 3️⃣ seed -> master xprv (bitcoin::bip32)，seed drop 时自动清零
+    let master_xprv = Xpriv::new_master(network, seed.as_slice())?;
 
     // 4️⃣ BIP86 路径
-    let path: DerivationPath = "m/86'/1'/0'/0/0".parse()?;
+    let path: DerivationPath = bip86_derivation_path(network).parse()?;
     // let path: DerivationPath = "m/86'/1'/0'/0/1".parse()?;
     let child_xprv = master_xprv.derive_priv(secp, &path)?;
+    let change_path: DerivationPath = bip86_change_path(network).parse()?;
+    let change_xprv = master_xprv.derive_priv(secp, &change_path)?;
 
     // 5️⃣ bitcoin 中 private_key 就是 secp256k1::SecretKey
     let secret_key = child_xprv.private_key;
@@ -58,7 +277,7 @@ pub fn create_taproot_wallet(
     // 8️⃣ Taproot 地址（使用 internal key）
     let (internal_xonly, _) = internal_keypair.x_only_public_key();
     println!("  📍 Internal XOnly: {}", internal_xonly.to_string());
-    let internal_address = Address::p2tr(secp, internal_xonly, None, Network::Testnet);
+    let internal_address = key_path_address(secp, internal_xonly, network)?;
     // let address: Address = Address::p2tr(
     //     secp,
     //     tweaked_keypair.to_keypair().x_only_public_key().0,
@@ -74,7 +293,7 @@ pub fn create_taproot_wallet(
         secp,
         tweaked_keypair.to_keypair().x_only_public_key().0,
         None,
-        Network::Testnet,
+        network,
     );
 
     println!(
@@ -91,17 +310,45 @@ pub fn create_taproot_wallet(
         tweaked_keypair,
         internal_keypair,
         internal_address,
+        network,
+        aux_rand: true,
+        change_xprv,
+        next_change_index: AtomicU32::new(0),
     })
 }
 
 impl TaprootWallet {
+    /// 设置是否使用 aux randomness 签名（false 为确定性签名）
+    pub fn with_aux_rand(mut self, aux_rand: bool) -> Self {
+        self.aux_rand = aux_rand;
+        self
+    }
+
+    pub fn uses_aux_rand(&self) -> bool {
+        self.aux_rand
+    }
+
+    /// 根据 aux_rand 选择随机或确定性的 schnorr 签名
+    fn sign_schnorr(
+        &self,
+        secp: &Secp256k1<bitcoin::secp256k1::All>,
+        msg: &bitcoin::secp256k1::Message,
+        keypair: &Keypair,
+    ) -> bitcoin::secp256k1::schnorr::Signature {
+        if self.aux_rand {
+            secp.sign_schnorr(msg, keypair)
+        } else {
+            sign_schnorr_deterministic(secp, msg, keypair)
+        }
+    }
+
     /// 用于所有 key-path 签名
     pub fn sign_keypath(
         &self,
         secp: &Secp256k1<bitcoin::secp256k1::All>,
         msg: &bitcoin::secp256k1::Message,
     ) -> bitcoin::secp256k1::schnorr::Signature {
-        secp.sign_schnorr(msg, &self.tweaked_keypair.to_keypair())
+        self.sign_schnorr(secp, msg, &self.tweaked_keypair.to_keypair())
     }
 
     /// 用于 tapscript（script-path）里显式放入的 x-only pubkey 的签名。
@@ -111,7 +358,82 @@ impl TaprootWallet {
         secp: &Secp256k1<bitcoin::secp256k1::All>,
         msg: &bitcoin::secp256k1::Message,
     ) -> bitcoin::secp256k1::schnorr::Signature {
-        secp.sign_schnorr(msg, &self.internal_keypair)
+        self.sign_schnorr(secp, msg, &self.internal_keypair)
+    }
+
+    /// =====================================================
+    /// script-path 花费：用 internal key 签名某个叶子脚本
+    /// =====================================================
+    ///
+    /// 计算 input_index 的 script-path sighash，签名后把 witness 设置为
+    /// [签名, 叶子脚本, control block]；叶子（连同 leaf_version）不在 spend_info 中时返回错误
+    #[allow(clippy::too_many_arguments)]
+    pub fn sign_script_path(
+        &self,
+        secp: &Secp256k1<bitcoin::secp256k1::All>,
+        tx: &mut Transaction,
+        input_index: usize,
+        prevouts: &[TxOut],
+        leaf_script: &ScriptBuf,
+        spend_info: &TaprootSpendInfo,
+        leaf_version: LeafVersion,
+        sighash_type: TapSighashType,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let control_block = spend_info
+            .control_block(&(leaf_script.clone(), leaf_version))
+            .ok_or("leaf script not found in taproot spend info")?;
+
+        let leaf_hash = TapLeafHash::from_script(leaf_script, leaf_version);
+        let sighash = SighashCache::new(&*tx).taproot_script_spend_signature_hash(
+            input_index,
+            &Prevouts::All(prevouts),
+            leaf_hash,
+            sighash_type,
+        )?;
+
+        // 脚本里放的是 internal key，所以用 internal key 签名；
+        // internal key 的 y 为奇数时，BIP340 签名会对私钥取负，签名仍对应脚本里的 x-only key
+        let signature = self.sign_internal(
+            secp,
+            &bitcoin::secp256k1::Message::from_digest_slice(sighash.as_ref())?,
+        );
+        let sig = taproot::Signature {
+            signature,
+            sighash_type,
+        };
+
+        let witness = &mut tx
+            .input
+            .get_mut(input_index)
+            .ok_or("input_index out of range")?
+            .witness;
+        witness.clear();
+        witness.push(sig.to_vec());
+        witness.push(leaf_script.as_bytes());
+        witness.push(control_block.serialize());
+
+        Ok(())
+    }
+
+    /// =====================================================
+    /// BIP322 simple 消息签名：证明控制 internal_address 而不花费任何币
+    /// =====================================================
+    ///
+    /// 对虚拟的 to_sign 交易做 key-path 签名（SIGHASH_DEFAULT），返回 base64 编码的 witness
+    pub fn sign_message(
+        &self,
+        secp: &Secp256k1<bitcoin::secp256k1::All>,
+        message: &str,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let to_spend =
+            bip322::to_spend_tx(&self.internal_address.script_pubkey(), message.as_bytes());
+        let to_sign = bip322::to_sign_tx(&to_spend);
+        let msg = bip322::to_sign_sighash(&to_spend, &to_sign, TapSighashType::Default)?;
+
+        let signature = self.sign_keypath(secp, &msg);
+        Ok(bip322::encode_simple_signature(&Witness::from_slice(&[
+            signature.as_ref(),
+        ])))
     }
 
     pub fn get_commit_address_with_script_tree(
@@ -123,7 +445,7 @@ impl TaprootWallet {
             secp,
             self.internal_xonly(),
             script_tree.merkle_root(),
-            Network::Testnet,
+            self.network,
         )
     }
 
@@ -131,12 +453,146 @@ impl TaprootWallet {
         self.internal_address.clone()
     }
 
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// =====================================================
+    /// 找零地址（BIP86 change 链 .../1/i）
+    /// =====================================================
+    ///
+    /// 找零不回到收款地址，有利于隐私；这些地址上的 UTXO 通过 change_descriptor 扫描，
+    /// 花费时由 sign_keypath_for 按 scriptPubKey 找到对应的 key
+    pub fn change_address(
+        &self,
+        secp: &Secp256k1<bitcoin::secp256k1::All>,
+        index: u32,
+    ) -> Result<Address, Box<dyn std::error::Error>> {
+        let (xonly, _) = self
+            .change_internal_keypair(secp, index)?
+            .x_only_public_key();
+        key_path_address(secp, xonly, self.network)
+    }
+
+    /// 派生下一个找零地址，每次调用序号加一
+    pub fn next_change_address(
+        &self,
+        secp: &Secp256k1<bitcoin::secp256k1::All>,
+    ) -> Result<Address, Box<dyn std::error::Error>> {
+        let index = self.next_change_index.fetch_add(1, Ordering::Relaxed);
+        self.change_address(secp, index)
+    }
+
+    /// 扫描 / 签名时覆盖的找零序号个数：已派生的序号加上 CHANGE_GAP_LIMIT
+    pub fn change_lookahead(&self) -> u32 {
+        self.next_change_index
+            .load(Ordering::Relaxed)
+            .saturating_add(CHANGE_GAP_LIMIT)
+    }
+
+    /// scantxoutset 用的找零链描述符 tr(<xpub>/*)，配合 range 使用
+    pub fn change_descriptor(&self, secp: &Secp256k1<bitcoin::secp256k1::All>) -> String {
+        format!("tr({}/*)", Xpub::from_priv(secp, &self.change_xprv))
+    }
+
+    fn change_internal_keypair(
+        &self,
+        secp: &Secp256k1<bitcoin::secp256k1::All>,
+        index: u32,
+    ) -> Result<Keypair, Box<dyn std::error::Error>> {
+        let child = self
+            .change_xprv
+            .derive_priv(secp, &[ChildNumber::from_normal_idx(index)?])?;
+        Ok(Keypair::from_secret_key(secp, &child.private_key))
+    }
+
+    /// key-path 签名，按 prevout 的 scriptPubKey 选择 key：收款地址，
+    /// 或 change_lookahead 范围内的找零地址；都不是时返回错误
+    pub fn sign_keypath_for(
+        &self,
+        secp: &Secp256k1<bitcoin::secp256k1::All>,
+        msg: &bitcoin::secp256k1::Message,
+        script_pubkey: &ScriptBuf,
+    ) -> Result<bitcoin::secp256k1::schnorr::Signature, Box<dyn std::error::Error>> {
+        if *script_pubkey == self.internal_address.script_pubkey() {
+            return Ok(self.sign_keypath(secp, msg));
+        }
+        for index in 0..self.change_lookahead() {
+            let keypair = self.change_internal_keypair(secp, index)?;
+            let (xonly, _) = keypair.x_only_public_key();
+            if ScriptBuf::new_p2tr(secp, xonly, None) == *script_pubkey {
+                let tweaked: TweakedKeypair = keypair.tap_tweak(secp, None);
+                return Ok(self.sign_schnorr(secp, msg, &tweaked.to_keypair()));
+            }
+        }
+        Err(format!(
+            "script {} does not belong to this wallet",
+            script_pubkey.to_hex_string()
+        )
+        .into())
+    }
+
     /// 用于构造 script tree
     pub fn internal_xonly(&self) -> bitcoin::secp256k1::XOnlyPublicKey {
         self.internal_xonly
     }
+
+    /// Taproot output key（tweak 之后的 x-only key）及其 parity：
+    /// 无 script tree 时就是地址 scriptPubKey 里的 witness program，parity 用于构造 control block
+    pub fn output_key(&self) -> (XOnlyPublicKey, Parity) {
+        self.tweaked_keypair.to_keypair().x_only_public_key()
+    }
+
+    /// =====================================================
+    /// BIP21 收款 URI：bitcoin:<address>?amount=..&label=..&message=..
+    /// =====================================================
+    ///
+    /// amount 以 BTC 为单位，最多 8 位小数并去掉末尾的 0；label / message 做百分号编码
+    pub fn payment_uri(
+        &self,
+        amount: Option<Amount>,
+        label: Option<&str>,
+        message: Option<&str>,
+    ) -> String {
+        let mut params = Vec::new();
+        if let Some(amount) = amount {
+            params.push(format!("amount={}", sat_to_btc_string(amount.to_sat())));
+        }
+        if let Some(label) = label {
+            params.push(format!("label={}", percent_encode(label)));
+        }
+        if let Some(message) = message {
+            params.push(format!("message={}", percent_encode(message)));
+        }
+
+        let mut uri = format!("bitcoin:{}", self.internal_address);
+        if !params.is_empty() {
+            uri.push('?');
+            uri.push_str(&params.join("&"));
+        }
+        uri
+    }
+}
+
+/// RFC 3986 百分号编码（保留 unreserved 字符，空格编码为 %20）
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::new();
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
 }
 
+/// BIP39 标准测试助记词，仅用于测试
+#[cfg(test)]
+pub const TEST_MNEMONIC: &str =
+    "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
 // pub fn create_taproot_wallet() -> Result<Vec<String>, Box<dyn std::error::Error>> {
 //     // Generate a default 12-word mnemonic in English
 //     // let mnemonic = generate_mnemonic(None, None).unwrap();
@@ -165,3 +621,376 @@ impl TaprootWallet {
 //         .map(|addr| addr.address.clone().to_string())
 //         .collect())
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_wallet() -> TaprootWallet {
+        let secp = Secp256k1::new();
+        create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap()
+    }
+
+    #[test]
+    fn test_deterministic_signing_is_reproducible() {
+        let secp = Secp256k1::new();
+        let msg = bitcoin::secp256k1::Message::from_digest([7u8; 32]);
+
+        let wallet = test_wallet().with_aux_rand(false);
+        assert!(!wallet.uses_aux_rand());
+        let sig1 = wallet.sign_keypath(&secp, &msg);
+        let sig2 = wallet.sign_keypath(&secp, &msg);
+        assert_eq!(sig1.serialize(), sig2.serialize());
+        assert_eq!(
+            wallet.sign_internal(&secp, &msg).serialize(),
+            wallet.sign_internal(&secp, &msg).serialize()
+        );
+
+        let output_key = wallet.tweaked_keypair.to_keypair().x_only_public_key().0;
+        secp.verify_schnorr(&sig1, &msg, &output_key).unwrap();
+
+        // 默认开启 aux randomness：每次签名都不同，但都有效
+        let wallet = test_wallet();
+        assert!(wallet.uses_aux_rand());
+        let sig3 = wallet.sign_keypath(&secp, &msg);
+        let sig4 = wallet.sign_keypath(&secp, &msg);
+        assert_ne!(sig3.serialize(), sig4.serialize());
+        secp.verify_schnorr(&sig3, &msg, &output_key).unwrap();
+    }
+
+    #[test]
+    fn test_sign_script_path_op_true_leaf() {
+        use bitcoin::opcodes::OP_TRUE;
+        use bitcoin::script::Builder;
+        use bitcoin::taproot::TaprootBuilder;
+        use bitcoin::transaction::Version;
+        use bitcoin::{OutPoint, Sequence, TxIn, Witness, absolute};
+
+        let secp = Secp256k1::new();
+        let wallet = test_wallet();
+        let leaf_script = Builder::new().push_opcode(OP_TRUE).into_script();
+        let spend_info = TaprootBuilder::new()
+            .add_leaf(0, leaf_script.clone())
+            .unwrap()
+            .finalize(&secp, wallet.internal_xonly())
+            .unwrap();
+        let commit_address = wallet.get_commit_address_with_script_tree(&secp, &spend_info);
+
+        let prevouts = [TxOut {
+            value: Amount::from_sat(10_000),
+            script_pubkey: commit_address.script_pubkey(),
+        }];
+        let mut tx = Transaction {
+            version: Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(9_000),
+                script_pubkey: wallet.get_internal_address().script_pubkey(),
+            }],
+        };
+
+        wallet
+            .sign_script_path(
+                &secp,
+                &mut tx,
+                0,
+                &prevouts,
+                &leaf_script,
+                &spend_info,
+                LeafVersion::TapScript,
+                TapSighashType::All,
+            )
+            .unwrap();
+
+        // witness = [签名(64 + sighash 字节), 叶子脚本, control block]
+        let witness = &tx.input[0].witness;
+        assert_eq!(witness.len(), 3);
+        assert_eq!(witness.nth(1).unwrap(), leaf_script.as_bytes());
+
+        let control_block = taproot::ControlBlock::decode(witness.nth(2).unwrap()).unwrap();
+        assert!(control_block.verify_taproot_commitment(
+            &secp,
+            spend_info.output_key().to_x_only_public_key(),
+            &leaf_script
+        ));
+
+        let sig = taproot::Signature::from_slice(witness.nth(0).unwrap()).unwrap();
+        assert_eq!(sig.sighash_type, TapSighashType::All);
+        let sighash = SighashCache::new(&tx)
+            .taproot_script_spend_signature_hash(
+                0,
+                &Prevouts::All(&prevouts),
+                TapLeafHash::from_script(&leaf_script, LeafVersion::TapScript),
+                TapSighashType::All,
+            )
+            .unwrap();
+        let msg = bitcoin::secp256k1::Message::from_digest_slice(sighash.as_ref()).unwrap();
+        secp.verify_schnorr(&sig.signature, &msg, &wallet.internal_xonly())
+            .unwrap();
+
+        // 不在 script tree 里的叶子
+        let other_leaf = Builder::new()
+            .push_opcode(OP_TRUE)
+            .push_opcode(OP_TRUE)
+            .into_script();
+        let err = wallet
+            .sign_script_path(
+                &secp,
+                &mut tx,
+                0,
+                &prevouts,
+                &other_leaf,
+                &spend_info,
+                LeafVersion::TapScript,
+                TapSighashType::Default,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("not found"));
+    }
+
+    #[test]
+    fn test_address_hrp_per_network() {
+        let secp = Secp256k1::new();
+        let cases = [
+            (Network::Bitcoin, "bc1p"),
+            (Network::Testnet, "tb1p"),
+            (Network::Signet, "tb1p"),
+            (Network::Regtest, "bcrt1p"),
+        ];
+
+        for (network, hrp) in cases {
+            let wallet =
+                create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, network).unwrap();
+            let address = wallet.get_internal_address().to_string();
+            assert!(address.starts_with(hrp), "{:?}: {}", network, address);
+            assert_eq!(wallet.network(), network);
+        }
+
+        // testnet / signet / regtest 共用 coin type 1，派生出同一个 key
+        let testnet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Testnet).unwrap();
+        let regtest =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Regtest).unwrap();
+        assert_eq!(testnet.internal_xonly(), regtest.internal_xonly());
+    }
+
+    #[test]
+    fn test_payment_uri_without_params() {
+        let wallet = test_wallet();
+        assert_eq!(
+            wallet.payment_uri(None, None, None),
+            format!("bitcoin:{}", wallet.get_internal_address())
+        );
+    }
+
+    #[test]
+    fn test_payment_uri_with_all_params() {
+        let wallet = test_wallet();
+        let uri = wallet.payment_uri(
+            Some(Amount::from_sat(100_000)),
+            Some("Luke Jr"),
+            Some("Donation for project xyz & co"),
+        );
+        assert_eq!(
+            uri,
+            format!(
+                "bitcoin:{}?amount=0.001&label=Luke%20Jr&message=Donation%20for%20project%20xyz%20%26%20co",
+                wallet.get_internal_address()
+            )
+        );
+    }
+
+    #[test]
+    fn test_sign_message_round_trip() {
+        let secp = Secp256k1::new();
+        let wallet = test_wallet();
+        let address = wallet.get_internal_address();
+
+        let signature = wallet.sign_message(&secp, "proof of reserves").unwrap();
+        assert!(bip322::verify_message(
+            &address,
+            "proof of reserves",
+            &signature
+        ));
+
+        // 消息被篡改后验证失败
+        assert!(!bip322::verify_message(
+            &address,
+            "proof of reserves!",
+            &signature
+        ));
+        // 其他地址不能用这个签名
+        let other = create_taproot_wallet_from_mnemonic(
+            &secp,
+            "legal winner thank year wave sausage worth useful legal winner thank yellow",
+            Network::Testnet,
+        )
+        .unwrap();
+        assert!(!bip322::verify_message(
+            &other.get_internal_address(),
+            "proof of reserves",
+            &signature
+        ));
+    }
+
+    #[test]
+    fn test_next_change_address_bip86_vector() {
+        let secp = Secp256k1::new();
+        let wallet =
+            create_taproot_wallet_from_mnemonic(&secp, TEST_MNEMONIC, Network::Bitcoin).unwrap();
+
+        // BIP86 测试向量：m/86'/0'/0'/1/0
+        assert_eq!(
+            wallet.next_change_address(&secp).unwrap().to_string(),
+            "bc1p3qkhfews2uk44qtvauqyr2ttdsw7svhkl9nkm9s9c3x4ax5h60wqwruhk7"
+        );
+        assert_eq!(
+            wallet.next_change_address(&secp).unwrap(),
+            wallet.change_address(&secp, 1).unwrap()
+        );
+        assert_eq!(wallet.change_lookahead(), 2 + CHANGE_GAP_LIMIT);
+        assert!(wallet.change_descriptor(&secp).starts_with("tr(xpub"));
+        assert!(wallet.change_descriptor(&secp).ends_with("/*)"));
+    }
+
+    #[test]
+    fn test_sign_keypath_for_change_address() {
+        let secp = Secp256k1::new();
+        let wallet = test_wallet();
+        let msg = bitcoin::secp256k1::Message::from_digest([7u8; 32]);
+
+        // 找零地址的签名对应该地址的 output key
+        let change = wallet.change_address(&secp, 3).unwrap().script_pubkey();
+        let sig = wallet.sign_keypath_for(&secp, &msg, &change).unwrap();
+        let output_key = XOnlyPublicKey::from_slice(&change.as_bytes()[2..]).unwrap();
+        assert!(secp.verify_schnorr(&sig, &msg, &output_key).is_ok());
+
+        // 收款地址用钱包的 output key
+        let receive = wallet.get_internal_address().script_pubkey();
+        let sig = wallet.sign_keypath_for(&secp, &msg, &receive).unwrap();
+        assert!(
+            secp.verify_schnorr(&sig, &msg, &wallet.output_key().0)
+                .is_ok()
+        );
+
+        // gap limit 之外和不属于钱包的脚本都找不到 key
+        let beyond = wallet
+            .change_address(&secp, CHANGE_GAP_LIMIT)
+            .unwrap()
+            .script_pubkey();
+        assert!(wallet.sign_keypath_for(&secp, &msg, &beyond).is_err());
+        wallet.next_change_address(&secp).unwrap();
+        assert!(wallet.sign_keypath_for(&secp, &msg, &beyond).is_ok());
+    }
+
+    #[test]
+    fn test_nums_internal_key_rejected() {
+        let secp = Secp256k1::new();
+        let nums: XOnlyPublicKey = NUMS_INTERNAL_KEY.parse().unwrap();
+
+        let err = key_path_address(&secp, nums, Network::Testnet).unwrap_err();
+        assert!(err.to_string().contains("NUMS"));
+
+        // 普通钱包的 internal key 正常构造
+        let wallet = test_wallet();
+        assert!(ensure_not_nums(&wallet.internal_xonly()).is_ok());
+        assert_eq!(
+            key_path_address(&secp, wallet.internal_xonly(), Network::Testnet).unwrap(),
+            wallet.get_internal_address()
+        );
+    }
+
+    #[test]
+    fn test_output_key_matches_address_witness_program() {
+        let secp = Secp256k1::new();
+        let wallet = test_wallet();
+        let (output_key, parity) = wallet.output_key();
+
+        let script_pubkey = wallet.get_internal_address().script_pubkey();
+        assert_eq!(&script_pubkey.as_bytes()[2..34], &output_key.serialize());
+
+        // 与 internal key 做 tap_tweak 的结果一致
+        let (tweaked, tweaked_parity) = wallet.internal_xonly().tap_tweak(&secp, None);
+        assert_eq!(tweaked.to_x_only_public_key(), output_key);
+        assert_eq!(tweaked_parity, parity);
+    }
+
+    #[test]
+    fn test_generate_mnemonic_word_counts() {
+        let secp = Secp256k1::new();
+        for word_count in MNEMONIC_WORD_COUNTS {
+            let mnemonic = generate_mnemonic(word_count).unwrap();
+            assert_eq!(mnemonic.split_whitespace().count(), word_count);
+            // 解析路径接受所有合法长度
+            assert!(
+                create_taproot_wallet_from_mnemonic(&secp, &mnemonic, Network::Testnet).is_ok()
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_mnemonic_rejects_invalid_word_count() {
+        let err = generate_mnemonic(13).unwrap_err();
+        assert!(err.to_string().contains("invalid mnemonic word count 13"));
+        assert!(generate_mnemonic(0).is_err());
+
+        let thirteen = format!("{} abandon", TEST_MNEMONIC);
+        assert!(
+            create_taproot_wallet_from_mnemonic(&Secp256k1::new(), &thirteen, Network::Testnet)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_validate_mnemonic_unknown_word() {
+        let mut words: Vec<&str> = TEST_MNEMONIC.split_whitespace().collect();
+        words[6] = "aple";
+        let err = validate_mnemonic(&words.join(" "), Language::English).unwrap_err();
+
+        assert_eq!(
+            err,
+            MnemonicError::UnknownWord {
+                language: Language::English,
+                position: 7,
+                word: "aple".to_string(),
+                suggestion: Some("apple"),
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "word 7 'aple' is not in the English wordlist; did you mean 'apple'?"
+        );
+
+        // 钱包构造返回同样具体的错误
+        let err = create_taproot_wallet_from_mnemonic(
+            &Secp256k1::new(),
+            &words.join(" "),
+            Network::Testnet,
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("word 7 'aple'"));
+    }
+
+    #[test]
+    fn test_validate_mnemonic_checksum_only() {
+        assert_eq!(validate_mnemonic(TEST_MNEMONIC, Language::English), Ok(()));
+
+        // 12 个 abandon：每个词都在词表中，只有校验和不对
+        let phrase = ["abandon"; 12].join(" ");
+        assert_eq!(
+            validate_mnemonic(&phrase, Language::English),
+            Err(MnemonicError::InvalidChecksum)
+        );
+        assert_eq!(
+            validate_mnemonic("abandon about", Language::English),
+            Err(MnemonicError::BadWordCount(2))
+        );
+    }
+}